@@ -0,0 +1,235 @@
+//! Background jobs.
+//!
+//! A plugin submits long-running work through the [`Jobs`] handle it gets in
+//! [`crate::NotosPlugin::attach`]. Each job runs on its own thread, reports
+//! progress through its [`JobContext`] and returns a [`PluginAction`] that the
+//! host applies on the UI thread once the job finishes. Running jobs are shown
+//! in the status bar, where the user can cancel them. When the plugin is
+//! unloaded, the host cancels its jobs and waits for them to return.
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+use crate::PluginAction;
+
+static NEXT_JOB_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Identifier of a job, unique across all plugins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct JobId(pub u64);
+
+/// What a job last reported.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct JobProgress {
+    /// Completed fraction in `0.0..=1.0`, or `None` while it is unknown.
+    pub fraction: Option<f32>,
+    pub message: String,
+}
+
+/// A running job as listed by the host.
+#[derive(Debug, Clone)]
+pub struct JobStatus {
+    pub id: JobId,
+    pub title: String,
+    pub progress: JobProgress,
+    pub cancelled: bool,
+}
+
+struct JobState {
+    id: JobId,
+    title: String,
+    progress: Mutex<JobProgress>,
+    cancelled: AtomicBool,
+    /// The action the job returned, taken by the host when the thread has finished.
+    outcome: Mutex<Option<PluginAction>>,
+    thread: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl JobState {
+    fn is_finished(&self) -> bool {
+        lock(&self.thread).as_ref().is_none_or(|t| t.is_finished())
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Passed to a running job to report progress and check for cancellation.
+pub struct JobContext {
+    state: Arc<JobState>,
+    ctx: Option<egui::Context>,
+}
+
+impl JobContext {
+    /// Report the completed fraction (`0.0..=1.0`) and a short message.
+    pub fn report(&self, fraction: f32, message: impl Into<String>) {
+        *lock(&self.state.progress) = JobProgress {
+            fraction: Some(fraction.clamp(0.0, 1.0)),
+            message: message.into(),
+        };
+        self.repaint();
+    }
+
+    /// Change the message, keeping the fraction.
+    pub fn set_message(&self, message: impl Into<String>) {
+        lock(&self.state.progress).message = message.into();
+        self.repaint();
+    }
+
+    /// Whether the job was cancelled. Check it regularly and return early when set;
+    /// the action a cancelled job returns is discarded.
+    pub fn is_cancelled(&self) -> bool {
+        self.state.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Wake the UI, e.g. after updating state the plugin draws.
+    pub fn repaint(&self) {
+        if let Some(ctx) = &self.ctx {
+            ctx.request_repaint();
+        }
+    }
+}
+
+/// The plugin's handle to a submitted job.
+#[derive(Clone)]
+pub struct JobHandle(Arc<JobState>);
+
+impl JobHandle {
+    pub fn id(&self) -> JobId {
+        self.0.id
+    }
+
+    /// Ask the job to stop. It keeps running until it checks `JobContext::is_cancelled`.
+    pub fn cancel(&self) {
+        self.0.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.0.is_finished()
+    }
+
+    pub fn progress(&self) -> JobProgress {
+        lock(&self.0.progress).clone()
+    }
+}
+
+/// A plugin's job queue, shared between the plugin and the host. Cheap to clone.
+#[derive(Clone, Default)]
+pub struct Jobs {
+    jobs: Arc<Mutex<Vec<Arc<JobState>>>>,
+    ctx: Option<egui::Context>,
+}
+
+impl Jobs {
+    /// A queue whose jobs repaint `ctx` when they report progress or finish.
+    pub fn new(ctx: Option<egui::Context>) -> Self {
+        Self {
+            jobs: Arc::default(),
+            ctx,
+        }
+    }
+
+    /// Run `work` on a background thread. Its result is applied by the host
+    /// on the UI thread, unless the job was cancelled.
+    pub fn submit(
+        &self,
+        title: impl Into<String>,
+        work: impl FnOnce(&JobContext) -> PluginAction + Send + 'static,
+    ) -> JobHandle {
+        let state = Arc::new(JobState {
+            id: JobId(NEXT_JOB_ID.fetch_add(1, Ordering::Relaxed)),
+            title: title.into(),
+            progress: Mutex::default(),
+            cancelled: AtomicBool::new(false),
+            outcome: Mutex::new(None),
+            thread: Mutex::new(None),
+        });
+        let job = JobContext {
+            state: state.clone(),
+            ctx: self.ctx.clone(),
+        };
+        *lock(&state.thread) = Some(std::thread::spawn(move || {
+            let action = work(&job);
+            *lock(&job.state.outcome) = Some(action);
+            job.repaint();
+        }));
+        lock(&self.jobs).push(state.clone());
+        if let Some(ctx) = &self.ctx {
+            ctx.request_repaint();
+        }
+        JobHandle(state)
+    }
+
+    /// Jobs that have not finished yet, oldest first.
+    pub fn running(&self) -> Vec<JobStatus> {
+        lock(&self.jobs)
+            .iter()
+            .filter(|job| !job.is_finished())
+            .map(|job| JobStatus {
+                id: job.id,
+                title: job.title.clone(),
+                progress: lock(&job.progress).clone(),
+                cancelled: job.cancelled.load(Ordering::Relaxed),
+            })
+            .collect()
+    }
+
+    /// Cancel the job with the given id, if it belongs to this queue.
+    pub fn cancel(&self, id: JobId) -> bool {
+        let jobs = lock(&self.jobs);
+        let job = jobs.iter().find(|job| job.id == id);
+        if let Some(job) = job {
+            job.cancelled.store(true, Ordering::Relaxed);
+        }
+        job.is_some()
+    }
+
+    /// Host side: remove finished jobs and return the actions of those that
+    /// were not cancelled.
+    pub fn take_finished(&self) -> Vec<PluginAction> {
+        let finished: Vec<Arc<JobState>> = {
+            let mut jobs = lock(&self.jobs);
+            let (finished, running) = jobs.drain(..).partition(|job| job.is_finished());
+            *jobs = running;
+            finished
+        };
+        let mut actions = Vec::new();
+        for job in finished {
+            if let Some(thread) = lock(&job.thread).take() {
+                if thread.join().is_err() {
+                    log::error!("Job {:?} panicked", job.title);
+                    continue;
+                }
+            }
+            let action = lock(&job.outcome).take();
+            if !job.cancelled.load(Ordering::Relaxed) {
+                actions.extend(action.filter(|a| *a != PluginAction::None));
+            }
+        }
+        actions
+    }
+
+    /// Host side: cancel every job and wait for all of them to return. Called
+    /// before the plugin is unloaded, since job threads run plugin code.
+    pub fn shutdown(&self) {
+        let jobs: Vec<Arc<JobState>> = lock(&self.jobs).drain(..).collect();
+        for job in &jobs {
+            job.cancelled.store(true, Ordering::Relaxed);
+        }
+        for job in jobs {
+            if let Some(thread) = lock(&job.thread).take() {
+                let _ = thread.join();
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for Jobs {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Jobs")
+            .field("jobs", &lock(&self.jobs).len())
+            .finish()
+    }
+}
@@ -1,6 +1,15 @@
 use egui::Context;
+use serde::{Deserialize, Serialize};
 use std::any::Any;
 
+mod jobs;
+mod settings;
+
+pub use jobs::{JobContext, JobHandle, JobId, JobProgress, JobStatus, Jobs};
+pub use settings::{
+    HostServices, PluginSettings, SettingKind, SettingSpec, SettingValue, SettingsHandle,
+};
+
 /// Actions that a plugin can request the main application to perform.
 #[derive(Debug, PartialEq, Eq)]
 pub enum PluginAction {
@@ -12,25 +21,307 @@ pub enum PluginAction {
     ReplaceSelection(String),
     /// Request the editor to draw an underline under the given character index range.
     UnderlineRegion(usize, usize),
+    /// Apply several edits to the active tab at once, recorded as a single undo step.
+    /// The cursor and selection are moved along with the surrounding text.
+    ApplyEdits(Vec<TextEdit>),
+    /// Show one of this plugin's panels (by the id declared in `NotosPlugin::panels`).
+    ShowPanel(String),
+    /// Apply a text action (`ReplaceAll`, `ReplaceSelection` or `ApplyEdits`) to the
+    /// given tab instead of the active one. Offsets refer to that tab's `TabInfo::content`.
+    InTab(TabId, Box<PluginAction>),
+    /// Switch to the given tab.
+    ActivateTab(TabId),
+}
+
+impl PluginAction {
+    /// Target this action at a specific tab, see `PluginAction::InTab`.
+    pub fn in_tab(self, tab: TabId) -> Self {
+        Self::InTab(tab, Box::new(self))
+    }
+}
+
+/// A replacement of one range of the active tab's content.
+///
+/// `start` and `end` are byte offsets into `EditorContext::content` as it was when the
+/// action was produced. Edits in one batch must not overlap; their order does not matter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+}
+
+impl TextEdit {
+    /// Replace the byte range `start..end` with `text`.
+    pub fn new(start: usize, end: usize, text: impl Into<String>) -> Self {
+        Self {
+            start,
+            end,
+            text: text.into(),
+        }
+    }
+
+    /// Insert `text` at byte offset `at`.
+    pub fn insert(at: usize, text: impl Into<String>) -> Self {
+        Self::new(at, at, text)
+    }
+
+    /// Delete the byte range `start..end`.
+    pub fn delete(start: usize, end: usize) -> Self {
+        Self::new(start, end, String::new())
+    }
+}
+
+/// A named command a plugin contributes to the command palette and keybindings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PluginCommand {
+    /// Identifier unique within the plugin. The host registers it as `<plugin id>.<id>`.
+    pub id: String,
+    /// Human readable title shown in the command palette.
+    pub title: String,
+    /// Default keybinding, e.g. `"Ctrl+Shift+J"` or the sequence `"Ctrl+K Ctrl+J"`.
+    /// Users can override it in `keybindings.json`.
+    pub keybinding: Option<String>,
+}
+
+impl PluginCommand {
+    pub fn new(id: impl Into<String>, title: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            title: title.into(),
+            keybinding: None,
+        }
+    }
+
+    /// Attach a default keybinding to the command.
+    pub fn with_keybinding(mut self, keybinding: impl Into<String>) -> Self {
+        self.keybinding = Some(keybinding.into());
+        self
+    }
+}
+
+/// Edge of the main window a plugin panel is docked to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PanelLocation {
+    Left,
+    Right,
+    Bottom,
+}
+
+/// A dockable panel a plugin contributes to the main window layout.
+///
+/// The host owns visibility, size, order and the dock edge (the user can move
+/// the panel), and persists them across sessions.
+#[derive(Debug, Clone)]
+pub struct PluginPanel {
+    /// Identifier unique within the plugin, e.g. `"preview"`.
+    pub id: String,
+    /// Title shown in the panel header and the View → Panels menu.
+    pub title: String,
+    /// Edge the panel docks to until the user moves it.
+    pub location: PanelLocation,
+    /// Initial width (side panels) or height (bottom panels) in points.
+    pub default_size: f32,
+}
+
+impl PluginPanel {
+    pub fn new(id: impl Into<String>, title: impl Into<String>, location: PanelLocation) -> Self {
+        Self {
+            id: id.into(),
+            title: title.into(),
+            location,
+            default_size: 300.0,
+        }
+    }
+
+    pub fn with_default_size(mut self, size: f32) -> Self {
+        self.default_size = size;
+        self
+    }
+}
+
+/// Side of the status bar an item is placed on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatusBarAlignment {
+    /// After the host's cursor position and character count.
+    Left,
+    /// Next to the host's encoding, line ending and zoom indicators.
+    Right,
+}
+
+/// A segment a plugin contributes to the status bar. Items are requested every
+/// frame, so they can change text and colour freely.
+#[derive(Debug, Clone)]
+pub struct StatusBarItem {
+    /// Identifier unique within the plugin, passed back to the click and popup callbacks.
+    pub id: String,
+    pub text: String,
+    pub tooltip: Option<String>,
+    pub color: Option<egui::Color32>,
+    pub alignment: StatusBarAlignment,
+    /// Clicking opens a popup drawn by `NotosPlugin::status_bar_popup_ui` instead of
+    /// calling `NotosPlugin::on_status_bar_click`.
+    pub has_popup: bool,
+}
+
+impl StatusBarItem {
+    pub fn new(id: impl Into<String>, text: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            text: text.into(),
+            tooltip: None,
+            color: None,
+            alignment: StatusBarAlignment::Left,
+            has_popup: false,
+        }
+    }
+
+    pub fn with_tooltip(mut self, tooltip: impl Into<String>) -> Self {
+        self.tooltip = Some(tooltip.into());
+        self
+    }
+
+    pub fn with_color(mut self, color: egui::Color32) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    pub fn align_right(mut self) -> Self {
+        self.alignment = StatusBarAlignment::Right;
+        self
+    }
+
+    /// Open a popup menu when clicked.
+    pub fn with_popup(mut self) -> Self {
+        self.has_popup = true;
+        self
+    }
+}
+
+/// Identifier of an open tab, stable for as long as the tab is open.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TabId(pub usize);
+
+/// Read-only view of an open tab.
+#[derive(Debug, Clone, Copy)]
+pub struct TabInfo<'a> {
+    pub id: TabId,
+    /// Title shown on the tab, usually the file name.
+    pub title: &'a str,
+    pub path: Option<&'a std::path::Path>,
+    /// Whether the tab has unsaved changes.
+    pub is_dirty: bool,
+    /// Language name derived from the file extension, see [`language_for_path`].
+    pub language: &'a str,
+    /// Encoding the file is saved with, e.g. `"UTF-8"`.
+    pub encoding: &'a str,
+    /// Line ending the file is saved with, e.g. `"Unix (LF)"`.
+    pub line_ending: &'a str,
+    pub content: &'a str,
+    /// Selection as char indices, like `EditorContext::selection`.
+    pub selection: Option<(usize, usize)>,
+}
+
+/// The open tabs of an [`EditorContext`], in tab bar order. The list is only
+/// built when a plugin first looks at it; most callbacks never do.
+pub struct TabList<'a> {
+    tabs: std::cell::OnceCell<Vec<TabInfo<'a>>>,
+    build: Box<dyn Fn() -> Vec<TabInfo<'a>> + 'a>,
+}
+
+impl<'a> TabList<'a> {
+    /// A list filled by `build` on first use.
+    pub fn new(build: impl Fn() -> Vec<TabInfo<'a>> + 'a) -> Self {
+        Self {
+            tabs: std::cell::OnceCell::new(),
+            build: Box::new(build),
+        }
+    }
+}
+
+impl<'a> std::ops::Deref for TabList<'a> {
+    type Target = [TabInfo<'a>];
+
+    fn deref(&self) -> &Self::Target {
+        self.tabs.get_or_init(|| (self.build)())
+    }
 }
 
 /// Information about the current editor state passed to plugins.
+///
+/// `content`, `selection` and `file_path` describe the active tab; `tabs` lists
+/// every open tab in tab bar order, including the active one.
 pub struct EditorContext<'a> {
     pub content: &'a str,
     pub selection: Option<(usize, usize)>,
     pub hovered_char_idx: Option<usize>,
     /// The file path of the active tab, if it has one.
     pub file_path: Option<&'a std::path::Path>,
+    /// Id of the active tab.
+    pub active_tab: Option<TabId>,
+    pub tabs: TabList<'a>,
+}
+
+impl<'a> EditorContext<'a> {
+    /// The open tab with the given id.
+    pub fn tab(&self, id: TabId) -> Option<&TabInfo<'a>> {
+        self.tabs.iter().find(|t| t.id == id)
+    }
+
+    /// The active tab.
+    pub fn active(&self) -> Option<&TabInfo<'a>> {
+        self.active_tab.and_then(|id| self.tab(id))
+    }
+}
+
+/// Language name for a file, from its extension; `"Plain Text"` when unknown.
+pub fn language_for_path(path: Option<&std::path::Path>) -> &'static str {
+    let ext = path
+        .and_then(|p| p.extension())
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match ext.as_str() {
+        "rs" => "Rust",
+        "toml" => "TOML",
+        "py" => "Python",
+        "js" | "mjs" | "cjs" => "JavaScript",
+        "ts" => "TypeScript",
+        "html" | "htm" => "HTML",
+        "css" => "CSS",
+        "json" => "JSON",
+        "md" | "markdown" => "Markdown",
+        "c" | "h" => "C",
+        "cpp" | "cc" | "cxx" | "hpp" => "C++",
+        "cs" => "C#",
+        "go" => "Go",
+        "java" => "Java",
+        "sh" | "bash" => "Shell",
+        "xml" => "XML",
+        "yaml" | "yml" => "YAML",
+        "ini" | "cfg" => "INI",
+        "rhai" => "Rhai",
+        _ => "Plain Text",
+    }
 }
 
 /// The trait that all plugins must implement.
 pub trait NotosPlugin: Any + Send + Sync {
-    /// Unique identifier for the plugin.
+    /// Unique identifier for the plugin. Ids starting with `@` are reserved
+    /// for the editor, and plugins using one are not loaded.
     fn id(&self) -> &str;
 
     /// Display name of the plugin.
     fn name(&self) -> &str;
 
+    /// Called once right after the plugin is created, before `on_load`, with the
+    /// services the host provides. Keep the handles you need.
+    fn attach(&mut self, _host: HostServices) {}
+
+    /// Settings this plugin exposes in the host's Settings window.
+    fn settings_schema(&self) -> Vec<SettingSpec> {
+        Vec::new()
+    }
+
     /// Called when the plugin is loaded.
     fn on_load(&mut self, _ctx: &Context) {}
 
@@ -54,8 +345,62 @@ pub trait NotosPlugin: Any + Send + Sync {
         PluginAction::None
     }
 
-    /// Called when the application is shutting down.
+    /// Commands this plugin contributes to the command palette. Queried once after loading.
+    fn commands(&self) -> Vec<PluginCommand> {
+        Vec::new()
+    }
+
+    /// Run one of the commands returned by `commands` against the active tab.
+    fn run_command(&mut self, _id: &str, _ed: &EditorContext) -> PluginAction {
+        PluginAction::None
+    }
+
+    /// Dockable panels this plugin contributes. Queried once after loading.
+    fn panels(&self) -> Vec<PluginPanel> {
+        Vec::new()
+    }
+
+    /// Draw the contents of one of the panels returned by `panels`. Only called while
+    /// the panel is visible.
+    fn panel_ui(&mut self, _id: &str, _ui: &mut egui::Ui, _ed: &EditorContext) -> PluginAction {
+        PluginAction::None
+    }
+
+    /// Items this plugin shows in the status bar. Called every frame while a tab is
+    /// open, so expensive values should be cached.
+    fn status_bar_items(&mut self, _ed: &EditorContext) -> Vec<StatusBarItem> {
+        Vec::new()
+    }
+
+    /// Called when a status bar item without a popup is clicked.
+    fn on_status_bar_click(&mut self, _id: &str, _ed: &EditorContext) -> PluginAction {
+        PluginAction::None
+    }
+
+    /// Draw the popup menu of a status bar item created `with_popup`.
+    fn status_bar_popup_ui(
+        &mut self,
+        _id: &str,
+        _ui: &mut egui::Ui,
+        _ed: &EditorContext,
+    ) -> PluginAction {
+        PluginAction::None
+    }
+
+    /// Called when the application is shutting down, or before the plugin is
+    /// reloaded in developer mode. Stop any threads you started and drop egui
+    /// callbacks here: none of the plugin's code may run after it is unloaded.
     fn on_unload(&mut self) {}
+
+    /// Developer mode: state to carry over into the rebuilt plugin, called just
+    /// before `on_unload` when the library is about to be reloaded.
+    fn save_state(&mut self) -> Option<String> {
+        None
+    }
+
+    /// Developer mode: receive the state the previous build returned from
+    /// `save_state`, called right after `on_load` of the rebuilt plugin.
+    fn restore_state(&mut self, _state: &str) {}
 }
 
 /// Type of the function that plugins must export to be loaded.
@@ -65,3 +410,82 @@ pub type CreatePluginFn = unsafe extern "C" fn() -> *mut std::ffi::c_void;
 /// Type of the function that plugins must export to be destroyed.
 /// Takes the pointer returned by CreatePluginFn.
 pub type DestroyPluginFn = unsafe extern "C" fn(*mut std::ffi::c_void);
+
+/// Name of the static with the id of a native plugin, see [`PluginId`].
+pub const PLUGIN_ID_SYMBOL: &[u8] = b"_PLUGIN_ID";
+
+/// The id of a native plugin, which its library exports as
+/// `#[no_mangle] pub static _PLUGIN_ID: PluginId` (see [`export_plugin!`]).
+/// The host reads it before calling `_create_plugin`, so a library overridden
+/// by another one with the same id runs none of its code. Must match
+/// `NotosPlugin::id`. Libraries without it are deduplicated once created.
+#[repr(transparent)]
+pub struct PluginId(*const std::ffi::c_char);
+
+// Only ever points to a `'static` string
+unsafe impl Sync for PluginId {}
+
+impl PluginId {
+    pub const fn new(id: &'static std::ffi::CStr) -> Self {
+        Self(id.as_ptr())
+    }
+
+    pub fn as_c_str(&self) -> &std::ffi::CStr {
+        // SAFETY: created from a `&'static CStr` in `new`
+        unsafe { std::ffi::CStr::from_ptr(self.0) }
+    }
+}
+
+/// Export the entry points of a native plugin: `_PLUGIN_ID`, `_create_plugin`
+/// (which calls `$plugin::new()`) and `_destroy_plugin`. Also defines
+/// `PLUGIN_ID`, for `NotosPlugin::id` to return, so the id is written once:
+///
+/// ```ignore
+/// impl NotosPlugin for MyPlugin {
+///     fn id(&self) -> &str {
+///         PLUGIN_ID
+///     }
+///     // ...
+/// }
+///
+/// notos_sdk::export_plugin!(MyPlugin, "my_plugin");
+/// ```
+#[macro_export]
+macro_rules! export_plugin {
+    ($plugin:ty, $id:literal) => {
+        /// Id of this plugin.
+        pub const PLUGIN_ID: &str = $id;
+
+        /// Plugin id, read by the host before the plugin is created
+        #[no_mangle]
+        pub static _PLUGIN_ID: $crate::PluginId = $crate::PluginId::new(
+            match ::std::ffi::CStr::from_bytes_with_nul(::std::concat!($id, "\0").as_bytes()) {
+                Ok(id) => id,
+                Err(_) => panic!("plugin id must not contain a NUL byte"),
+            },
+        );
+
+        /// Dynamic library entry point for creation
+        #[no_mangle]
+        #[allow(clippy::missing_safety_doc)]
+        pub unsafe extern "C" fn _create_plugin() -> *mut ::std::ffi::c_void {
+            let plugin: ::std::boxed::Box<dyn $crate::NotosPlugin> =
+                ::std::boxed::Box::new(<$plugin>::new());
+            let wrapper = ::std::boxed::Box::new(plugin);
+            ::std::boxed::Box::into_raw(wrapper) as *mut ::std::ffi::c_void
+        }
+
+        /// Dynamic library entry point for destruction
+        #[no_mangle]
+        #[allow(clippy::missing_safety_doc)]
+        pub unsafe extern "C" fn _destroy_plugin(ptr: *mut ::std::ffi::c_void) {
+            if !ptr.is_null() {
+                let wrapper: ::std::boxed::Box<::std::boxed::Box<dyn $crate::NotosPlugin>> =
+                    ::std::boxed::Box::from_raw(
+                        ptr as *mut ::std::boxed::Box<dyn $crate::NotosPlugin>,
+                    );
+                drop(wrapper);
+            }
+        }
+    };
+}
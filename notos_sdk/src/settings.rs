@@ -0,0 +1,224 @@
+//! Persistent per-plugin settings.
+//!
+//! The host keeps one key/value store per plugin id and saves it in the user's
+//! config directory. A plugin receives a [`SettingsHandle`] to its own store in
+//! [`crate::NotosPlugin::attach`] and may read or write it at any time; values
+//! it describes in [`crate::NotosPlugin::settings_schema`] are also editable in
+//! the host's Settings window.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+
+/// A stored setting value.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SettingValue {
+    Bool(bool),
+    Number(f64),
+    String(String),
+}
+
+impl SettingValue {
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            SettingValue::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            SettingValue::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            SettingValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+impl From<bool> for SettingValue {
+    fn from(value: bool) -> Self {
+        SettingValue::Bool(value)
+    }
+}
+
+impl From<f64> for SettingValue {
+    fn from(value: f64) -> Self {
+        SettingValue::Number(value)
+    }
+}
+
+impl From<String> for SettingValue {
+    fn from(value: String) -> Self {
+        SettingValue::String(value)
+    }
+}
+
+impl From<&str> for SettingValue {
+    fn from(value: &str) -> Self {
+        SettingValue::String(value.to_string())
+    }
+}
+
+/// How a setting is edited in the Settings window.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SettingKind {
+    Bool,
+    String,
+    Number {
+        min: f64,
+        max: f64,
+    },
+    /// One of a fixed list of options, stored as the option string.
+    Enum(Vec<String>),
+}
+
+/// Description of one setting a plugin exposes in the Settings window.
+#[derive(Debug, Clone)]
+pub struct SettingSpec {
+    pub key: String,
+    pub label: String,
+    pub description: Option<String>,
+    pub kind: SettingKind,
+    pub default: SettingValue,
+}
+
+impl SettingSpec {
+    pub fn bool(key: impl Into<String>, label: impl Into<String>, default: bool) -> Self {
+        Self::new(key, label, SettingKind::Bool, default.into())
+    }
+
+    pub fn string(key: impl Into<String>, label: impl Into<String>, default: &str) -> Self {
+        Self::new(key, label, SettingKind::String, default.into())
+    }
+
+    pub fn number(
+        key: impl Into<String>,
+        label: impl Into<String>,
+        default: f64,
+        min: f64,
+        max: f64,
+    ) -> Self {
+        Self::new(key, label, SettingKind::Number { min, max }, default.into())
+    }
+
+    /// A choice between `options`; `default` should be one of them.
+    pub fn choice(
+        key: impl Into<String>,
+        label: impl Into<String>,
+        options: &[&str],
+        default: &str,
+    ) -> Self {
+        let options = options.iter().map(|o| o.to_string()).collect();
+        Self::new(key, label, SettingKind::Enum(options), default.into())
+    }
+
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    fn new(
+        key: impl Into<String>,
+        label: impl Into<String>,
+        kind: SettingKind,
+        default: SettingValue,
+    ) -> Self {
+        Self {
+            key: key.into(),
+            label: label.into(),
+            description: None,
+            kind,
+            default,
+        }
+    }
+}
+
+/// The stored values of one plugin.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct PluginSettings {
+    values: BTreeMap<String, SettingValue>,
+    #[serde(skip)]
+    dirty: bool,
+}
+
+impl PluginSettings {
+    pub fn get(&self, key: &str) -> Option<&SettingValue> {
+        self.values.get(key)
+    }
+
+    pub fn set(&mut self, key: &str, value: impl Into<SettingValue>) {
+        let value = value.into();
+        if self.values.get(key) != Some(&value) {
+            self.values.insert(key.to_string(), value);
+            self.dirty = true;
+        }
+    }
+
+    /// Forget a stored value so the default applies again.
+    pub fn remove(&mut self, key: &str) {
+        if self.values.remove(key).is_some() {
+            self.dirty = true;
+        }
+    }
+
+    /// Whether values changed since the last call; used by the host to decide when to save.
+    pub fn take_dirty(&mut self) -> bool {
+        std::mem::take(&mut self.dirty)
+    }
+}
+
+/// Shared handle to a plugin's settings, cheap to clone.
+#[derive(Debug, Clone, Default)]
+pub struct SettingsHandle(Arc<RwLock<PluginSettings>>);
+
+impl SettingsHandle {
+    pub fn new(settings: PluginSettings) -> Self {
+        Self(Arc::new(RwLock::new(settings)))
+    }
+
+    /// Run `f` with read access to the settings.
+    pub fn read<R>(&self, f: impl FnOnce(&PluginSettings) -> R) -> R {
+        f(&self.0.read().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    /// Run `f` with write access to the settings.
+    pub fn write<R>(&self, f: impl FnOnce(&mut PluginSettings) -> R) -> R {
+        f(&mut self.0.write().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    pub fn get_bool(&self, key: &str, default: bool) -> bool {
+        self.read(|s| s.get(key).and_then(SettingValue::as_bool))
+            .unwrap_or(default)
+    }
+
+    pub fn get_f64(&self, key: &str, default: f64) -> f64 {
+        self.read(|s| s.get(key).and_then(SettingValue::as_f64))
+            .unwrap_or(default)
+    }
+
+    pub fn get_string(&self, key: &str, default: &str) -> String {
+        self.read(|s| s.get(key).and_then(|v| v.as_str().map(str::to_string)))
+            .unwrap_or_else(|| default.to_string())
+    }
+
+    pub fn set(&self, key: &str, value: impl Into<SettingValue>) {
+        self.write(|s| s.set(key, value));
+    }
+}
+
+/// Services the host hands to every plugin right after creating it.
+#[derive(Debug, Clone)]
+pub struct HostServices {
+    /// This plugin's persistent settings.
+    pub settings: SettingsHandle,
+    /// Queue for this plugin's background jobs.
+    pub jobs: crate::Jobs,
+}
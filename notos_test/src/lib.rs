@@ -0,0 +1,457 @@
+//! Headless test harness for Notos plugins.
+//!
+//! [`PluginHarness`] runs a plugin's UI callbacks in an egui context without a
+//! window, finds widgets by the text they draw and clicks them with simulated
+//! pointer input, and hands back the [`PluginAction`] the plugin returned. [`TestEditor`] stands in for the
+//! active tab, and for any other open tabs added with [`TestEditor::with_tab`].
+//!
+//! ```
+//! use notos_sdk::{EditorContext, NotosPlugin, PluginAction};
+//! use notos_test::{PluginHarness, Surface, TestEditor};
+//!
+//! struct Upper;
+//!
+//! impl NotosPlugin for Upper {
+//!     fn id(&self) -> &str { "upper" }
+//!     fn name(&self) -> &str { "Upper" }
+//!     fn plugins_menu_ui(&mut self, ui: &mut egui::Ui, ed: &EditorContext) -> PluginAction {
+//!         if ui.button("To Upper Case").clicked() {
+//!             return PluginAction::ReplaceAll(ed.content.to_uppercase());
+//!         }
+//!         PluginAction::None
+//!     }
+//! }
+//!
+//! let mut harness = PluginHarness::new(Upper).with_editor(TestEditor::new("hello"));
+//! let action = harness.click(Surface::PluginsMenu, "To Upper Case");
+//! assert_eq!(action, PluginAction::ReplaceAll("HELLO".into()));
+//! harness.editor.apply(&action);
+//! assert_eq!(harness.editor.content, "HELLO");
+//! ```
+
+use std::path::PathBuf;
+
+use notos_sdk::{
+    EditorContext, HostServices, Jobs, NotosPlugin, PluginAction, PluginSettings, SettingsHandle,
+    TabId, TabInfo, TabList,
+};
+
+/// Extra frames drawn after an input frame while egui keeps requesting repaints.
+const MAX_SETTLE_FRAMES: usize = 4;
+
+/// The active tab as a plugin sees it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TestEditor {
+    pub content: String,
+    /// Selection as `(anchor, cursor)` in character indices, like the host's.
+    pub selection: Option<(usize, usize)>,
+    pub hovered_char_idx: Option<usize>,
+    pub file_path: Option<PathBuf>,
+    /// Other open tabs, after the active one. The active tab has id
+    /// [`TestEditor::ACTIVE_TAB`], the others count up from there.
+    pub other_tabs: Vec<TestEditor>,
+}
+
+impl TestEditor {
+    /// Id of the active tab in [`TestEditor::context`].
+    pub const ACTIVE_TAB: TabId = TabId(1);
+
+    pub fn new(content: impl Into<String>) -> Self {
+        Self {
+            content: content.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_selection(mut self, anchor: usize, cursor: usize) -> Self {
+        self.selection = Some((anchor, cursor));
+        self
+    }
+
+    pub fn with_hovered(mut self, char_idx: usize) -> Self {
+        self.hovered_char_idx = Some(char_idx);
+        self
+    }
+
+    pub fn with_file_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.file_path = Some(path.into());
+        self
+    }
+
+    /// Open another (inactive) tab next to this one.
+    pub fn with_tab(mut self, tab: TestEditor) -> Self {
+        self.other_tabs.push(tab);
+        self
+    }
+
+    /// The context passed to plugin callbacks.
+    pub fn context(&self) -> EditorContext<'_> {
+        let tabs = TabList::new(move || {
+            std::iter::once(self)
+                .chain(&self.other_tabs)
+                .enumerate()
+                .map(|(i, tab)| tab.tab_info(TabId(Self::ACTIVE_TAB.0 + i)))
+                .collect()
+        });
+        EditorContext {
+            content: &self.content,
+            selection: self.selection,
+            hovered_char_idx: self.hovered_char_idx,
+            file_path: self.file_path.as_deref(),
+            active_tab: Some(Self::ACTIVE_TAB),
+            tabs,
+        }
+    }
+
+    fn tab_info(&self, id: TabId) -> TabInfo<'_> {
+        let path = self.file_path.as_deref();
+        TabInfo {
+            id,
+            title: path
+                .and_then(|p| p.file_name())
+                .and_then(|n| n.to_str())
+                .unwrap_or("Untitled"),
+            path,
+            is_dirty: false,
+            language: notos_sdk::language_for_path(path),
+            encoding: "UTF-8",
+            line_ending: "Unix (LF)",
+            content: &self.content,
+            selection: self.selection,
+        }
+    }
+
+    /// Apply a text action the way the host applies it to the active tab.
+    ///
+    /// Panics on edits the host would reject (out of bounds, overlapping or
+    /// not on a character boundary), so a test fails where the app would
+    /// silently drop them.
+    pub fn apply(&mut self, action: &PluginAction) {
+        match action {
+            PluginAction::ReplaceAll(text) => self.content = text.clone(),
+            PluginAction::ReplaceSelection(text) => {
+                let (a, b) = self.selection.unwrap_or((0, 0));
+                let (start, end) = (a.min(b), a.max(b));
+                let (bs, be) = (self.byte_offset(start), self.byte_offset(end));
+                self.content.replace_range(bs..be, text);
+                let caret = start + text.chars().count();
+                self.selection = Some((caret, caret));
+            }
+            PluginAction::ApplyEdits(edits) => {
+                let mut edits: Vec<_> = edits.iter().collect();
+                edits.sort_by_key(|e| e.start);
+                for pair in edits.windows(2) {
+                    assert!(
+                        pair[0].end <= pair[1].start,
+                        "overlapping edits: {:?}",
+                        pair
+                    );
+                }
+                for edit in edits.iter().rev() {
+                    assert!(
+                        edit.start <= edit.end
+                            && self.content.is_char_boundary(edit.start)
+                            && self.content.is_char_boundary(edit.end),
+                        "invalid edit range {}..{} in {:?}",
+                        edit.start,
+                        edit.end,
+                        self.content
+                    );
+                    self.content.replace_range(edit.start..edit.end, &edit.text);
+                }
+            }
+            PluginAction::InTab(id, action) => {
+                let tab = match id.0.checked_sub(Self::ACTIVE_TAB.0) {
+                    Some(0) => self,
+                    Some(i) => self
+                        .other_tabs
+                        .get_mut(i - 1)
+                        .unwrap_or_else(|| panic!("no open tab with id {:?}", id)),
+                    None => panic!("no open tab with id {:?}", id),
+                };
+                tab.apply(action);
+            }
+            // Not text changes
+            _ => {}
+        }
+    }
+
+    fn byte_offset(&self, char_idx: usize) -> usize {
+        self.content
+            .char_indices()
+            .nth(char_idx)
+            .map_or(self.content.len(), |(i, _)| i)
+    }
+}
+
+/// Which of the plugin's UI callbacks a frame draws.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Surface {
+    /// `NotosPlugin::plugins_menu_ui`
+    PluginsMenu,
+    /// `NotosPlugin::context_menu_ui`
+    ContextMenu,
+    /// `NotosPlugin::menu_ui`
+    MenuBar,
+    /// `NotosPlugin::ui`, i.e. the plugin's own windows
+    Windows,
+    /// `NotosPlugin::panel_ui` for the panel with this id
+    Panel(String),
+    /// `NotosPlugin::status_bar_popup_ui` for the item with this id
+    StatusBarPopup(String),
+}
+
+/// A piece of text drawn in the last frame: a button caption, a label, a
+/// menu entry...
+#[derive(Debug, Clone)]
+pub struct Widget {
+    pub label: String,
+    /// Where the text was drawn, in points.
+    pub rect: egui::Rect,
+}
+
+/// Drives one plugin in a headless egui context.
+pub struct PluginHarness<P: NotosPlugin> {
+    pub plugin: P,
+    pub editor: TestEditor,
+    /// Modifier keys held during every frame, e.g. Ctrl for Ctrl+hover.
+    pub modifiers: egui::Modifiers,
+    ctx: egui::Context,
+    settings: SettingsHandle,
+    jobs: Jobs,
+    widgets: Vec<Widget>,
+    copied_text: String,
+    time: f64,
+}
+
+impl<P: NotosPlugin> PluginHarness<P> {
+    /// Attach `plugin` to empty settings and call `on_load`.
+    pub fn new(plugin: P) -> Self {
+        Self::with_settings(plugin, PluginSettings::default())
+    }
+
+    /// Like [`PluginHarness::new`], with stored setting values.
+    pub fn with_settings(mut plugin: P, settings: PluginSettings) -> Self {
+        let ctx = egui::Context::default();
+        let settings = SettingsHandle::new(settings);
+        let jobs = Jobs::new(Some(ctx.clone()));
+        plugin.attach(HostServices {
+            settings: settings.clone(),
+            jobs: jobs.clone(),
+        });
+        plugin.on_load(&ctx);
+        Self {
+            plugin,
+            editor: TestEditor::default(),
+            modifiers: egui::Modifiers::NONE,
+            ctx,
+            settings,
+            jobs,
+            widgets: Vec::new(),
+            copied_text: String::new(),
+            time: 0.0,
+        }
+    }
+
+    pub fn with_editor(mut self, editor: TestEditor) -> Self {
+        self.editor = editor;
+        self
+    }
+
+    /// The plugin's settings, shared with the plugin like in the app.
+    pub fn settings(&self) -> &SettingsHandle {
+        &self.settings
+    }
+
+    pub fn ctx(&self) -> &egui::Context {
+        &self.ctx
+    }
+
+    /// The plugin's job queue, shared with the plugin like in the app.
+    pub fn jobs(&self) -> &Jobs {
+        &self.jobs
+    }
+
+    /// Wait for every submitted job to finish and return their actions, the
+    /// ones the host would apply.
+    pub fn finish_jobs(&mut self) -> Vec<PluginAction> {
+        let mut actions = Vec::new();
+        loop {
+            actions.extend(self.jobs.take_finished());
+            if self.jobs.running().is_empty() {
+                actions.extend(self.jobs.take_finished());
+                return actions;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+    }
+
+    /// Draw `surface` and return the plugin's action.
+    pub fn show(&mut self, surface: Surface) -> PluginAction {
+        self.run_frame(&surface, Vec::new())
+    }
+
+    /// Text the plugin put on the clipboard during the last `show` or `click`, if any.
+    pub fn copied_text(&self) -> Option<&str> {
+        Some(self.copied_text.as_str()).filter(|t| !t.is_empty())
+    }
+
+    /// Text drawn by the last frame, in painting order.
+    pub fn widgets(&self) -> &[Widget] {
+        &self.widgets
+    }
+
+    /// Draw `surface` and return all the text it shows.
+    pub fn labels(&mut self, surface: Surface) -> Vec<String> {
+        self.show(surface);
+        self.widgets.iter().map(|w| w.label.clone()).collect()
+    }
+
+    /// Draw `surface`, click the widget labelled `label` and return the
+    /// plugin's action from the frames of the click.
+    ///
+    /// The label matches exactly or, failing that, as the only text that
+    /// contains it, so emoji prefixes can be left out. Panics if nothing
+    /// matches. Clicking a disabled widget does nothing. Clicking a
+    /// `menu_button` opens it; click an entry of the submenu with a second call.
+    pub fn click(&mut self, surface: Surface, label: &str) -> PluginAction {
+        self.show(surface.clone());
+        let pos = self.find(label).rect.center();
+        let button = |pressed| egui::Event::PointerButton {
+            pos,
+            button: egui::PointerButton::Primary,
+            pressed,
+            modifiers: self.modifiers,
+        };
+        let (press, release) = (button(true), button(false));
+        let pressed = self.run_frame(&surface, vec![egui::Event::PointerMoved(pos), press]);
+        let released = self.run_frame(&surface, vec![release]);
+        match pressed {
+            PluginAction::None => released,
+            action => action,
+        }
+    }
+
+    /// The text `label` drawn in the last frame; see [`PluginHarness::click`].
+    pub fn find(&self, label: &str) -> Widget {
+        if let Some(widget) = self.widgets.iter().find(|w| w.label == label) {
+            return widget.clone();
+        }
+        let matches: Vec<&Widget> = self
+            .widgets
+            .iter()
+            .filter(|w| w.label.contains(label))
+            .collect();
+        match matches.as_slice() {
+            [widget] => (*widget).clone(),
+            [] => panic!(
+                "no text {:?}; found {:?}",
+                label,
+                self.widgets.iter().map(|w| &w.label).collect::<Vec<_>>()
+            ),
+            _ => panic!(
+                "{:?} is ambiguous: {:?}",
+                label,
+                matches.iter().map(|w| &w.label).collect::<Vec<_>>()
+            ),
+        }
+    }
+
+    /// Run a frame with `events`, then frames without input while egui asks
+    /// for an immediate repaint, e.g. for a window's first appearance. Returns
+    /// the first action the plugin returned.
+    fn run_frame(&mut self, surface: &Surface, events: Vec<egui::Event>) -> PluginAction {
+        self.copied_text.clear();
+        let (mut action, mut repaint) = self.run_single_frame(surface, events);
+        for _ in 0..MAX_SETTLE_FRAMES {
+            if !repaint {
+                break;
+            }
+            let (next, again) = self.run_single_frame(surface, Vec::new());
+            if action == PluginAction::None {
+                action = next;
+            }
+            repaint = again;
+        }
+        action
+    }
+
+    /// Run one frame; also returns whether egui wants the next one right away.
+    fn run_single_frame(
+        &mut self,
+        surface: &Surface,
+        events: Vec<egui::Event>,
+    ) -> (PluginAction, bool) {
+        let input = egui::RawInput {
+            screen_rect: Some(egui::Rect::from_min_size(
+                egui::Pos2::ZERO,
+                egui::vec2(1024.0, 768.0),
+            )),
+            time: Some(self.time),
+            modifiers: self.modifiers,
+            events,
+            ..Default::default()
+        };
+        self.time += 1.0 / 60.0;
+
+        let plugin = &mut self.plugin;
+        let ed = self.editor.context();
+        let mut action = PluginAction::None;
+        let output = self.ctx.run(input, |ctx| {
+            let frame_action = match surface {
+                Surface::Windows => plugin.ui(ctx, &ed),
+                surface => {
+                    egui::CentralPanel::default()
+                        .show(ctx, |ui| match surface {
+                            Surface::PluginsMenu => plugin.plugins_menu_ui(ui, &ed),
+                            Surface::ContextMenu => plugin.context_menu_ui(ui, &ed),
+                            Surface::MenuBar => {
+                                egui::menu::bar(ui, |ui| plugin.menu_ui(ui, &ed)).inner
+                            }
+                            Surface::Panel(id) => plugin.panel_ui(id, ui, &ed),
+                            Surface::StatusBarPopup(id) => plugin.status_bar_popup_ui(id, ui, &ed),
+                            Surface::Windows => unreachable!(),
+                        })
+                        .inner
+                }
+            };
+            // Input is only delivered to the first pass of a multi-pass frame
+            if action == PluginAction::None {
+                action = frame_action;
+            }
+        });
+
+        if !output.platform_output.copied_text.is_empty() {
+            self.copied_text = output.platform_output.copied_text;
+        }
+        self.widgets.clear();
+        for clipped in &output.shapes {
+            collect_text(&clipped.shape, clipped.clip_rect, &mut self.widgets);
+        }
+        let repaint = output
+            .viewport_output
+            .get(&egui::ViewportId::ROOT)
+            .is_some_and(|v| v.repaint_delay.is_zero());
+        (action, repaint)
+    }
+}
+
+fn collect_text(shape: &egui::Shape, clip_rect: egui::Rect, out: &mut Vec<Widget>) {
+    match shape {
+        egui::Shape::Vec(shapes) => {
+            for shape in shapes {
+                collect_text(shape, clip_rect, out);
+            }
+        }
+        egui::Shape::Text(text) => {
+            let rect = text.galley.rect.translate(text.pos.to_vec2());
+            if !text.galley.is_empty() && clip_rect.intersects(rect) {
+                out.push(Widget {
+                    label: text.galley.text().to_string(),
+                    rect: rect.intersect(clip_rect),
+                });
+            }
+        }
+        _ => {}
+    }
+}
@@ -13,7 +13,7 @@ impl AboutPlugin {
 
 impl NotosPlugin for AboutPlugin {
     fn id(&self) -> &str {
-        "notos_about"
+        PLUGIN_ID
     }
 
     fn name(&self) -> &str {
@@ -48,21 +48,31 @@ impl NotosPlugin for AboutPlugin {
     }
 }
 
-/// Dynamic library entry point for creation
-#[no_mangle]
-#[allow(clippy::missing_safety_doc)]
-pub unsafe extern "C" fn _create_plugin() -> *mut std::ffi::c_void {
-    let plugin: Box<dyn NotosPlugin> = Box::new(AboutPlugin::new());
-    let wrapper = Box::new(plugin);
-    Box::into_raw(wrapper) as *mut std::ffi::c_void
-}
+notos_sdk::export_plugin!(AboutPlugin, "notos_about");
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notos_test::{PluginHarness, Surface};
+
+    #[test]
+    fn help_menu_opens_about_window() {
+        let mut harness = PluginHarness::new(AboutPlugin::new());
+        assert!(harness.labels(Surface::Windows).is_empty());
 
-/// Dynamic library entry point for destruction
-#[no_mangle]
-#[allow(clippy::missing_safety_doc)]
-pub unsafe extern "C" fn _destroy_plugin(ptr: *mut std::ffi::c_void) {
-    if !ptr.is_null() {
-        let wrapper: Box<Box<dyn NotosPlugin>> = Box::from_raw(ptr as *mut Box<dyn NotosPlugin>);
-        drop(wrapper);
+        harness.click(Surface::MenuBar, "Help");
+        assert_eq!(
+            harness.click(Surface::MenuBar, "About Notos"),
+            PluginAction::None
+        );
+        let labels = harness.labels(Surface::Windows);
+        assert!(
+            labels.iter().any(|l| l == "Notos Text Editor"),
+            "{:?}",
+            labels
+        );
+        assert!(labels
+            .iter()
+            .any(|l| l == &format!("Version {}", env!("CARGO_PKG_VERSION"))));
     }
 }
@@ -1,5 +1,5 @@
 use base64::{engine::general_purpose, Engine as _};
-use notos_sdk::{EditorContext, NotosPlugin, PluginAction};
+use notos_sdk::{EditorContext, NotosPlugin, PluginAction, PluginCommand};
 
 struct Base64Plugin;
 
@@ -22,55 +22,81 @@ impl Base64Plugin {
             }
         }
     }
+
+    /// Encode the selection if there is one, otherwise the entire file.
+    fn encode_action(&self, ed: &EditorContext) -> PluginAction {
+        if let Some((s, e)) = ed.selection {
+            let (start, end) = (s.min(e), s.max(e));
+            if start != end {
+                return match ed.content.get(start..end) {
+                    Some(selected_text) => {
+                        PluginAction::ReplaceSelection(self.encode(selected_text))
+                    }
+                    None => PluginAction::None,
+                };
+            }
+        }
+        if ed.content.is_empty() {
+            return PluginAction::None;
+        }
+        PluginAction::ReplaceAll(self.encode(ed.content))
+    }
+
+    /// Decode the selection if there is one, otherwise the entire file.
+    fn decode_action(&self, ed: &EditorContext) -> PluginAction {
+        if let Some((s, e)) = ed.selection {
+            let (start, end) = (s.min(e), s.max(e));
+            if start != end {
+                return match ed.content.get(start..end).and_then(|t| self.decode(t)) {
+                    Some(decoded) => PluginAction::ReplaceSelection(decoded),
+                    None => PluginAction::None,
+                };
+            }
+        }
+        if ed.content.is_empty() {
+            return PluginAction::None;
+        }
+        match self.decode(ed.content) {
+            Some(decoded) => PluginAction::ReplaceAll(decoded),
+            None => PluginAction::None,
+        }
+    }
 }
 
 impl NotosPlugin for Base64Plugin {
     fn id(&self) -> &str {
-        "notos_base64"
+        PLUGIN_ID
     }
 
     fn name(&self) -> &str {
         "Base64 Tool"
     }
 
+    fn commands(&self) -> Vec<PluginCommand> {
+        vec![
+            PluginCommand::new("encode", "Base64 Encode"),
+            PluginCommand::new("decode", "Base64 Decode"),
+        ]
+    }
+
+    fn run_command(&mut self, id: &str, ed: &EditorContext) -> PluginAction {
+        match id {
+            "encode" => self.encode_action(ed),
+            "decode" => self.decode_action(ed),
+            _ => PluginAction::None,
+        }
+    }
+
     fn plugins_menu_ui(&mut self, ui: &mut egui::Ui, ed: &EditorContext) -> PluginAction {
         let mut action = PluginAction::None;
 
         if ui.button("🔐 Base64 Encode").clicked() {
-            if let Some((s, e)) = ed.selection {
-                let (start, end) = (s.min(e), s.max(e));
-                if start != end {
-                    if let Some(selected_text) = ed.content.get(start..end) {
-                        action = PluginAction::ReplaceSelection(self.encode(selected_text));
-                    }
-                } else if !ed.content.is_empty() {
-                    action = PluginAction::ReplaceAll(self.encode(ed.content));
-                }
-            } else if !ed.content.is_empty() {
-                action = PluginAction::ReplaceAll(self.encode(ed.content));
-            }
+            action = self.encode_action(ed);
             ui.close_menu();
         }
 
         if ui.button("🔓 Base64 Decode").clicked() {
-            if let Some((s, e)) = ed.selection {
-                let (start, end) = (s.min(e), s.max(e));
-                if start != end {
-                    if let Some(selected_text) = ed.content.get(start..end) {
-                        if let Some(decoded) = self.decode(selected_text) {
-                            action = PluginAction::ReplaceSelection(decoded);
-                        }
-                    }
-                } else if !ed.content.is_empty() {
-                    if let Some(decoded) = self.decode(ed.content) {
-                        action = PluginAction::ReplaceAll(decoded);
-                    }
-                }
-            } else if !ed.content.is_empty() {
-                if let Some(decoded) = self.decode(ed.content) {
-                    action = PluginAction::ReplaceAll(decoded);
-                }
-            }
+            action = self.decode_action(ed);
             ui.close_menu();
         }
 
@@ -106,21 +132,59 @@ impl NotosPlugin for Base64Plugin {
     }
 }
 
-/// Dynamic library entry point for creation
-#[no_mangle]
-#[allow(clippy::missing_safety_doc)]
-pub unsafe extern "C" fn _create_plugin() -> *mut std::ffi::c_void {
-    let plugin: Box<dyn NotosPlugin> = Box::new(Base64Plugin::new());
-    let wrapper = Box::new(plugin);
-    Box::into_raw(wrapper) as *mut std::ffi::c_void
-}
+notos_sdk::export_plugin!(Base64Plugin, "notos_base64");
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notos_test::{PluginHarness, Surface, TestEditor};
+
+    #[test]
+    fn menu_encodes_whole_document() {
+        let mut harness =
+            PluginHarness::new(Base64Plugin::new()).with_editor(TestEditor::new("hello"));
+        let action = harness.click(Surface::PluginsMenu, "Base64 Encode");
+        assert_eq!(action, PluginAction::ReplaceAll("aGVsbG8=".into()));
+    }
+
+    #[test]
+    fn menu_decodes_selection() {
+        let mut harness = PluginHarness::new(Base64Plugin::new())
+            .with_editor(TestEditor::new("x aGk= y").with_selection(2, 6));
+        let action = harness.click(Surface::PluginsMenu, "Base64 Decode");
+        assert_eq!(action, PluginAction::ReplaceSelection("hi".into()));
+        harness.editor.apply(&action);
+        assert_eq!(harness.editor.content, "x hi y");
+    }
+
+    #[test]
+    fn invalid_base64_is_left_alone() {
+        let mut harness =
+            PluginHarness::new(Base64Plugin::new()).with_editor(TestEditor::new("not base64!"));
+        assert_eq!(
+            harness.click(Surface::PluginsMenu, "Base64 Decode"),
+            PluginAction::None
+        );
+    }
+
+    #[test]
+    fn context_menu_needs_a_selection() {
+        let mut harness =
+            PluginHarness::new(Base64Plugin::new()).with_editor(TestEditor::new("hello"));
+        assert!(harness.labels(Surface::ContextMenu).is_empty());
+
+        harness.editor.selection = Some((0, 5));
+        let action = harness.click(Surface::ContextMenu, "Encode Selection");
+        assert_eq!(action, PluginAction::ReplaceSelection("aGVsbG8=".into()));
+    }
 
-/// Dynamic library entry point for destruction
-#[no_mangle]
-#[allow(clippy::missing_safety_doc)]
-pub unsafe extern "C" fn _destroy_plugin(ptr: *mut std::ffi::c_void) {
-    if !ptr.is_null() {
-        let wrapper: Box<Box<dyn NotosPlugin>> = Box::from_raw(ptr as *mut Box<dyn NotosPlugin>);
-        drop(wrapper);
+    #[test]
+    fn commands_match_menu() {
+        let mut plugin = Base64Plugin::new();
+        let editor = TestEditor::new("hello");
+        assert_eq!(
+            plugin.run_command("encode", &editor.context()),
+            PluginAction::ReplaceAll("aGVsbG8=".into())
+        );
     }
 }
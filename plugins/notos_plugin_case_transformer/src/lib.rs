@@ -1,8 +1,22 @@
-use notos_sdk::{EditorContext, NotosPlugin, PluginAction};
+use notos_sdk::{EditorContext, NotosPlugin, PluginAction, PluginCommand};
 use heck::{ToShoutySnakeCase, ToSnakeCase, ToKebabCase, ToPascalCase, ToLowerCamelCase, ToTitleCase};
 
 struct CaseTransformerPlugin;
 
+type Transform = fn(&str) -> String;
+
+/// Transforms exposed as commands: (command id, title, transform).
+const TRANSFORMS: &[(&str, &str, Transform)] = &[
+    ("upper", "UPPERCASE", |s| s.to_uppercase()),
+    ("lower", "lowercase", |s| s.to_lowercase()),
+    ("title", "Title Case", |s| s.to_title_case()),
+    ("pascal", "PascalCase", |s| s.to_pascal_case()),
+    ("camel", "camelCase", |s| s.to_lower_camel_case()),
+    ("snake", "snake_case", |s| s.to_snake_case()),
+    ("screaming_snake", "SCREAMING_SNAKE_CASE", |s| s.to_shouty_snake_case()),
+    ("kebab", "kebab-case", |s| s.to_kebab_case()),
+];
+
 impl CaseTransformerPlugin {
     fn new() -> Self {
         Self
@@ -11,7 +25,7 @@ impl CaseTransformerPlugin {
 
 impl NotosPlugin for CaseTransformerPlugin {
     fn id(&self) -> &str {
-        "notos_case_transformer"
+        PLUGIN_ID
     }
 
     fn name(&self) -> &str {
@@ -22,6 +36,20 @@ impl NotosPlugin for CaseTransformerPlugin {
         self.draw_case_menu(ui, ed)
     }
 
+    fn commands(&self) -> Vec<PluginCommand> {
+        TRANSFORMS
+            .iter()
+            .map(|(id, title, _)| PluginCommand::new(*id, format!("Transform to {}", title)))
+            .collect()
+    }
+
+    fn run_command(&mut self, id: &str, ed: &EditorContext) -> PluginAction {
+        match TRANSFORMS.iter().find(|(t, _, _)| *t == id) {
+            Some((_, _, transform)) => self.apply_transform(ed, transform),
+            None => PluginAction::None,
+        }
+    }
+
     fn plugins_menu_ui(&mut self, ui: &mut egui::Ui, ed: &EditorContext) -> PluginAction {
         self.draw_case_menu(ui, ed)
     }
@@ -90,17 +118,53 @@ impl CaseTransformerPlugin {
     }
 }
 
-#[no_mangle]
-pub unsafe extern "C" fn _create_plugin() -> *mut std::ffi::c_void {
-    let plugin: Box<dyn NotosPlugin> = Box::new(CaseTransformerPlugin::new());
-    let wrapper = Box::new(plugin);
-    Box::into_raw(wrapper) as *mut std::ffi::c_void
-}
+notos_sdk::export_plugin!(CaseTransformerPlugin, "notos_case_transformer");
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notos_test::{PluginHarness, Surface, TestEditor};
+
+    #[test]
+    fn submenu_transforms_selection() {
+        let mut harness = PluginHarness::new(CaseTransformerPlugin::new())
+            .with_editor(TestEditor::new("let my value = 1;").with_selection(4, 12));
+        assert_eq!(
+            harness.click(Surface::PluginsMenu, "Transform Case"),
+            PluginAction::None
+        );
+        let action = harness.click(Surface::PluginsMenu, "SCREAMING_SNAKE_CASE");
+        assert_eq!(action, PluginAction::ReplaceSelection("MY_VALUE".into()));
+        harness.editor.apply(&action);
+        assert_eq!(harness.editor.content, "let MY_VALUE = 1;");
+    }
 
-#[no_mangle]
-pub unsafe extern "C" fn _destroy_plugin(ptr: *mut std::ffi::c_void) {
-    if !ptr.is_null() {
-        let wrapper: Box<Box<dyn NotosPlugin>> = Box::from_raw(ptr as *mut Box<dyn NotosPlugin>);
-        drop(wrapper);
+    #[test]
+    fn context_menu_transforms_whole_document_without_selection() {
+        let mut harness = PluginHarness::new(CaseTransformerPlugin::new())
+            .with_editor(TestEditor::new("hello world"));
+        harness.click(Surface::ContextMenu, "Transform Case");
+        assert_eq!(
+            harness.click(Surface::ContextMenu, "camelCase"),
+            PluginAction::ReplaceAll("helloWorld".into())
+        );
+    }
+
+    #[test]
+    fn every_command_has_a_menu_entry() {
+        let mut harness = PluginHarness::new(CaseTransformerPlugin::new())
+            .with_editor(TestEditor::new("some text"));
+        harness.click(Surface::PluginsMenu, "Transform Case");
+        let labels = harness.labels(Surface::PluginsMenu);
+        for command in harness.plugin.commands() {
+            let title = command.title.trim_start_matches("Transform to ");
+            assert!(labels.iter().any(|l| l == title), "no menu entry for {}", title);
+            let via_menu = harness.click(Surface::PluginsMenu, title);
+            let via_command = harness
+                .plugin
+                .run_command(&command.id, &harness.editor.context());
+            assert_eq!(via_menu, via_command, "{}", command.id);
+            harness.click(Surface::PluginsMenu, "Transform Case");
+        }
     }
 }
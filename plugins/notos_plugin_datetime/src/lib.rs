@@ -1,4 +1,4 @@
-use notos_sdk::{EditorContext, NotosPlugin, PluginAction};
+use notos_sdk::{EditorContext, NotosPlugin, PluginAction, PluginCommand};
 use chrono::Local;
 
 struct DateTimePlugin;
@@ -11,13 +11,36 @@ impl DateTimePlugin {
 
 impl NotosPlugin for DateTimePlugin {
     fn id(&self) -> &str {
-        "notos_datetime"
+        PLUGIN_ID
     }
 
     fn name(&self) -> &str {
         "Customizable Date/Time"
     }
 
+    fn commands(&self) -> Vec<PluginCommand> {
+        vec![
+            PluginCommand::new("insert_standard", "Insert Date/Time (Standard)"),
+            PluginCommand::new("insert_iso8601", "Insert Date/Time (ISO 8601)"),
+            PluginCommand::new("insert_date", "Insert Date Only"),
+            PluginCommand::new("insert_time", "Insert Time Only"),
+            PluginCommand::new("insert_unix", "Insert Unix Timestamp"),
+        ]
+    }
+
+    fn run_command(&mut self, id: &str, _ed: &EditorContext) -> PluginAction {
+        let now = Local::now();
+        let text = match id {
+            "insert_standard" => now.format("%Y-%m-%d %H:%M:%S").to_string(),
+            "insert_iso8601" => now.to_rfc3339(),
+            "insert_date" => now.format("%Y-%m-%d").to_string(),
+            "insert_time" => now.format("%H:%M:%S").to_string(),
+            "insert_unix" => now.timestamp().to_string(),
+            _ => return PluginAction::None,
+        };
+        PluginAction::ReplaceSelection(text)
+    }
+
     fn plugins_menu_ui(&mut self, ui: &mut egui::Ui, _ed: &EditorContext) -> PluginAction {
         let mut action = PluginAction::None;
 
@@ -56,17 +79,44 @@ impl NotosPlugin for DateTimePlugin {
     }
 }
 
-#[no_mangle]
-pub unsafe extern "C" fn _create_plugin() -> *mut std::ffi::c_void {
-    let plugin: Box<dyn NotosPlugin> = Box::new(DateTimePlugin::new());
-    let wrapper = Box::new(plugin);
-    Box::into_raw(wrapper) as *mut std::ffi::c_void
-}
+notos_sdk::export_plugin!(DateTimePlugin, "notos_datetime");
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notos_test::{PluginHarness, Surface, TestEditor};
+
+    #[test]
+    fn unix_timestamp_is_inserted_at_selection() {
+        let mut harness = PluginHarness::new(DateTimePlugin::new())
+            .with_editor(TestEditor::new("at: ").with_selection(4, 4));
+        harness.click(Surface::PluginsMenu, "Insert Date/Time");
+        let before = Local::now().timestamp();
+        let action = harness.click(Surface::PluginsMenu, "Unix Timestamp");
+        let PluginAction::ReplaceSelection(text) = &action else {
+            panic!("unexpected action {:?}", action);
+        };
+        let stamp: i64 = text.parse().expect("numeric timestamp");
+        assert!((before - 1..=before + 5).contains(&stamp));
+        harness.editor.apply(&action);
+        assert_eq!(harness.editor.content, format!("at: {}", stamp));
+    }
+
+    #[test]
+    fn date_only_has_no_time() {
+        let mut harness = PluginHarness::new(DateTimePlugin::new());
+        harness.click(Surface::PluginsMenu, "Insert Date/Time");
+        let action = harness.click(Surface::PluginsMenu, "Date Only");
+        let PluginAction::ReplaceSelection(text) = action else {
+            panic!("unexpected action {:?}", action);
+        };
+        assert!(chrono::NaiveDate::parse_from_str(&text, "%Y-%m-%d").is_ok(), "{}", text);
+    }
 
-#[no_mangle]
-pub unsafe extern "C" fn _destroy_plugin(ptr: *mut std::ffi::c_void) {
-    if !ptr.is_null() {
-        let wrapper: Box<Box<dyn NotosPlugin>> = Box::from_raw(ptr as *mut Box<dyn NotosPlugin>);
-        drop(wrapper);
+    #[test]
+    fn unknown_command_does_nothing() {
+        let mut plugin = DateTimePlugin::new();
+        let editor = TestEditor::new("");
+        assert_eq!(plugin.run_command("nope", &editor.context()), PluginAction::None);
     }
 }
@@ -0,0 +1,178 @@
+use notos_sdk::{EditorContext, NotosPlugin, PluginAction, StatusBarItem};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// How often `HEAD` is re-read for the same directory (branches change outside the editor).
+const HEAD_REFRESH: Duration = Duration::from_secs(2);
+
+#[derive(Clone)]
+struct RepoInfo {
+    root: PathBuf,
+    /// Branch name, or `None` for a detached `HEAD`.
+    branch: Option<String>,
+    /// Abbreviated commit when detached.
+    commit: String,
+}
+
+impl RepoInfo {
+    fn label(&self) -> String {
+        match &self.branch {
+            Some(branch) => branch.clone(),
+            None => format!("({})", self.commit),
+        }
+    }
+}
+
+/// Find the repository containing `dir` and read its current `HEAD`.
+fn find_repo(dir: &Path) -> Option<RepoInfo> {
+    for root in dir.ancestors() {
+        let dot_git = root.join(".git");
+        let git_dir = if dot_git.is_dir() {
+            dot_git
+        } else if dot_git.is_file() {
+            // Worktrees and submodules use a `gitdir: <path>` pointer file
+            let pointer = std::fs::read_to_string(&dot_git).ok()?;
+            let target = pointer.trim().strip_prefix("gitdir:")?.trim();
+            root.join(target)
+        } else {
+            continue;
+        };
+
+        let head = std::fs::read_to_string(git_dir.join("HEAD")).ok()?;
+        let head = head.trim();
+        let (branch, commit) = match head.strip_prefix("ref:") {
+            Some(reference) => {
+                let reference = reference.trim();
+                let name = reference.strip_prefix("refs/heads/").unwrap_or(reference);
+                (Some(name.to_string()), String::new())
+            }
+            None => (None, head.chars().take(7).collect()),
+        };
+        return Some(RepoInfo {
+            root: root.to_path_buf(),
+            branch,
+            commit,
+        });
+    }
+    None
+}
+
+struct GitBranchPlugin {
+    /// Last lookup as (directory, time, result).
+    cache: Option<(PathBuf, Instant, Option<RepoInfo>)>,
+}
+
+impl GitBranchPlugin {
+    fn new() -> Self {
+        Self { cache: None }
+    }
+
+    fn repo_for(&mut self, ed: &EditorContext) -> Option<RepoInfo> {
+        let dir = ed.file_path?.parent()?;
+        match &self.cache {
+            Some((cached_dir, at, info)) if cached_dir == dir && at.elapsed() <= HEAD_REFRESH => {
+                info.clone()
+            }
+            _ => {
+                let info = find_repo(dir);
+                self.cache = Some((dir.to_path_buf(), Instant::now(), info.clone()));
+                info
+            }
+        }
+    }
+}
+
+impl NotosPlugin for GitBranchPlugin {
+    fn id(&self) -> &str {
+        PLUGIN_ID
+    }
+
+    fn name(&self) -> &str {
+        "Git Branch"
+    }
+
+    fn status_bar_items(&mut self, ed: &EditorContext) -> Vec<StatusBarItem> {
+        match self.repo_for(ed) {
+            Some(repo) => vec![StatusBarItem::new("branch", format!("⎇ {}", repo.label()))
+                .with_tooltip(format!("Git repository: {}", repo.root.display()))
+                .with_popup()],
+            None => Vec::new(),
+        }
+    }
+
+    fn status_bar_popup_ui(
+        &mut self,
+        _id: &str,
+        ui: &mut egui::Ui,
+        ed: &EditorContext,
+    ) -> PluginAction {
+        let Some(repo) = self.repo_for(ed) else {
+            ui.label("Not in a git repository");
+            return PluginAction::None;
+        };
+        ui.label(match &repo.branch {
+            Some(branch) => format!("Branch: {}", branch),
+            None => format!("Detached HEAD at {}", repo.commit),
+        });
+        ui.label(format!("Repository: {}", repo.root.display()));
+        ui.separator();
+        if ui.button("📋 Copy Branch Name").clicked() {
+            ui.output_mut(|o| o.copied_text = repo.label());
+            ui.close_menu();
+        }
+        PluginAction::None
+    }
+}
+
+notos_sdk::export_plugin!(GitBranchPlugin, "notos_git_branch");
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notos_test::{PluginHarness, Surface, TestEditor};
+
+    /// A fake repository with `head` as the content of `.git/HEAD`.
+    fn repo(name: &str, head: &str) -> PathBuf {
+        let root =
+            std::env::temp_dir().join(format!("notos_git_branch_{}_{}", name, std::process::id()));
+        std::fs::create_dir_all(root.join(".git")).unwrap();
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(root.join(".git").join("HEAD"), head).unwrap();
+        root
+    }
+
+    #[test]
+    fn shows_branch_and_copies_it() {
+        let root = repo("branch", "ref: refs/heads/feature/x\n");
+        let mut harness = PluginHarness::new(GitBranchPlugin::new())
+            .with_editor(TestEditor::new("").with_file_path(root.join("src").join("main.rs")));
+        let items = harness.plugin.status_bar_items(&harness.editor.context());
+        assert_eq!(items[0].text, "⎇ feature/x");
+
+        harness.click(Surface::StatusBarPopup("branch".into()), "Copy Branch Name");
+        assert_eq!(harness.copied_text(), Some("feature/x"));
+        std::fs::remove_dir_all(root).ok();
+    }
+
+    #[test]
+    fn detached_head_shows_commit() {
+        let root = repo("detached", "0123456789abcdef\n");
+        let mut harness = PluginHarness::new(GitBranchPlugin::new())
+            .with_editor(TestEditor::new("").with_file_path(root.join("notes.txt")));
+        let labels = harness.labels(Surface::StatusBarPopup("branch".into()));
+        assert!(
+            labels.iter().any(|l| l == "Detached HEAD at 0123456"),
+            "{:?}",
+            labels
+        );
+        std::fs::remove_dir_all(root).ok();
+    }
+
+    #[test]
+    fn untitled_tab_has_no_item() {
+        let mut plugin = GitBranchPlugin::new();
+        assert!(plugin
+            .status_bar_items(&TestEditor::new("").context())
+            .is_empty());
+    }
+}
@@ -1,21 +1,28 @@
-use egui::Context;
-use notos_sdk::{EditorContext, NotosPlugin, PluginAction};
-use std::path::PathBuf;
+use notos_sdk::{
+    EditorContext, HostServices, JobContext, JobHandle, Jobs, NotosPlugin, PanelLocation, PluginAction,
+    PluginPanel,
+};
+use std::io::Read;
+use std::path::{Path, PathBuf};
 
 /// Maximum file size we'll load into the hex viewer (128 MB).
 const MAX_HEX_FILE_SIZE: u64 = 128 * 1024 * 1024;
 
+/// Files are read in chunks of this size, reporting progress after each.
+const READ_CHUNK_SIZE: usize = 1024 * 1024;
+
 /// Number of bytes displayed per row in the hex view.
 const BYTES_PER_ROW: usize = 16;
 
 /// Number of rows to display per page.
 const ROWS_PER_PAGE: usize = 256;
 
+/// Id of the dockable panel the viewer is shown in.
+const HEX_PANEL: &str = "hex";
+
 use std::sync::{Arc, RwLock};
 
 struct HexViewerState {
-    /// Whether the hex viewer window is open.
-    open: bool,
     /// The raw bytes loaded from the file.
     data: Vec<u8>,
     /// The path of the file currently loaded into the viewer.
@@ -36,12 +43,15 @@ struct HexViewerState {
 
 struct HexViewerPlugin {
     state: Arc<RwLock<HexViewerState>>,
+    jobs: Jobs,
+    /// Background file loader, cancelled when another file is loaded.
+    loader: Option<JobHandle>,
 }
 
 impl HexViewerState {
 
-    /// Load raw bytes from the given file path asynchronously.
-    fn load_file_async(state: Arc<RwLock<Self>>, path: PathBuf, ctx: egui::Context) {
+    /// Load raw bytes from the given file path in a background job.
+    fn load_file_async(state: Arc<RwLock<Self>>, path: PathBuf, jobs: &Jobs) -> JobHandle {
         {
             let mut s = state.write().unwrap();
             s.data.clear();
@@ -51,56 +61,55 @@ impl HexViewerState {
             s.loaded_path = Some(path.clone());
         }
 
-        std::thread::spawn(move || {
-            match std::fs::metadata(&path) {
-                Ok(meta) => {
-                    let file_size = meta.len();
-                    if file_size > MAX_HEX_FILE_SIZE {
-                        if let Ok(mut s) = state.write() {
-                            if s.loaded_path.as_ref() == Some(&path) {
-                                s.status = format!(
-                                    "File too large ({:.2} MB). Max is {} MB.",
-                                    file_size as f64 / (1024.0 * 1024.0),
-                                    MAX_HEX_FILE_SIZE / (1024 * 1024)
-                                );
-                            }
-                        }
-                        ctx.request_repaint();
-                        return;
-                    }
-
-                    match std::fs::read(&path) {
-                        Ok(bytes) => {
-                            if let Ok(mut s) = state.write() {
-                                if s.loaded_path.as_ref() == Some(&path) {
-                                    s.status = format!(
-                                        "Loaded {} bytes from {}",
-                                        bytes.len(),
-                                        path.display()
-                                    );
-                                    s.data = bytes;
-                                }
-                            }
-                        }
-                        Err(e) => {
-                            if let Ok(mut s) = state.write() {
-                                if s.loaded_path.as_ref() == Some(&path) {
-                                    s.status = format!("Failed to read file: {}", e);
-                                }
-                            }
-                        }
-                    }
-                }
-                Err(e) => {
-                    if let Ok(mut s) = state.write() {
-                        if s.loaded_path.as_ref() == Some(&path) {
-                            s.status = format!("Failed to get file metadata: {}", e);
+        let title = format!("Hex: {}", path.file_name().unwrap_or_default().to_string_lossy());
+        jobs.submit(title, move |job| {
+            let result = Self::read_file(&path, job);
+            if let Ok(mut s) = state.write() {
+                if s.loaded_path.as_ref() == Some(&path) {
+                    match result {
+                        Ok(Some(bytes)) => {
+                            s.status = format!("Loaded {} bytes from {}", bytes.len(), path.display());
+                            s.data = bytes;
                         }
+                        Ok(None) => s.status = "Loading cancelled.".to_string(),
+                        Err(e) => s.status = e,
                     }
                 }
             }
-            ctx.request_repaint();
-        });
+            PluginAction::None
+        })
+    }
+
+    /// Read a file in chunks, reporting progress. `Ok(None)` when the job was cancelled.
+    fn read_file(path: &Path, job: &JobContext) -> Result<Option<Vec<u8>>, String> {
+        let file_size = std::fs::metadata(path)
+            .map_err(|e| format!("Failed to get file metadata: {}", e))?
+            .len();
+        if file_size > MAX_HEX_FILE_SIZE {
+            return Err(format!(
+                "File too large ({:.2} MB). Max is {} MB.",
+                file_size as f64 / (1024.0 * 1024.0),
+                MAX_HEX_FILE_SIZE / (1024 * 1024)
+            ));
+        }
+
+        let mut file = std::fs::File::open(path).map_err(|e| format!("Failed to read file: {}", e))?;
+        let mut data = Vec::with_capacity(file_size as usize);
+        let mut chunk = vec![0; READ_CHUNK_SIZE];
+        loop {
+            if job.is_cancelled() {
+                return Ok(None);
+            }
+            let n = file.read(&mut chunk).map_err(|e| format!("Failed to read file: {}", e))?;
+            if n == 0 {
+                return Ok(Some(data));
+            }
+            data.extend_from_slice(&chunk[..n]);
+            job.report(
+                data.len() as f32 / file_size.max(1) as f32,
+                format!("{} / {} KB", data.len() / 1024, file_size / 1024),
+            );
+        }
     }
 
     /// Decode a hex search string into bytes.
@@ -148,36 +157,6 @@ impl HexViewerState {
         }
     }
 
-    /// Render the hex viewer window as a native OS window.
-    fn show_window(state: Arc<RwLock<Self>>, ctx: &Context) {
-        let is_open = state.read().unwrap().open;
-        if !is_open {
-            return;
-        }
-
-        ctx.show_viewport_deferred(
-            egui::ViewportId::from_hash_of("hex_viewer_window"),
-            egui::ViewportBuilder::default()
-                .with_title("🔢 HEX Viewer")
-                .with_inner_size([685.0, 550.0])
-                .with_min_inner_size([600.0, 300.0]),
-            move |ctx, class| {
-                if class == egui::ViewportClass::Deferred {
-                    let mut s = state.write().unwrap();
-                    let mut open = s.open;
-                    egui::CentralPanel::default().show(ctx, |ui| {
-                        s.render_ui(ui);
-                    });
-
-                    if ctx.input(|i| i.viewport().close_requested()) {
-                        open = false;
-                    }
-                    s.open = open;
-                }
-            },
-        );
-    }
-
     /// The actual UI content of the hex viewer.
     fn render_ui(&mut self, ui: &mut egui::Ui) {
         let is_dark = ui.visuals().dark_mode;
@@ -490,7 +469,6 @@ impl HexViewerPlugin {
     fn new() -> Self {
         Self {
             state: Arc::new(RwLock::new(HexViewerState {
-                open: false,
                 data: Vec::new(),
                 loaded_path: None,
                 status: String::new(),
@@ -500,19 +478,53 @@ impl HexViewerPlugin {
                 search_match_len: 0,
                 goto_offset_str: String::new(),
             })),
+            jobs: Jobs::default(),
+            loader: None,
+        }
+    }
+
+    fn load(&mut self, path: PathBuf) {
+        if let Some(previous) = self.loader.take() {
+            previous.cancel();
         }
+        self.loader = Some(HexViewerState::load_file_async(self.state.clone(), path, &self.jobs));
     }
 }
 
 impl NotosPlugin for HexViewerPlugin {
     fn id(&self) -> &str {
-        "notos_hex_viewer"
+        PLUGIN_ID
     }
 
     fn name(&self) -> &str {
         "HEX Viewer"
     }
 
+    fn attach(&mut self, host: HostServices) {
+        self.jobs = host.jobs;
+    }
+
+    fn on_unload(&mut self) {
+        // The host waits for the job to return before unloading
+        if let Some(loader) = self.loader.take() {
+            loader.cancel();
+        }
+    }
+
+    fn save_state(&mut self) -> Option<String> {
+        let s = self.state.read().unwrap();
+        let path = s.loaded_path.as_ref()?;
+        Some(format!("{}\n{}", s.current_offset, path.display()))
+    }
+
+    fn restore_state(&mut self, state: &str) {
+        let Some((offset, path)) = state.split_once('\n') else {
+            return;
+        };
+        self.load(PathBuf::from(path));
+        self.state.write().unwrap().current_offset = offset.parse().unwrap_or(0);
+    }
+
     fn plugins_menu_ui(&mut self, ui: &mut egui::Ui, ed: &EditorContext) -> PluginAction {
         if ui.button("🔢 Hex Viewer").clicked() {
             let mut needs_reload = false;
@@ -533,7 +545,6 @@ impl NotosPlugin for HexViewerPlugin {
 
             {
                 let mut s = self.state.write().unwrap();
-                s.open = true;
                 if ed.file_path.is_none() {
                     // No file path — show hex of the current content's raw UTF-8 bytes
                     s.data = ed.content.as_bytes().to_vec();
@@ -546,39 +557,99 @@ impl NotosPlugin for HexViewerPlugin {
 
             if needs_reload {
                 if let Some(path) = target_path {
-                    let ctx = ui.ctx().clone();
-                    HexViewerState::load_file_async(self.state.clone(), path, ctx);
+                    self.load(path);
                 }
             }
 
             ui.close_menu();
+            return PluginAction::ShowPanel(HEX_PANEL.to_string());
         }
 
         PluginAction::None
     }
 
-    fn ui(&mut self, ctx: &Context, _ed: &EditorContext) -> PluginAction {
-        let state_clone = Arc::clone(&self.state);
-        HexViewerState::show_window(state_clone, ctx);
+    fn panels(&self) -> Vec<PluginPanel> {
+        vec![PluginPanel::new(HEX_PANEL, "🔢 HEX Viewer", PanelLocation::Bottom)
+            .with_default_size(320.0)]
+    }
+
+    fn panel_ui(&mut self, _id: &str, ui: &mut egui::Ui, _ed: &EditorContext) -> PluginAction {
+        self.state.write().unwrap().render_ui(ui);
         PluginAction::None
     }
 }
 
-/// Dynamic library entry point for creation
-#[no_mangle]
-#[allow(clippy::missing_safety_doc)]
-pub unsafe extern "C" fn _create_plugin() -> *mut std::ffi::c_void {
-    let plugin: Box<dyn NotosPlugin> = Box::new(HexViewerPlugin::new());
-    let wrapper = Box::new(plugin);
-    Box::into_raw(wrapper) as *mut std::ffi::c_void
-}
+notos_sdk::export_plugin!(HexViewerPlugin, "notos_hex_viewer");
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notos_test::{PluginHarness, Surface, TestEditor};
+
+    fn panel() -> Surface {
+        Surface::Panel(HEX_PANEL.into())
+    }
+
+    #[test]
+    fn shows_untitled_content_in_panel() {
+        let mut harness =
+            PluginHarness::new(HexViewerPlugin::new()).with_editor(TestEditor::new("hello"));
+        assert_eq!(
+            harness.click(Surface::PluginsMenu, "Hex Viewer"),
+            PluginAction::ShowPanel(HEX_PANEL.into())
+        );
+        let labels = harness.labels(panel());
+        assert!(
+            labels.iter().any(|l| l == "Showing in-memory content (5 bytes)"),
+            "{:?}",
+            labels
+        );
+    }
+
+    #[test]
+    fn search_finds_bytes() {
+        let mut harness = PluginHarness::new(HexViewerPlugin::new())
+            .with_editor(TestEditor::new("hello world"));
+        harness.click(Surface::PluginsMenu, "Hex Viewer");
+
+        harness.plugin.state.write().unwrap().search_hex = "6F 20".into();
+        harness.click(panel(), "Find");
+        assert_eq!(
+            harness.plugin.state.read().unwrap().status,
+            "Found match at offset 0x00000004 (4 bytes)"
+        );
+
+        harness.plugin.state.write().unwrap().search_hex = "zz".into();
+        harness.click(panel(), "Find");
+        assert!(harness
+            .plugin
+            .state
+            .read()
+            .unwrap()
+            .status
+            .starts_with("Invalid hex string"));
+    }
+
+    #[test]
+    fn loads_file_in_background_job() {
+        let path = std::env::temp_dir().join(format!("notos_hex_test_{}.bin", std::process::id()));
+        std::fs::write(&path, [0xDE, 0xAD, 0xBE, 0xEF]).unwrap();
+        let mut harness = PluginHarness::new(HexViewerPlugin::new())
+            .with_editor(TestEditor::new("").with_file_path(&path));
+        harness.click(Surface::PluginsMenu, "Hex Viewer");
+
+        assert_eq!(harness.finish_jobs(), Vec::new());
+        let state = harness.plugin.state.read().unwrap();
+        assert_eq!(state.data, [0xDE, 0xAD, 0xBE, 0xEF]);
+        assert!(state.status.starts_with("Loaded 4 bytes"), "{}", state.status);
+        drop(state);
+        std::fs::remove_file(path).unwrap();
+    }
 
-/// Dynamic library entry point for destruction
-#[no_mangle]
-#[allow(clippy::missing_safety_doc)]
-pub unsafe extern "C" fn _destroy_plugin(ptr: *mut std::ffi::c_void) {
-    if !ptr.is_null() {
-        let wrapper: Box<Box<dyn NotosPlugin>> = Box::from_raw(ptr as *mut Box<dyn NotosPlugin>);
-        drop(wrapper);
+    #[test]
+    fn empty_viewer_has_no_navigation() {
+        let mut harness = PluginHarness::new(HexViewerPlugin::new());
+        let labels = harness.labels(panel());
+        assert!(!labels.iter().any(|l| l.contains("Next")), "{:?}", labels);
     }
 }
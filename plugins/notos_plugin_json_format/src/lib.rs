@@ -1,17 +1,89 @@
-use notos_sdk::{EditorContext, NotosPlugin, PluginAction};
+use notos_sdk::{
+    EditorContext, HostServices, NotosPlugin, PluginAction, PluginCommand, SettingSpec,
+    SettingsHandle, StatusBarItem,
+};
+use serde::Serialize;
+use std::time::{Duration, Instant};
 
-struct JsonFormatPlugin;
+/// Indentation choices offered in the Settings window.
+const INDENT_OPTIONS: [&str; 3] = ["2 spaces", "4 spaces", "Tab"];
+
+/// How long a validity result is reused while the document looks unchanged.
+const VALIDITY_REFRESH: Duration = Duration::from_millis(500);
+
+/// Result of the last validity check, keyed by the content buffer it ran on.
+struct Validity {
+    key: (usize, usize),
+    checked_at: Instant,
+    error: Option<String>,
+}
+
+struct JsonFormatPlugin {
+    settings: SettingsHandle,
+    validity: Option<Validity>,
+}
 
 impl JsonFormatPlugin {
     fn new() -> Self {
-        Self
+        Self {
+            settings: SettingsHandle::default(),
+            validity: None,
+        }
+    }
+
+    fn indent(&self) -> &'static [u8] {
+        match self
+            .settings
+            .get_string("indent", INDENT_OPTIONS[0])
+            .as_str()
+        {
+            "4 spaces" => b"    ",
+            "Tab" => b"\t",
+            _ => b"  ",
+        }
+    }
+
+    /// Whether the status bar indicator applies to the active tab.
+    fn is_json_document(ed: &EditorContext) -> bool {
+        match ed.file_path {
+            Some(path) => path
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("json")),
+            None => {
+                let trimmed = ed.content.trim_start();
+                trimmed.starts_with('{') || trimmed.starts_with('[')
+            }
+        }
+    }
+
+    /// Parse the document, reusing the previous result for an unchanged buffer.
+    fn validity_error(&mut self, content: &str) -> Option<&str> {
+        let key = (content.as_ptr() as usize, content.len());
+        let fresh = self
+            .validity
+            .as_ref()
+            .is_some_and(|v| v.key == key && v.checked_at.elapsed() <= VALIDITY_REFRESH);
+        if !fresh {
+            let error = serde_json::from_str::<serde::de::IgnoredAny>(content)
+                .err()
+                .map(|e| e.to_string());
+            self.validity = Some(Validity {
+                key,
+                checked_at: Instant::now(),
+                error,
+            });
+        }
+        self.validity.as_ref().and_then(|v| v.error.as_deref())
     }
 
     fn format_json(&self, text: &str) -> Option<String> {
         match serde_json::from_str::<serde_json::Value>(text) {
             Ok(value) => {
-                if let Ok(pretty) = serde_json::to_string_pretty(&value) {
-                    return Some(pretty);
+                let mut out = Vec::new();
+                let formatter = serde_json::ser::PrettyFormatter::with_indent(self.indent());
+                let mut serializer = serde_json::Serializer::with_formatter(&mut out, formatter);
+                if value.serialize(&mut serializer).is_ok() {
+                    return String::from_utf8(out).ok();
                 }
             }
             Err(e) => {
@@ -20,42 +92,104 @@ impl JsonFormatPlugin {
         }
         None
     }
+
+    /// Format the selection if there is one, otherwise the entire file.
+    fn format_action(&self, ed: &EditorContext) -> PluginAction {
+        if let Some((s, e)) = ed.selection {
+            let (start, end) = (s.min(e), s.max(e));
+            if start != end {
+                if let Some(selected_text) = ed.content.get(start..end) {
+                    if let Some(formatted) = self.format_json(selected_text) {
+                        return PluginAction::ReplaceSelection(formatted);
+                    }
+                }
+                return PluginAction::None;
+            }
+        }
+        match self.format_json(ed.content) {
+            Some(formatted) => PluginAction::ReplaceAll(formatted),
+            None => PluginAction::None,
+        }
+    }
 }
 
 impl NotosPlugin for JsonFormatPlugin {
     fn id(&self) -> &str {
-        "notos_json_format"
+        PLUGIN_ID
     }
 
     fn name(&self) -> &str {
         "JSON Formatter"
     }
 
+    fn attach(&mut self, host: HostServices) {
+        self.settings = host.settings;
+    }
+
+    fn settings_schema(&self) -> Vec<SettingSpec> {
+        vec![SettingSpec::choice(
+            "indent",
+            "Indentation",
+            &INDENT_OPTIONS,
+            INDENT_OPTIONS[0],
+        )]
+    }
+
+    fn commands(&self) -> Vec<PluginCommand> {
+        vec![PluginCommand::new("format", "Format JSON").with_keybinding("Ctrl+Alt+J")]
+    }
+
+    fn run_command(&mut self, id: &str, ed: &EditorContext) -> PluginAction {
+        match id {
+            "format" => self.format_action(ed),
+            _ => PluginAction::None,
+        }
+    }
+
+    fn status_bar_items(&mut self, ed: &EditorContext) -> Vec<StatusBarItem> {
+        if !Self::is_json_document(ed) {
+            return Vec::new();
+        }
+        let item = match self.validity_error(ed.content) {
+            None => StatusBarItem::new("validity", "✔ JSON")
+                .with_color(egui::Color32::from_rgb(80, 160, 80))
+                .with_tooltip("Valid JSON"),
+            Some(error) => StatusBarItem::new("validity", "✖ JSON")
+                .with_color(egui::Color32::from_rgb(210, 80, 80))
+                .with_tooltip(format!("Invalid JSON: {}", error)),
+        };
+        vec![item.align_right().with_popup()]
+    }
+
+    fn status_bar_popup_ui(
+        &mut self,
+        _id: &str,
+        ui: &mut egui::Ui,
+        ed: &EditorContext,
+    ) -> PluginAction {
+        let error = self.validity_error(ed.content).map(str::to_string);
+        match &error {
+            None => ui.label("Document is valid JSON."),
+            Some(e) => ui.label(format!("Invalid JSON: {}", e)),
+        };
+        ui.separator();
+        if ui
+            .add_enabled(error.is_none(), egui::Button::new("✨ Format Document"))
+            .clicked()
+        {
+            ui.close_menu();
+            if let Some(formatted) = self.format_json(ed.content) {
+                return PluginAction::ReplaceAll(formatted);
+            }
+        }
+        PluginAction::None
+    }
+
     fn plugins_menu_ui(&mut self, ui: &mut egui::Ui, ed: &EditorContext) -> PluginAction {
         let mut action = PluginAction::None;
 
         if ui.button("✨ Format JSON").clicked() {
-            if let Some((s, e)) = ed.selection {
-                let (start, end) = (s.min(e), s.max(e));
-                if start != end {
-                    // Format selection
-                    if let Some(selected_text) = ed.content.get(start..end) {
-                        if let Some(formatted) = self.format_json(selected_text) {
-                            action = PluginAction::ReplaceSelection(formatted);
-                        }
-                    }
-                } else {
-                    // Format entire file
-                    if let Some(formatted) = self.format_json(ed.content) {
-                        action = PluginAction::ReplaceAll(formatted);
-                    }
-                }
-            } else {
-                // Format entire file
-                if let Some(formatted) = self.format_json(ed.content) {
-                    action = PluginAction::ReplaceAll(formatted);
-                }
-            }
+            action = self.format_action(ed);
             ui.close_menu();
         }
 
@@ -63,21 +197,78 @@ impl NotosPlugin for JsonFormatPlugin {
     }
 }
 
-/// Dynamic library entry point for creation
-#[no_mangle]
-#[allow(clippy::missing_safety_doc)]
-pub unsafe extern "C" fn _create_plugin() -> *mut std::ffi::c_void {
-    let plugin: Box<dyn NotosPlugin> = Box::new(JsonFormatPlugin::new());
-    let wrapper = Box::new(plugin);
-    Box::into_raw(wrapper) as *mut std::ffi::c_void
-}
+notos_sdk::export_plugin!(JsonFormatPlugin, "notos_json_format");
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notos_sdk::PluginSettings;
+    use notos_test::{PluginHarness, Surface, TestEditor};
+
+    #[test]
+    fn formats_with_configured_indent() {
+        let mut harness = PluginHarness::new(JsonFormatPlugin::new())
+            .with_editor(TestEditor::new(r#"{"a":[1]}"#));
+        assert_eq!(
+            harness.click(Surface::PluginsMenu, "Format JSON"),
+            PluginAction::ReplaceAll("{\n  \"a\": [\n    1\n  ]\n}".into())
+        );
+
+        harness.settings().set("indent", "Tab");
+        assert_eq!(
+            harness.click(Surface::PluginsMenu, "Format JSON"),
+            PluginAction::ReplaceAll("{\n\t\"a\": [\n\t\t1\n\t]\n}".into())
+        );
+    }
+
+    #[test]
+    fn stored_setting_applies_from_the_start() {
+        let mut settings = PluginSettings::default();
+        settings.set("indent", "4 spaces");
+        let mut harness = PluginHarness::with_settings(JsonFormatPlugin::new(), settings)
+            .with_editor(TestEditor::new("[1]"));
+        assert_eq!(
+            harness.click(Surface::PluginsMenu, "Format JSON"),
+            PluginAction::ReplaceAll("[\n    1\n]".into())
+        );
+    }
+
+    #[test]
+    fn invalid_selection_is_left_alone() {
+        let mut harness = PluginHarness::new(JsonFormatPlugin::new())
+            .with_editor(TestEditor::new(r#"{"a": oops}"#).with_selection(0, 6));
+        assert_eq!(
+            harness.click(Surface::PluginsMenu, "Format JSON"),
+            PluginAction::None
+        );
+    }
+
+    #[test]
+    fn status_bar_popup_formats_only_valid_json() {
+        let mut harness =
+            PluginHarness::new(JsonFormatPlugin::new()).with_editor(TestEditor::new("{\"a\":"));
+        let popup = Surface::StatusBarPopup("validity".into());
+        assert_eq!(
+            harness.click(popup.clone(), "Format Document"),
+            PluginAction::None
+        );
+
+        harness.editor.content = "[ ]".into();
+        assert_eq!(
+            harness.click(popup, "Format Document"),
+            PluginAction::ReplaceAll("[]".into())
+        );
+    }
+
+    #[test]
+    fn status_item_shows_validity_for_json_files() {
+        let mut plugin = JsonFormatPlugin::new();
+        let editor = TestEditor::new("not json").with_file_path("data.json");
+        let items = plugin.status_bar_items(&editor.context());
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].text, "✖ JSON");
 
-/// Dynamic library entry point for destruction
-#[no_mangle]
-#[allow(clippy::missing_safety_doc)]
-pub unsafe extern "C" fn _destroy_plugin(ptr: *mut std::ffi::c_void) {
-    if !ptr.is_null() {
-        let wrapper: Box<Box<dyn NotosPlugin>> = Box::from_raw(ptr as *mut Box<dyn NotosPlugin>);
-        drop(wrapper);
+        let editor = TestEditor::new("not json").with_file_path("notes.txt");
+        assert!(plugin.status_bar_items(&editor.context()).is_empty());
     }
 }
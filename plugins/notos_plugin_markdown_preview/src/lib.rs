@@ -1,31 +1,24 @@
 // Markdown Preview Plugin
-use notos_sdk::{EditorContext, NotosPlugin, PluginAction};
+use notos_sdk::{EditorContext, NotosPlugin, PanelLocation, PluginAction, PluginPanel};
 use egui_commonmark::{CommonMarkCache, CommonMarkViewer};
-use std::sync::{Arc, RwLock};
 
-struct MarkdownState {
-    open: bool,
-    cache: CommonMarkCache,
-}
+const PREVIEW_PANEL: &str = "preview";
 
 struct MarkdownPreviewPlugin {
-    state: Arc<RwLock<MarkdownState>>,
+    cache: CommonMarkCache,
 }
 
 impl MarkdownPreviewPlugin {
     fn new() -> Self {
         Self {
-            state: Arc::new(RwLock::new(MarkdownState {
-                open: false,
-                cache: CommonMarkCache::default(),
-            })),
+            cache: CommonMarkCache::default(),
         }
     }
 }
 
 impl NotosPlugin for MarkdownPreviewPlugin {
     fn id(&self) -> &str {
-        "notos_markdown_preview"
+        PLUGIN_ID
     }
 
     fn name(&self) -> &str {
@@ -33,58 +26,51 @@ impl NotosPlugin for MarkdownPreviewPlugin {
     }
 
     fn plugins_menu_ui(&mut self, ui: &mut egui::Ui, _ed: &EditorContext) -> PluginAction {
-        let mut s = self.state.write().unwrap();
-        if ui.checkbox(&mut s.open, " Show Markdown Preview").changed() {
-            // Repaint to show/hide
-            ui.ctx().request_repaint();
+        if ui.button("📝 Show Markdown Preview").clicked() {
+            ui.close_menu();
+            return PluginAction::ShowPanel(PREVIEW_PANEL.to_string());
         }
         PluginAction::None
     }
 
-    fn ui(&mut self, ctx: &egui::Context, ed: &EditorContext) -> PluginAction {
-        let is_open = self.state.read().unwrap().open;
-        if is_open {
-            let state_clone = Arc::clone(&self.state);
-            let content = ed.content.to_string();
-            
-            ctx.show_viewport_deferred(
-                egui::ViewportId::from_hash_of("markdown_preview_window"),
-                egui::ViewportBuilder::default()
-                    .with_title("📝 Markdown Preview")
-                    .with_inner_size([600.0, 800.0]),
-                move |ctx, _class| {
-                    let mut s = state_clone.write().unwrap();
-                    
-                    egui::CentralPanel::default().show(ctx, |ui| {
-                        egui::ScrollArea::vertical()
-                            .id_salt("md_preview_scroll")
-                            .show(ui, |ui| {
-                                CommonMarkViewer::new()
-                                    .show(ui, &mut s.cache, &content);
-                            });
-                    });
+    fn panels(&self) -> Vec<PluginPanel> {
+        vec![PluginPanel::new(PREVIEW_PANEL, "📝 Markdown Preview", PanelLocation::Right)
+            .with_default_size(400.0)]
+    }
 
-                    if ctx.input(|i| i.viewport().close_requested()) {
-                        s.open = false;
-                    }
-                },
-            );
-        }
+    fn panel_ui(&mut self, _id: &str, ui: &mut egui::Ui, ed: &EditorContext) -> PluginAction {
+        egui::ScrollArea::vertical()
+            .id_salt("md_preview_scroll")
+            .auto_shrink([false, false])
+            .show(ui, |ui| {
+                CommonMarkViewer::new().show(ui, &mut self.cache, ed.content);
+            });
         PluginAction::None
     }
 }
 
-#[no_mangle]
-pub unsafe extern "C" fn _create_plugin() -> *mut std::ffi::c_void {
-    let plugin: Box<dyn NotosPlugin> = Box::new(MarkdownPreviewPlugin::new());
-    let wrapper = Box::new(plugin);
-    Box::into_raw(wrapper) as *mut std::ffi::c_void
-}
+notos_sdk::export_plugin!(MarkdownPreviewPlugin, "notos_markdown_preview");
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notos_test::{PluginHarness, Surface, TestEditor};
+
+    #[test]
+    fn menu_shows_preview_panel() {
+        let mut harness = PluginHarness::new(MarkdownPreviewPlugin::new());
+        assert_eq!(
+            harness.click(Surface::PluginsMenu, "Show Markdown Preview"),
+            PluginAction::ShowPanel(PREVIEW_PANEL.into())
+        );
+        assert!(harness.plugin.panels().iter().any(|p| p.id == PREVIEW_PANEL));
+    }
 
-#[no_mangle]
-pub unsafe extern "C" fn _destroy_plugin(ptr: *mut std::ffi::c_void) {
-    if !ptr.is_null() {
-        let wrapper: Box<Box<dyn NotosPlugin>> = Box::from_raw(ptr as *mut Box<dyn NotosPlugin>);
-        drop(wrapper);
+    #[test]
+    fn panel_renders_document() {
+        let mut harness = PluginHarness::new(MarkdownPreviewPlugin::new())
+            .with_editor(TestEditor::new("# Title\n\nSome *text* here."));
+        let labels = harness.labels(Surface::Panel(PREVIEW_PANEL.into()));
+        assert!(labels.iter().any(|l| l.contains("Title")), "{:?}", labels);
     }
 }
@@ -1,16 +1,42 @@
 use egui::Context;
-use notos_sdk::{EditorContext, NotosPlugin, PluginAction};
+use notos_sdk::{
+    EditorContext, HostServices, NotosPlugin, PluginAction, SettingSpec, SettingsHandle,
+};
 
 struct UrlDetectorPlugin {
-    enabled: bool,
+    settings: SettingsHandle,
 }
 
 impl UrlDetectorPlugin {
     fn new() -> Self {
-        Self { enabled: true }
+        Self {
+            settings: SettingsHandle::default(),
+        }
+    }
+
+    fn enabled(&self) -> bool {
+        self.settings.get_bool("enabled", true)
+    }
+
+    /// `http://`, `https://` and any user-configured prefixes.
+    fn url_prefixes(&self) -> Vec<String> {
+        let mut prefixes = vec!["http://".to_string(), "https://".to_string()];
+        prefixes.extend(
+            self.settings
+                .get_string("extra_prefixes", "")
+                .split(',')
+                .map(str::trim)
+                .filter(|p| !p.is_empty())
+                .map(str::to_string),
+        );
+        prefixes
     }
 
-    fn find_url_range_at_index(content: &str, index: usize) -> Option<(usize, usize, String)> {
+    fn find_url_range_at_index(
+        content: &str,
+        index: usize,
+        prefixes: &[String],
+    ) -> Option<(usize, usize, String)> {
         if content.is_empty() || index > content.len() {
             return None;
         }
@@ -61,7 +87,7 @@ impl UrlDetectorPlugin {
 
         if start < end {
             let extracted = &content[start..end];
-            if extracted.starts_with("http://") || extracted.starts_with("https://") {
+            if prefixes.iter().any(|p| extracted.starts_with(p.as_str())) {
                 return Some((start, end, extracted.to_string()));
             }
         }
@@ -71,27 +97,45 @@ impl UrlDetectorPlugin {
 
 impl NotosPlugin for UrlDetectorPlugin {
     fn id(&self) -> &str {
-        "notos_url_detector"
+        PLUGIN_ID
     }
 
     fn name(&self) -> &str {
         "URL Detector"
     }
 
+    fn attach(&mut self, host: HostServices) {
+        self.settings = host.settings;
+    }
+
+    fn settings_schema(&self) -> Vec<SettingSpec> {
+        vec![
+            SettingSpec::bool("enabled", "Enable URL detection", true),
+            SettingSpec::string("extra_prefixes", "Additional URL prefixes", "")
+                .with_description("Comma-separated, e.g. ftp://, file://"),
+        ]
+    }
+
     fn plugins_menu_ui(&mut self, ui: &mut egui::Ui, _ed: &EditorContext) -> PluginAction {
-        ui.checkbox(&mut self.enabled, "Enable URL Detection");
+        let mut enabled = self.enabled();
+        if ui.checkbox(&mut enabled, "Enable URL Detection").changed() {
+            self.settings.set("enabled", enabled);
+        }
         PluginAction::None
     }
 
     fn ui(&mut self, ctx: &Context, ed: &EditorContext) -> PluginAction {
-        if !self.enabled {
+        if !self.enabled() {
             return PluginAction::None;
         }
+        let prefixes = self.url_prefixes();
 
         // Handle Hover Underline and Cursor
         let mut action = PluginAction::None;
         if let Some(hovered_idx) = ed.hovered_char_idx {
-            if let Some((start, end, _)) = Self::find_url_range_at_index(ed.content, hovered_idx) {
+            if let Some((start, end, _)) =
+                Self::find_url_range_at_index(ed.content, hovered_idx, &prefixes)
+            {
                 // Change cursor and underline only if CTRL is held
                 if ctx.input(|i| i.modifiers.ctrl) {
                     ctx.set_cursor_icon(egui::CursorIcon::PointingHand);
@@ -106,7 +150,8 @@ impl NotosPlugin for UrlDetectorPlugin {
             let target_idx = ed.hovered_char_idx.or_else(|| ed.selection.map(|(s, _)| s));
 
             if let Some(idx) = target_idx {
-                if let Some((_, _, url)) = Self::find_url_range_at_index(ed.content, idx) {
+                if let Some((_, _, url)) = Self::find_url_range_at_index(ed.content, idx, &prefixes)
+                {
                     let url_str = url.clone();
                     std::thread::spawn(move || {
                         #[cfg(target_os = "windows")]
@@ -137,21 +182,52 @@ impl NotosPlugin for UrlDetectorPlugin {
     }
 }
 
-/// Dynamic library entry point for creation
-#[no_mangle]
-#[allow(clippy::missing_safety_doc)]
-pub unsafe extern "C" fn _create_plugin() -> *mut std::ffi::c_void {
-    let plugin: Box<dyn NotosPlugin> = Box::new(UrlDetectorPlugin::new());
-    let wrapper = Box::new(plugin);
-    Box::into_raw(wrapper) as *mut std::ffi::c_void
-}
+notos_sdk::export_plugin!(UrlDetectorPlugin, "notos_url_detector");
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notos_test::{PluginHarness, Surface, TestEditor};
+
+    const TEXT: &str = "see https://example.com/a?b=1 or ftp://host/file";
+
+    #[test]
+    fn ctrl_hover_underlines_url() {
+        let mut harness = PluginHarness::new(UrlDetectorPlugin::new())
+            .with_editor(TestEditor::new(TEXT).with_hovered(10));
+        assert_eq!(harness.show(Surface::Windows), PluginAction::None);
+
+        harness.modifiers = egui::Modifiers::CTRL;
+        assert_eq!(
+            harness.show(Surface::Windows),
+            PluginAction::UnderlineRegion(4, 29)
+        );
+
+        harness.editor.hovered_char_idx = Some(1);
+        assert_eq!(harness.show(Surface::Windows), PluginAction::None);
+    }
+
+    #[test]
+    fn extra_prefixes_come_from_settings() {
+        let mut harness = PluginHarness::new(UrlDetectorPlugin::new())
+            .with_editor(TestEditor::new(TEXT).with_hovered(40));
+        harness.modifiers = egui::Modifiers::CTRL;
+        assert_eq!(harness.show(Surface::Windows), PluginAction::None);
+
+        harness.settings().set("extra_prefixes", "ftp://, file://");
+        assert_eq!(
+            harness.show(Surface::Windows),
+            PluginAction::UnderlineRegion(33, 48)
+        );
+    }
 
-/// Dynamic library entry point for destruction
-#[no_mangle]
-#[allow(clippy::missing_safety_doc)]
-pub unsafe extern "C" fn _destroy_plugin(ptr: *mut std::ffi::c_void) {
-    if !ptr.is_null() {
-        let wrapper: Box<Box<dyn NotosPlugin>> = Box::from_raw(ptr as *mut Box<dyn NotosPlugin>);
-        drop(wrapper);
+    #[test]
+    fn menu_checkbox_disables_detection() {
+        let mut harness = PluginHarness::new(UrlDetectorPlugin::new())
+            .with_editor(TestEditor::new(TEXT).with_hovered(10));
+        harness.modifiers = egui::Modifiers::CTRL;
+        harness.click(Surface::PluginsMenu, "Enable URL Detection");
+        assert!(!harness.settings().get_bool("enabled", true));
+        assert_eq!(harness.show(Surface::Windows), PluginAction::None);
     }
 }
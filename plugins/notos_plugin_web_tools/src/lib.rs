@@ -1,4 +1,4 @@
-use notos_sdk::{EditorContext, NotosPlugin, PluginAction};
+use notos_sdk::{EditorContext, NotosPlugin, PluginAction, PluginCommand};
 use minifier::js::minify as js_minify;
 use minifier::css::minify as css_minify;
 
@@ -12,13 +12,35 @@ impl WebToolsPlugin {
 
 impl NotosPlugin for WebToolsPlugin {
     fn id(&self) -> &str {
-        "notos_web_tools"
+        PLUGIN_ID
     }
 
     fn name(&self) -> &str {
         "Web Tools (Minify)"
     }
 
+    fn commands(&self) -> Vec<PluginCommand> {
+        vec![
+            PluginCommand::new("minify_js", "Minify JS"),
+            PluginCommand::new("minify_css", "Minify CSS"),
+            PluginCommand::new("format_css", "Basic Format CSS"),
+        ]
+    }
+
+    fn run_command(&mut self, id: &str, ed: &EditorContext) -> PluginAction {
+        match id {
+            "minify_js" => self.apply_to_selection_or_all(ed, |t| js_minify(t).to_string()),
+            "minify_css" => self.apply_to_selection_or_all(ed, |t| {
+                match css_minify(t) {
+                    Ok(m) => m.to_string(),
+                    Err(_) => t.to_string(),
+                }
+            }),
+            "format_css" => self.apply_to_selection_or_all(ed, |t| self.simple_css_unminify(t)),
+            _ => PluginAction::None,
+        }
+    }
+
     fn plugins_menu_ui(&mut self, ui: &mut egui::Ui, ed: &EditorContext) -> PluginAction {
         let mut action = PluginAction::None;
 
@@ -98,17 +120,43 @@ impl WebToolsPlugin {
     }
 }
 
-#[no_mangle]
-pub unsafe extern "C" fn _create_plugin() -> *mut std::ffi::c_void {
-    let plugin: Box<dyn NotosPlugin> = Box::new(WebToolsPlugin::new());
-    let wrapper = Box::new(plugin);
-    Box::into_raw(wrapper) as *mut std::ffi::c_void
-}
+notos_sdk::export_plugin!(WebToolsPlugin, "notos_web_tools");
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notos_test::{PluginHarness, Surface, TestEditor};
+
+    #[test]
+    fn minifies_css_from_submenu() {
+        let mut harness = PluginHarness::new(WebToolsPlugin::new())
+            .with_editor(TestEditor::new("a {\n    color: red;\n}\n"));
+        harness.click(Surface::PluginsMenu, "Web Tools");
+        assert_eq!(
+            harness.click(Surface::PluginsMenu, "Minify CSS"),
+            PluginAction::ReplaceAll("a{color:red;}".into())
+        );
+    }
+
+    #[test]
+    fn formats_selected_css() {
+        let mut harness = PluginHarness::new(WebToolsPlugin::new())
+            .with_editor(TestEditor::new("a{color:red}").with_selection(0, 12));
+        harness.click(Surface::PluginsMenu, "Web Tools");
+        let action = harness.click(Surface::PluginsMenu, "Basic Format CSS");
+        assert_eq!(
+            action,
+            PluginAction::ReplaceSelection("a {\n    color:red\n}\n".into())
+        );
+    }
 
-#[no_mangle]
-pub unsafe extern "C" fn _destroy_plugin(ptr: *mut std::ffi::c_void) {
-    if !ptr.is_null() {
-        let wrapper: Box<Box<dyn NotosPlugin>> = Box::from_raw(ptr as *mut Box<dyn NotosPlugin>);
-        drop(wrapper);
+    #[test]
+    fn empty_document_does_nothing() {
+        let mut harness = PluginHarness::new(WebToolsPlugin::new());
+        harness.click(Surface::PluginsMenu, "Web Tools");
+        assert_eq!(
+            harness.click(Surface::PluginsMenu, "Minify JS"),
+            PluginAction::None
+        );
     }
 }
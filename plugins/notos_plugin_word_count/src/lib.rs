@@ -0,0 +1,252 @@
+use notos_sdk::{
+    EditorContext, HostServices, NotosPlugin, PluginAction, SettingSpec, SettingsHandle,
+    StatusBarItem,
+};
+use std::time::{Duration, Instant};
+
+/// How long document counts are reused while the buffer looks unchanged.
+const COUNT_REFRESH: Duration = Duration::from_millis(500);
+
+/// Default reading speed used for the reading time estimate.
+const WORDS_PER_MINUTE: f64 = 200.0;
+
+#[derive(Clone, Copy, Default)]
+struct Counts {
+    words: usize,
+    chars: usize,
+    chars_no_spaces: usize,
+    lines: usize,
+    paragraphs: usize,
+}
+
+impl Counts {
+    fn of(text: &str) -> Self {
+        let mut counts = Counts {
+            words: text.split_whitespace().count(),
+            lines: text.lines().count().max(1),
+            ..Default::default()
+        };
+        for c in text.chars() {
+            counts.chars += 1;
+            if !c.is_whitespace() {
+                counts.chars_no_spaces += 1;
+            }
+        }
+        let mut in_paragraph = false;
+        for line in text.lines() {
+            let blank = line.trim().is_empty();
+            if !blank && !in_paragraph {
+                counts.paragraphs += 1;
+            }
+            in_paragraph = !blank;
+        }
+        counts
+    }
+}
+
+struct WordCountPlugin {
+    settings: SettingsHandle,
+    /// Document counts keyed by the content buffer they were computed from.
+    cache: Option<((usize, usize), Instant, Counts)>,
+}
+
+impl WordCountPlugin {
+    fn new() -> Self {
+        Self {
+            settings: SettingsHandle::default(),
+            cache: None,
+        }
+    }
+
+    fn document_counts(&mut self, content: &str) -> Counts {
+        let key = (content.as_ptr() as usize, content.len());
+        match self.cache {
+            Some((k, at, counts)) if k == key && at.elapsed() <= COUNT_REFRESH => counts,
+            _ => {
+                let counts = Counts::of(content);
+                self.cache = Some((key, Instant::now(), counts));
+                counts
+            }
+        }
+    }
+
+    fn selected_text<'a>(ed: &EditorContext<'a>) -> Option<&'a str> {
+        let (s, e) = ed.selection?;
+        let (start, end) = (s.min(e), s.max(e));
+        if start == end {
+            return None;
+        }
+        // The selection is in chars
+        let mut offsets = ed
+            .content
+            .char_indices()
+            .map(|(i, _)| i)
+            .chain(std::iter::once(ed.content.len()));
+        let start_byte = offsets.nth(start)?;
+        let end_byte = offsets.nth(end - start - 1)?;
+        Some(&ed.content[start_byte..end_byte])
+    }
+}
+
+fn counts_grid(ui: &mut egui::Ui, id: &str, counts: &Counts, words_per_minute: usize) {
+    egui::Grid::new(id)
+        .num_columns(2)
+        .spacing([16.0, 2.0])
+        .show(ui, |ui| {
+            ui.label("Words");
+            ui.label(counts.words.to_string());
+            ui.end_row();
+            ui.label("Characters");
+            ui.label(counts.chars.to_string());
+            ui.end_row();
+            ui.label("Characters (no spaces)");
+            ui.label(counts.chars_no_spaces.to_string());
+            ui.end_row();
+            ui.label("Lines");
+            ui.label(counts.lines.to_string());
+            ui.end_row();
+            ui.label("Paragraphs");
+            ui.label(counts.paragraphs.to_string());
+            ui.end_row();
+            ui.label("Reading time");
+            ui.label(format!(
+                "{} min",
+                counts.words.div_ceil(words_per_minute.max(1)).max(1)
+            ));
+            ui.end_row();
+        });
+}
+
+impl NotosPlugin for WordCountPlugin {
+    fn id(&self) -> &str {
+        PLUGIN_ID
+    }
+
+    fn name(&self) -> &str {
+        "Word Count"
+    }
+
+    fn attach(&mut self, host: HostServices) {
+        self.settings = host.settings;
+    }
+
+    fn settings_schema(&self) -> Vec<SettingSpec> {
+        vec![SettingSpec::number(
+            "words_per_minute",
+            "Reading speed (words per minute)",
+            WORDS_PER_MINUTE,
+            50.0,
+            1000.0,
+        )]
+    }
+
+    fn status_bar_items(&mut self, ed: &EditorContext) -> Vec<StatusBarItem> {
+        let total = self.document_counts(ed.content).words;
+        let text = match Self::selected_text(ed) {
+            Some(selection) => format!(
+                "{} of {} words",
+                selection.split_whitespace().count(),
+                total
+            ),
+            None if total == 1 => "1 word".to_string(),
+            None => format!("{} words", total),
+        };
+        vec![StatusBarItem::new("words", text)
+            .with_tooltip("Word count (click for details)")
+            .with_popup()]
+    }
+
+    fn status_bar_popup_ui(
+        &mut self,
+        _id: &str,
+        ui: &mut egui::Ui,
+        ed: &EditorContext,
+    ) -> PluginAction {
+        ui.set_min_width(220.0);
+        let wpm = self
+            .settings
+            .get_f64("words_per_minute", WORDS_PER_MINUTE)
+            .round() as usize;
+        let document = self.document_counts(ed.content);
+        ui.strong("Document");
+        counts_grid(ui, "word_count_document", &document, wpm);
+        if let Some(selection) = Self::selected_text(ed) {
+            ui.separator();
+            ui.strong("Selection");
+            counts_grid(ui, "word_count_selection", &Counts::of(selection), wpm);
+        }
+        PluginAction::None
+    }
+}
+
+notos_sdk::export_plugin!(WordCountPlugin, "notos_word_count");
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notos_test::{PluginHarness, Surface, TestEditor};
+
+    const TEXT: &str = "One two three.\n\nFour five.\n";
+
+    #[test]
+    fn status_item_counts_document_and_selection() {
+        let mut plugin = WordCountPlugin::new();
+        let editor = TestEditor::new(TEXT);
+        assert_eq!(
+            plugin.status_bar_items(&editor.context())[0].text,
+            "5 words"
+        );
+
+        let editor = editor.with_selection(0, 7);
+        assert_eq!(
+            plugin.status_bar_items(&editor.context())[0].text,
+            "2 of 5 words"
+        );
+    }
+
+    #[test]
+    fn selection_is_counted_in_chars() {
+        let mut plugin = WordCountPlugin::new();
+        // "Über" and "café" take more bytes than chars
+        let editor = TestEditor::new("Über café, naïve résumé").with_selection(11, 23);
+        assert_eq!(
+            plugin.status_bar_items(&editor.context())[0].text,
+            "2 of 4 words"
+        );
+        let editor = editor.with_selection(5, 9);
+        assert_eq!(
+            plugin.status_bar_items(&editor.context())[0].text,
+            "1 of 4 words"
+        );
+    }
+
+    #[test]
+    fn popup_lists_counts() {
+        let mut harness =
+            PluginHarness::new(WordCountPlugin::new()).with_editor(TestEditor::new(TEXT));
+        let popup = Surface::StatusBarPopup("words".into());
+        let labels = harness.labels(popup.clone());
+        for expected in ["Document", "Words", "5", "Paragraphs", "2", "1 min"] {
+            assert!(
+                labels.iter().any(|l| l == expected),
+                "{} in {:?}",
+                expected,
+                labels
+            );
+        }
+        assert!(!labels.iter().any(|l| l == "Selection"));
+
+        harness.editor.selection = Some((0, 3));
+        assert!(harness.labels(popup).iter().any(|l| l == "Selection"));
+    }
+
+    #[test]
+    fn reading_time_uses_setting() {
+        let words = "word ".repeat(120);
+        let mut harness =
+            PluginHarness::new(WordCountPlugin::new()).with_editor(TestEditor::new(words));
+        harness.settings().set("words_per_minute", 50.0);
+        let labels = harness.labels(Surface::StatusBarPopup("words".into()));
+        assert!(labels.iter().any(|l| l == "3 min"), "{:?}", labels);
+    }
+}
@@ -1,18 +1,33 @@
+use crate::commands::{CommandRegistry, CommandTarget};
+use crate::keymap::Keymap;
 use crate::plugin::PluginManager;
+use crate::settings::SettingsStore;
 use crate::undo_manager::UndoManager;
 use eframe::egui;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
-use crate::dialogs::{CloseConfirmationDialog, FindDialog, GotoLineDialog};
+use crate::dialogs::{
+    CloseConfirmationDialog, CommandPalette, FindDialog, GotoLineDialog, KeybindingEditor,
+    SettingsWindow,
+};
 use crate::editor::{EditorTab, TabId};
 
 mod actions;
+mod autosave;
+mod disk;
 mod editor_panel;
 mod file_ops;
+mod follow;
+mod macros;
+mod panels;
+mod recovery;
+mod remote;
+mod scripts;
 mod session;
 mod style;
 mod update;
 
+use panels::{PanelLayout, RegisteredPanel, TOGGLE_PANEL_PREFIX};
 use session::SessionState;
 use style::{setup_custom_fonts, setup_custom_style};
 
@@ -20,6 +35,26 @@ pub struct NotosApp {
     tabs: Vec<EditorTab>,
     active_tab_id: Option<TabId>,
     plugin_manager: PluginManager,
+    commands: CommandRegistry,
+    command_palette: CommandPalette,
+    keymap: Keymap,
+    keybinding_editor: KeybindingEditor,
+    plugin_panels: Vec<RegisteredPanel>,
+    panel_layouts: Vec<PanelLayout>,
+    plugin_settings: SettingsStore,
+    /// The editor's own part of `plugin_settings`.
+    editor_settings: notos_sdk::SettingsHandle,
+    settings_schemas: Vec<(String, String, Vec<notos_sdk::SettingSpec>)>,
+    settings_window: SettingsWindow,
+    scripts: Vec<crate::scripting::Script>,
+    script_job: Option<crate::scripting::ScriptJob>,
+    script_notice: Option<(String, std::time::Instant)>,
+    macro_store: crate::macros::MacroStore,
+    /// Steps recorded so far while recording a macro.
+    macro_recording: Option<Vec<crate::macros::MacroStep>>,
+    last_macro: Vec<crate::macros::MacroStep>,
+    macro_playback: Option<crate::macros::Playback>,
+    macro_dialog: crate::dialogs::MacroDialog,
     current_cursor_pos: (usize, usize), // Line, Col (1-based)
     find_dialog: FindDialog,
     goto_dialog: GotoLineDialog,
@@ -31,13 +66,29 @@ pub struct NotosApp {
     editor_font_family: String,
     custom_fonts: std::collections::HashMap<String, Vec<u8>>,
     recent_files: Vec<std::path::PathBuf>,
-    ipc_receiver: std::sync::mpsc::Receiver<String>,
+    ipc_receiver: std::sync::mpsc::Receiver<crate::ipc::Incoming>,
+    /// Command-line options for files that are still loading.
+    open_options: HashMap<std::path::PathBuf, remote::OpenOptions>,
+    waiters: Vec<remote::Waiter>,
+    /// Tabs that standard input is read into, by IPC connection.
+    input_tabs: HashMap<u64, TabId>,
     next_underline: Option<(usize, usize)>,
     hovered_char_idx: Option<usize>,
     last_session_save: std::time::Instant,
     file_load_receiver: std::sync::mpsc::Receiver<(std::path::PathBuf, std::result::Result<EditorTab, String>)>,
     file_load_sender: std::sync::mpsc::Sender<(std::path::PathBuf, std::result::Result<EditorTab, String>)>,
     loading_paths: HashSet<std::path::PathBuf>,
+    file_watcher: crate::watcher::FileWatcher,
+    /// Tabs in follow mode.
+    followers: HashMap<TabId, crate::follow::FollowHandle>,
+    follow_receiver: std::sync::mpsc::Receiver<(TabId, crate::follow::FollowEvent)>,
+    follow_sender: std::sync::mpsc::Sender<(TabId, crate::follow::FollowEvent)>,
+    disk_conflicts: Vec<disk::DiskConflict>,
+    /// Journal of unsaved edits, for recovery after a crash.
+    journal: Option<crate::recovery::Journal>,
+    /// Unsaved edits recovered from a crashed instance, still to ask about.
+    recovery: Option<crate::recovery::Recovery>,
+    autosave: autosave::AutoSaveState,
     prev_dark_mode: bool,
     pub(crate) undo_manager: UndoManager,
 }
@@ -45,8 +96,9 @@ pub struct NotosApp {
 impl NotosApp {
     pub fn new(
         cc: &eframe::CreationContext<'_>,
-        args: Vec<String>,
-        rx: std::sync::mpsc::Receiver<String>,
+        open: crate::cli::OpenRequest,
+        plugin_paths: Vec<std::path::PathBuf>,
+        rx: std::sync::mpsc::Receiver<crate::ipc::Incoming>,
     ) -> Self {
         // Initial setup
         setup_custom_fonts(&cc.egui_ctx);
@@ -54,10 +106,33 @@ impl NotosApp {
         let (tx_load, rx_load) =
             std::sync::mpsc::channel::<(std::path::PathBuf, std::result::Result<EditorTab, String>)>();
 
+        let (tx_follow, rx_follow) = std::sync::mpsc::channel();
+
+        let mut plugin_settings = SettingsStore::load();
+        let editor_settings = plugin_settings.handle(crate::settings::EDITOR_ID);
+
         let mut app = Self {
             tabs: vec![EditorTab::default()],
             active_tab_id: None, // Will be set in init
-            plugin_manager: PluginManager::new(),
+            plugin_manager: PluginManager::new(plugin_paths),
+            commands: CommandRegistry::new(),
+            command_palette: CommandPalette::default(),
+            keymap: Keymap::load(),
+            keybinding_editor: KeybindingEditor::default(),
+            plugin_panels: Vec::new(),
+            panel_layouts: Vec::new(),
+            plugin_settings,
+            editor_settings,
+            settings_schemas: Vec::new(),
+            settings_window: SettingsWindow::default(),
+            scripts: Vec::new(),
+            script_job: None,
+            script_notice: None,
+            macro_store: crate::macros::MacroStore::load(),
+            macro_recording: None,
+            last_macro: Vec::new(),
+            macro_playback: None,
+            macro_dialog: crate::dialogs::MacroDialog::default(),
             current_cursor_pos: (1, 1),
             find_dialog: FindDialog::default(),
             goto_dialog: GotoLineDialog::default(),
@@ -70,12 +145,23 @@ impl NotosApp {
             close_confirmation: CloseConfirmationDialog::default(),
             recent_files: Vec::new(),
             ipc_receiver: rx,
+            open_options: HashMap::new(),
+            waiters: Vec::new(),
+            input_tabs: HashMap::new(),
             next_underline: None,
             hovered_char_idx: None,
             last_session_save: std::time::Instant::now(),
             file_load_receiver: rx_load,
             file_load_sender: tx_load,
             loading_paths: HashSet::new(),
+            file_watcher: crate::watcher::FileWatcher::spawn(cc.egui_ctx.clone()),
+            followers: HashMap::new(),
+            follow_receiver: rx_follow,
+            follow_sender: tx_follow,
+            disk_conflicts: Vec::new(),
+            journal: None,
+            recovery: None,
+            autosave: autosave::AutoSaveState::default(),
             prev_dark_mode: false,
             undo_manager: UndoManager::new(None),
         };
@@ -91,6 +177,7 @@ impl NotosApp {
             app.editor_font_family = session.editor_font_family;
             app.custom_fonts = session.custom_fonts;
             app.recent_files = session.recent_files;
+            app.panel_layouts = session.panels;
 
             // Restore fonts in egui
             let mut fonts = egui::FontDefinitions::default();
@@ -144,25 +231,25 @@ impl NotosApp {
             app.prev_dark_mode = app.dark_mode;
         }
 
+        app.start_recovery();
+
         // Load plugins here
         app.plugin_manager.load_plugins();
+        app.plugin_manager.attach(&cc.egui_ctx, &mut app.plugin_settings);
         app.plugin_manager.on_load(&cc.egui_ctx);
+        app.register_plugin_contributions();
+        app.register_scripts();
+        app.register_macro_commands();
 
         // Handle command line arguments
-        let mut opened_any = false;
-        for arg in args {
-            let path = std::path::PathBuf::from(arg);
-            if path.exists() && path.is_file() && app.open_path(path) {
-                opened_any = true;
-            }
-        }
+        let opened_any = app.open_request(open, crate::ipc::LOCAL_CONNECTION, None);
 
         // If we opened files from args and we have the default empty untitled tab, remove it
         if opened_any && app.tabs.len() > 1 {
             if let Some(pos) = app
                 .tabs
                 .iter()
-                .position(|t| t.path.is_none() && t.content.is_empty())
+                .position(|t| t.path.is_none() && t.content.is_empty() && !app.is_input_tab(t.id))
             {
                 let id = app.tabs[pos].id;
                 app.tabs.remove(pos);
@@ -176,6 +263,39 @@ impl NotosApp {
         app
     }
 
+    /// (Re-)register everything plugins contribute to the host: commands,
+    /// panels and settings. Called at startup and after a developer-mode reload.
+    pub(crate) fn register_plugin_contributions(&mut self) {
+        self.commands.retain(|c| {
+            matches!(c.target, CommandTarget::Host(_)) && !c.id.starts_with(TOGGLE_PANEL_PREFIX)
+        });
+        self.settings_schemas = std::iter::once(crate::settings::editor_schema())
+            .chain(self.plugin_manager.settings_schemas())
+            .collect();
+        for (plugin_id, plugin_name, commands) in self.plugin_manager.commands() {
+            self.commands
+                .register_plugin_commands(&plugin_id, &plugin_name, commands);
+        }
+        self.register_plugin_panels();
+        self.keymap.apply(&mut self.commands);
+        for conflict in crate::keymap::conflicts(&self.commands) {
+            log::warn!(
+                "Keybinding {} is bound to several commands: {}",
+                conflict.binding,
+                conflict.commands.join(", ")
+            );
+        }
+    }
+
+    /// How saving backs up files.
+    fn backup(&self) -> crate::save::Backup {
+        crate::settings::backup(&self.editor_settings)
+    }
+
+    fn active_tab(&self) -> Option<&EditorTab> {
+        self.tabs.iter().find(|t| Some(t.id) == self.active_tab_id)
+    }
+
     fn active_tab_mut(&mut self) -> Option<&mut EditorTab> {
         self.tabs
             .iter_mut()
@@ -193,29 +313,39 @@ impl NotosApp {
             &self.editor_font_family,
             &self.custom_fonts,
             &self.recent_files,
+            &self.panel_layouts,
             undo_state,
         )
     }
 }
 
-fn get_ed_ctx(
+pub(crate) fn get_ed_ctx(
     tabs: &[EditorTab],
     active_tab_id: Option<TabId>,
     hovered_char_idx: Option<usize>,
 ) -> notos_sdk::EditorContext<'_> {
-    if let Some(tab) = tabs.iter().find(|t| Some(t.id) == active_tab_id) {
-        notos_sdk::EditorContext {
-            content: &tab.content,
-            selection: tab.cursor_range,
-            hovered_char_idx,
-            file_path: tab.path.as_deref(),
-        }
-    } else {
-        notos_sdk::EditorContext {
-            content: "",
-            selection: None,
-            hovered_char_idx,
-            file_path: None,
-        }
+    let active = tabs.iter().find(|t| Some(t.id) == active_tab_id);
+    notos_sdk::EditorContext {
+        content: active.map_or("", |t| t.content.as_str()),
+        selection: active.and_then(|t| t.cursor_range),
+        hovered_char_idx,
+        file_path: active.and_then(|t| t.path.as_deref()),
+        active_tab: active.map(|t| t.id.into()),
+        tabs: notos_sdk::TabList::new(move || tabs.iter().map(tab_info).collect()),
+    }
+}
+
+/// Read-only view of a tab for plugins.
+fn tab_info(tab: &EditorTab) -> notos_sdk::TabInfo<'_> {
+    notos_sdk::TabInfo {
+        id: tab.id.into(),
+        title: &tab.title,
+        path: tab.path.as_deref(),
+        is_dirty: tab.is_dirty,
+        language: notos_sdk::language_for_path(tab.path.as_deref()),
+        encoding: tab.encoding.name(),
+        line_ending: tab.line_ending.name(),
+        content: &tab.content,
+        selection: tab.cursor_range,
     }
 }
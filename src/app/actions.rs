@@ -1,23 +1,51 @@
 use eframe::egui;
 use rfd::FileDialog;
 
-use crate::editor::EditorTab;
+use crate::commands::CommandTarget;
+use crate::dialogs::MacroPrompt;
+use crate::editor::{EditorTab, TabId};
+use crate::macros::Repeat;
+use crate::trust::TrustDecision;
 use crate::ui::MenuAction;
 
 use super::style::setup_custom_style;
-use super::NotosApp;
+use super::{get_ed_ctx, NotosApp};
 
 impl NotosApp {
     pub(crate) fn handle_plugin_action(
         &mut self,
         action: notos_sdk::PluginAction,
         ctx: &egui::Context,
+    ) {
+        self.apply_plugin_action(action, self.active_tab_id, ctx);
+    }
+
+    /// Apply a plugin action, with text actions going to the tab `target`.
+    pub(super) fn apply_plugin_action(
+        &mut self,
+        action: notos_sdk::PluginAction,
+        target: Option<TabId>,
+        ctx: &egui::Context,
     ) {
         use notos_sdk::PluginAction;
         match action {
             PluginAction::None => {}
+            PluginAction::InTab(tab_id, action) => {
+                let tab_id = TabId::from(tab_id);
+                if self.tabs.iter().any(|t| t.id == tab_id) {
+                    self.apply_plugin_action(*action, Some(tab_id), ctx);
+                } else {
+                    log::warn!("Plugin action for a tab that is not open: {:?}", tab_id);
+                }
+            }
+            PluginAction::ActivateTab(tab_id) => {
+                let tab_id = TabId::from(tab_id);
+                if self.tabs.iter().any(|t| t.id == tab_id) {
+                    self.active_tab_id = Some(tab_id);
+                }
+            }
             PluginAction::ReplaceAll(new_text) => {
-                if let Some(tab) = self.tabs.iter_mut().find(|t| Some(t.id) == self.active_tab_id) {
+                if let Some(tab) = self.tabs.iter_mut().find(|t| Some(t.id) == target && !t.read_only) {
                     let (curr, _) = tab.cursor_range.unwrap_or((0, 0));
                     self.undo_manager.push_undo(tab.id, tab.content.clone(), curr, tab.large_file);
                     tab.content = new_text;
@@ -28,7 +56,7 @@ impl NotosApp {
                 }
             }
             PluginAction::ReplaceSelection(new_text) => {
-                if let Some(tab) = self.tabs.iter_mut().find(|t| Some(t.id) == self.active_tab_id) {
+                if let Some(tab) = self.tabs.iter_mut().find(|t| Some(t.id) == target && !t.read_only) {
                     let id = egui::Id::new("editor").with(tab.id);
                     let mut state = egui::TextEdit::load_state(ctx, id).unwrap_or_default();
                     let range = state.cursor.char_range().unwrap_or_else(|| {
@@ -68,10 +96,41 @@ impl NotosApp {
                 }
             }
             PluginAction::UnderlineRegion(_, _) => {}
+            PluginAction::ShowPanel(key) => self.show_panel(&key),
+            PluginAction::ApplyEdits(edits) => {
+                if let Some(tab) = self.tabs.iter_mut().find(|t| Some(t.id) == target && !t.read_only) {
+                    if edits.is_empty() {
+                        return;
+                    }
+                    let (curr, _) = tab.cursor_range.unwrap_or((0, 0));
+                    let previous = tab.content.clone();
+                    if let Err(e) = tab.apply_edits(edits) {
+                        log::warn!("Rejected plugin edits: {}", e);
+                        return;
+                    }
+                    self.undo_manager.push_undo(tab.id, previous, curr, tab.large_file);
+                    tab.undo_snapshot = tab.content.clone();
+
+                    // Keep egui's cursor in sync with the rebased selection; scroll position is untouched
+                    let (p, s) = tab.cursor_range.unwrap_or((0, 0));
+                    tab.undo_snapshot_cursor = p;
+                    let id = egui::Id::new("editor").with(tab.id);
+                    if let Some(mut state) = egui::TextEdit::load_state(ctx, id) {
+                        state
+                            .cursor
+                            .set_char_range(Some(egui::text::CCursorRange::two(
+                                egui::text::CCursor::new(p),
+                                egui::text::CCursor::new(s),
+                            )));
+                        egui::TextEdit::store_state(ctx, id, state);
+                    }
+                }
+            }
         }
     }
 
     pub(crate) fn handle_menu_action(&mut self, action: MenuAction, ctx: &egui::Context) {
+        self.record_menu_action(&action);
         match action {
             MenuAction::NewTab => {
                 self.new_tab();
@@ -85,9 +144,43 @@ impl NotosApp {
             }
             MenuAction::Save => self.save_file(),
             MenuAction::SaveAs => self.save_file_as(),
+            MenuAction::CloseTab => {
+                if let Some(id) = self.active_tab_id {
+                    self.close_tab(id);
+                }
+            }
+            MenuAction::CommandPalette => {
+                self.command_palette.open = true;
+                self.command_palette.query.clear();
+                self.command_palette.selected = 0;
+                self.command_palette.just_opened = true;
+            }
+            MenuAction::KeyboardShortcuts => self.keybinding_editor.open = true,
+            MenuAction::TogglePanel(key) => self.toggle_panel(&key),
+            MenuAction::Settings => self.settings_window.open = true,
+            MenuAction::RunScript(id) => self.run_script(&id, ctx),
+            MenuAction::CancelScript => self.cancel_script(),
+            MenuAction::NewScript => self.new_script(),
+            MenuAction::ReloadScripts => self.register_scripts(),
+            MenuAction::ToggleMacroRecording => self.toggle_macro_recording(),
+            MenuAction::PlayMacro => self.play_macro(self.last_macro.clone(), Repeat::Times(1)),
+            MenuAction::PlayMacroTimes => self.ask_macro(MacroPrompt::PlayTimes),
+            MenuAction::PlayMacroToEnd => self.play_macro(self.last_macro.clone(), Repeat::ToEnd),
+            MenuAction::SaveMacro => self.ask_macro(MacroPrompt::SaveAs),
+            MenuAction::PlaySavedMacro(name) => self.play_saved_macro(&name),
+            MenuAction::DeleteMacro(name) => self.delete_macro(&name),
+            MenuAction::TogglePluginTrust => {
+                let enabled = !self.plugin_manager.trust_enabled();
+                self.plugin_manager.set_trust_enabled(enabled);
+            }
+            MenuAction::TrustPlugin(path) => {
+                if self.plugin_manager.resolve_trust(&path, TrustDecision::Trust, ctx, &mut self.plugin_settings) {
+                    self.register_plugin_contributions();
+                }
+            }
             MenuAction::Exit => ctx.send_viewport_cmd(egui::ViewportCommand::Close),
             MenuAction::Undo => {
-                if let Some(tab) = self.tabs.iter_mut().find(|t| Some(t.id) == self.active_tab_id) {
+                if let Some(tab) = self.tabs.iter_mut().find(|t| Some(t.id) == self.active_tab_id && !t.read_only) {
                     let (curr, _) = tab.cursor_range.unwrap_or((0, 0));
                     if let Some(entry) = self.undo_manager.undo(tab.id, tab.content.clone(), curr) {
                         tab.content = entry.content;
@@ -108,7 +201,7 @@ impl NotosApp {
                 }
             }
             MenuAction::Redo => {
-                if let Some(tab) = self.tabs.iter_mut().find(|t| Some(t.id) == self.active_tab_id) {
+                if let Some(tab) = self.tabs.iter_mut().find(|t| Some(t.id) == self.active_tab_id && !t.read_only) {
                     let (curr, _) = tab.cursor_range.unwrap_or((0, 0));
                     if let Some(entry) = self.undo_manager.redo(tab.id, tab.content.clone(), curr) {
                         tab.content = entry.content;
@@ -143,7 +236,7 @@ impl NotosApp {
                 self.goto_dialog.line_str = self.current_cursor_pos.0.to_string();
             }
             MenuAction::TimeDate => {
-                if let Some(tab) = self.tabs.iter_mut().find(|t| Some(t.id) == self.active_tab_id) {
+                if let Some(tab) = self.tabs.iter_mut().find(|t| Some(t.id) == self.active_tab_id && !t.read_only) {
                     let now = chrono::Local::now();
                     let time_str = now.format("%I:%M %p %m/%d/%Y").to_string();
 
@@ -177,9 +270,19 @@ impl NotosApp {
                     ctx.request_repaint();
                 }
             }
-            MenuAction::ToggleWordWrap => { /* handled by ref mut */ }
-            MenuAction::ToggleLineNumbers => { /* handled by ref mut */ }
+            MenuAction::ToggleWordWrap => {
+                self.word_wrap = !self.word_wrap;
+            }
+            MenuAction::ToggleFollow => {
+                if let Some(id) = self.active_tab_id {
+                    self.toggle_follow(id, ctx);
+                }
+            }
+            MenuAction::ToggleLineNumbers => {
+                self.show_line_numbers = !self.show_line_numbers;
+            }
             MenuAction::ToggleDarkMode => {
+                self.dark_mode = !self.dark_mode;
                 setup_custom_style(ctx, self.dark_mode);
             }
             MenuAction::ZoomIn => {
@@ -240,46 +343,33 @@ impl NotosApp {
         self.tabs.push(tab);
     }
 
-    pub(crate) fn handle_shortcuts(&mut self, ctx: &egui::Context) {
-        if ctx.input_mut(|i| i.consume_key(egui::Modifiers::CTRL, egui::Key::N))
-            || ctx.input_mut(|i| i.consume_key(egui::Modifiers::CTRL, egui::Key::T))
-        {
-            self.new_tab();
-        }
-        if ctx.input_mut(|i| i.consume_key(egui::Modifiers::CTRL, egui::Key::O)) {
-            self.open_file();
-        }
-        if ctx.input_mut(|i| i.consume_key(egui::Modifiers::CTRL, egui::Key::S)) {
-            self.save_file();
-        }
-        if ctx.input_mut(|i| {
-            i.consume_key(egui::Modifiers::CTRL | egui::Modifiers::SHIFT, egui::Key::S)
-        }) {
-            self.save_file_as();
-        }
-        if ctx.input_mut(|i| i.consume_key(egui::Modifiers::CTRL, egui::Key::W)) {
-            if let Some(id) = self.active_tab_id {
-                self.close_tab(id);
+    /// Run a registered command by id against the active tab.
+    pub(crate) fn execute_command(&mut self, id: &str, ctx: &egui::Context) {
+        let Some(target) = self.commands.get(id).map(|c| c.target.clone()) else {
+            log::warn!("Unknown command: {}", id);
+            return;
+        };
+        match target {
+            CommandTarget::Host(action) => self.handle_menu_action(action, ctx),
+            CommandTarget::Plugin {
+                plugin_id,
+                command_id,
+            } => {
+                self.record_command(id);
+                let action = {
+                    let ed_ctx = get_ed_ctx(&self.tabs, self.active_tab_id, self.hovered_char_idx);
+                    self.plugin_manager.run_command(&plugin_id, &command_id, &ed_ctx)
+                };
+                self.handle_plugin_action(action, ctx);
             }
         }
-        if ctx.input_mut(|i| i.consume_key(egui::Modifiers::CTRL, egui::Key::Z)) {
-            self.handle_menu_action(MenuAction::Undo, ctx);
-        }
-        if ctx.input_mut(|i| i.consume_key(egui::Modifiers::CTRL, egui::Key::Y)) {
-            self.handle_menu_action(MenuAction::Redo, ctx);
-        }
+    }
 
-        // Zoom Shortcuts
-        if ctx.input(|i| {
-            i.modifiers.ctrl && (i.key_pressed(egui::Key::Plus) || i.key_pressed(egui::Key::Equals))
-        }) {
-            self.editor_font_size = (self.editor_font_size + 1.0).clamp(6.0, 72.0);
-        }
-        if ctx.input(|i| i.modifiers.ctrl && i.key_pressed(egui::Key::Minus)) {
-            self.editor_font_size = (self.editor_font_size - 1.0).clamp(6.0, 72.0);
-        }
-        if ctx.input(|i| i.modifiers.ctrl && i.key_pressed(egui::Key::Num0)) {
-            self.editor_font_size = 14.0;
+    pub(crate) fn handle_shortcuts(&mut self, ctx: &egui::Context) {
+        if !self.keybinding_editor.is_recording() {
+            if let Some(id) = self.keymap.dispatch(ctx, &self.commands) {
+                self.execute_command(&id, ctx);
+            }
         }
 
         // Mouse Wheel Zoom
@@ -293,23 +383,5 @@ impl NotosApp {
                 }
             }
         }
-
-        if ctx.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::F5)) {
-            self.handle_menu_action(MenuAction::TimeDate, ctx);
-        }
-
-        if ctx.input_mut(|i| i.consume_key(egui::Modifiers::CTRL, egui::Key::G)) {
-            self.handle_menu_action(MenuAction::GotoLine, ctx);
-        }
-
-        if ctx.input_mut(|i| i.consume_key(egui::Modifiers::CTRL, egui::Key::F)) {
-            self.handle_menu_action(MenuAction::Find, ctx);
-        }
-        if ctx.input_mut(|i| i.consume_key(egui::Modifiers::CTRL, egui::Key::H)) {
-            self.handle_menu_action(MenuAction::Replace, ctx);
-        }
-        if ctx.input_mut(|i| i.consume_key(egui::Modifiers::CTRL, egui::Key::A)) {
-            self.handle_menu_action(MenuAction::SelectAll, ctx);
-        }
     }
 }
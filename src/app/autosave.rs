@@ -0,0 +1,215 @@
+//! Saving tabs without being asked to, as the auto-save setting says.
+
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+use eframe::egui;
+
+use crate::editor::{EditorTab, TabId};
+use crate::save::Backup;
+use crate::settings::AutoSave;
+
+use super::NotosApp;
+
+/// How long a failed auto-save is shown in the status bar.
+const ERROR_NOTICE_TIME: Duration = Duration::from_secs(10);
+
+#[derive(Default)]
+pub(super) struct AutoSaveState {
+    /// When each tab with unsaved edits was last edited, as far as known.
+    edited: HashMap<TabId, Instant>,
+    /// The active tab of the last frame.
+    active: Option<TabId>,
+    /// Tabs that an auto-save already backed up. Later auto-saves skip the
+    /// backup, which would otherwise only ever hold the last few seconds.
+    backed_up: HashSet<TabId>,
+    /// The last auto-save that failed and when, until the tab is saved.
+    error: Option<(TabId, String, Instant)>,
+}
+
+/// Whether `tab` can be saved without asking anything: it has a file, and
+/// saving is neither disabled nor pointless.
+fn can_autosave(tab: &EditorTab) -> bool {
+    tab.is_dirty && tab.path.is_some() && !tab.large_file && !tab.read_only
+}
+
+/// The tabs in `edited` idle for at least `delay` at `now`, and how long until
+/// the next of the others is.
+fn due_after_delay(
+    edited: &HashMap<TabId, Instant>,
+    delay: Duration,
+    now: Instant,
+) -> (Vec<TabId>, Option<Duration>) {
+    let mut next: Option<Duration> = None;
+    let due = edited
+        .iter()
+        .filter(|(_, edited)| {
+            let idle = now.duration_since(**edited);
+            if idle < delay {
+                next = Some(next.map_or(delay - idle, |n| n.min(delay - idle)));
+            }
+            idle >= delay
+        })
+        .map(|(id, _)| *id)
+        .collect();
+    (due, next)
+}
+
+impl NotosApp {
+    pub(super) fn poll_autosave(&mut self, ctx: &egui::Context) {
+        let mode = crate::settings::autosave(&self.editor_settings);
+        let now = Instant::now();
+        let state = &mut self.autosave;
+        state
+            .edited
+            .retain(|id, _| self.tabs.iter().any(|t| t.id == *id && can_autosave(t)));
+        state
+            .backed_up
+            .retain(|id| self.tabs.iter().any(|t| t.id == *id));
+        if let Some((id, _, _)) = &state.error {
+            if !self.tabs.iter().any(|t| t.id == *id && t.is_dirty) {
+                state.error = None;
+            }
+        }
+        for tab in self.tabs.iter().filter(|t| can_autosave(t)) {
+            let edited = state.edited.entry(tab.id).or_insert(now);
+            if let Some(last_edit) = tab.last_edit_time {
+                *edited = (*edited).max(last_edit);
+            }
+        }
+        let switched_from = state.active.filter(|id| Some(*id) != self.active_tab_id);
+        state.active = self.active_tab_id;
+
+        let due: Vec<TabId> = match mode {
+            AutoSave::Off => Vec::new(),
+            AutoSave::AfterDelay(delay) => {
+                let (due, next) = due_after_delay(&state.edited, delay, now);
+                if let Some(next) = next {
+                    ctx.request_repaint_after(next);
+                }
+                due
+            }
+            AutoSave::FocusLost => {
+                let focus_lost = ctx.input(|i| {
+                    i.events
+                        .iter()
+                        .any(|e| matches!(e, egui::Event::WindowFocused(false)))
+                });
+                if focus_lost {
+                    state.edited.keys().copied().collect()
+                } else {
+                    Vec::new()
+                }
+            }
+            AutoSave::TabSwitch => switched_from
+                .filter(|id| state.edited.contains_key(id))
+                .into_iter()
+                .collect(),
+        };
+        for id in due {
+            self.autosave_tab(id);
+        }
+    }
+
+    /// Save tab `id`, unless that needs asking: a file changed by another
+    /// program is left to the conflict prompt.
+    fn autosave_tab(&mut self, id: TabId) {
+        // Whatever happens, try again after another delay, not every frame
+        self.autosave.edited.insert(id, Instant::now());
+        if self.has_disk_conflict(id) {
+            return;
+        }
+        // The version from before this session of auto-saves is the one worth keeping
+        let backup = if self.autosave.backed_up.contains(&id) {
+            Backup::Off
+        } else {
+            self.backup()
+        };
+        let Some(tab) = self.tabs.iter_mut().find(|t| t.id == id) else {
+            return;
+        };
+        if tab.changed_on_disk().is_some() {
+            return;
+        }
+        match tab.save(backup) {
+            Ok(()) => {
+                self.autosave.edited.remove(&id);
+                self.autosave.backed_up.insert(id);
+                if self
+                    .autosave
+                    .error
+                    .as_ref()
+                    .is_some_and(|(e, _, _)| *e == id)
+                {
+                    self.autosave.error = None;
+                }
+            }
+            Err(e) => {
+                log::error!("Failed to auto-save {:?}: {}", tab.path, e);
+                let message = format!("⚠ Auto-save of \"{}\" failed: {}", tab.title, e);
+                self.autosave.error = Some((id, message, Instant::now()));
+            }
+        }
+    }
+
+    /// Status bar text about a failed auto-save.
+    pub(super) fn autosave_status(&self) -> Option<String> {
+        self.autosave
+            .error
+            .as_ref()
+            .filter(|(_, _, at)| at.elapsed() < ERROR_NOTICE_TIME)
+            .map(|(_, message, _)| message.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn dirty_tab(path: Option<&str>) -> EditorTab {
+        let mut tab = EditorTab::new(path.map(PathBuf::from), "text".to_string());
+        tab.is_dirty = true;
+        tab
+    }
+
+    #[test]
+    fn only_plain_dirty_files_are_auto_saved() {
+        assert!(can_autosave(&dirty_tab(Some("notes.txt"))));
+
+        let untitled = dirty_tab(None);
+        assert!(!can_autosave(&untitled));
+
+        let mut large = dirty_tab(Some("big.log"));
+        large.large_file = true;
+        assert!(!can_autosave(&large));
+
+        let mut read_only = dirty_tab(Some("notes.txt"));
+        read_only.read_only = true;
+        assert!(!can_autosave(&read_only));
+
+        let mut saved = dirty_tab(Some("notes.txt"));
+        saved.is_dirty = false;
+        assert!(!can_autosave(&saved));
+    }
+
+    #[test]
+    fn tabs_are_due_once_idle_for_the_delay() {
+        let now = Instant::now();
+        let delay = Duration::from_secs(5);
+        let (idle, recent, newest) = (TabId(1), TabId(2), TabId(3));
+        let edited = HashMap::from([
+            (idle, now - Duration::from_secs(6)),
+            (recent, now - Duration::from_secs(2)),
+            (newest, now - Duration::from_secs(1)),
+        ]);
+        let (due, next) = due_after_delay(&edited, delay, now);
+        assert_eq!(due, [idle]);
+        // The repaint comes when the first of the others is due
+        assert_eq!(next, Some(Duration::from_secs(3)));
+
+        let (due, next) = due_after_delay(&HashMap::new(), delay, now);
+        assert!(due.is_empty());
+        assert_eq!(next, None);
+    }
+}
@@ -0,0 +1,198 @@
+//! Files changed by other programs while they are open: clean tabs reload,
+//! tabs with unsaved edits and saves over a changed file ask first.
+
+use eframe::egui;
+
+use crate::dialogs::DiskConflictChoice;
+use crate::editor::{DiskChange, EditorTab, TabId};
+use crate::watcher::Watched;
+
+use super::NotosApp;
+
+/// A tab whose file changed while it had unsaved edits, or that is being saved
+/// over a changed file.
+pub(super) struct DiskConflict {
+    tab: TabId,
+    /// The file as it is now.
+    disk: EditorTab,
+    /// Raised by saving rather than by the watcher.
+    saving: bool,
+}
+
+impl NotosApp {
+    /// Handle the changes the watcher found, tell it which files to watch
+    /// and ask about the first conflict.
+    pub(super) fn poll_disk_changes(&mut self, ctx: &egui::Context) {
+        while let Some((path, change)) = self.file_watcher.try_recv() {
+            let Some(id) = self
+                .tabs
+                .iter()
+                .find(|t| t.path.as_ref() == Some(&path))
+                .map(|t| t.id)
+            else {
+                continue;
+            };
+            self.handle_disk_change(id, change);
+        }
+
+        // A tab with a conflict is compared with the file it was told about,
+        // so the same change is not reported again
+        let files = self
+            .tabs
+            .iter()
+            // Followed tabs are read as they grow instead
+            .filter(|tab| !self.followers.contains_key(&tab.id))
+            .filter_map(|tab| {
+                let conflict = self.disk_conflicts.iter().find(|c| c.tab == tab.id);
+                let state =
+                    conflict.map_or(tab.disk_state.as_ref(), |c| c.disk.disk_state.as_ref())?;
+                Some(Watched {
+                    path: tab.path.clone()?,
+                    state: state.clone(),
+                    encoding: tab.encoding,
+                })
+            })
+            .collect();
+        self.file_watcher.watch(files);
+
+        self.show_disk_conflict(ctx);
+    }
+
+    fn handle_disk_change(&mut self, id: TabId, change: DiskChange) {
+        let conflict = self.disk_conflicts.iter_mut().find(|c| c.tab == id);
+        let Some(tab) = self.tabs.iter_mut().find(|t| t.id == id) else {
+            return;
+        };
+        match change {
+            DiskChange::Touched(state) => match conflict {
+                Some(conflict) => conflict.disk.disk_state = Some(state),
+                None => tab.disk_state = Some(state),
+            },
+            DiskChange::Modified(disk) => {
+                if disk.content == tab.content {
+                    // Changed to what the tab already shows
+                    tab.disk_state = disk.disk_state;
+                    self.disk_conflicts.retain(|c| c.tab != id);
+                } else if tab.is_dirty || conflict.is_some() {
+                    self.raise_disk_conflict(DiskConflict {
+                        tab: id,
+                        disk: *disk,
+                        saving: false,
+                    });
+                } else {
+                    self.reload_tab(id, *disk);
+                }
+            }
+        }
+    }
+
+    /// Ask about `conflict`, replacing an earlier one for the same tab.
+    fn raise_disk_conflict(&mut self, mut conflict: DiskConflict) {
+        if let Some(earlier) = self
+            .disk_conflicts
+            .iter_mut()
+            .find(|c| c.tab == conflict.tab)
+        {
+            conflict.saving |= earlier.saving;
+            *earlier = conflict;
+        } else {
+            self.disk_conflicts.push(conflict);
+        }
+    }
+
+    /// Replace the content of tab `id` with the file as it is now, keeping
+    /// the cursor where it was. The reload can be undone.
+    fn reload_tab(&mut self, id: TabId, disk: EditorTab) {
+        let Some(tab) = self.tabs.iter_mut().find(|t| t.id == id) else {
+            return;
+        };
+        let (p, s) = tab.cursor_range.unwrap_or((0, 0));
+        let previous = std::mem::replace(&mut tab.content, disk.content);
+        self.undo_manager
+            .push_undo(tab.id, previous, p, tab.large_file);
+        tab.line_ending = disk.line_ending;
+        tab.large_file = disk.large_file;
+        tab.file_size = disk.file_size;
+        tab.disk_state = disk.disk_state;
+        tab.is_dirty = false;
+        tab.refresh_metadata();
+        tab.undo_snapshot = if tab.large_file {
+            String::new()
+        } else {
+            tab.content.clone()
+        };
+        tab.undo_snapshot_cursor = p.min(tab.char_count);
+        tab.cursor_range = Some((p.min(tab.char_count), s.min(tab.char_count)));
+    }
+
+    /// Whether tab `id` waits for an answer about its changed file.
+    pub(super) fn has_disk_conflict(&self, id: TabId) -> bool {
+        self.disk_conflicts.iter().any(|c| c.tab == id)
+    }
+
+    /// Save tab `id`, unless another program changed its file since it was
+    /// loaded or saved: then ask first.
+    pub(super) fn save_tab_checked(&mut self, id: TabId) {
+        let backup = self.backup();
+        let Some(tab) = self.tabs.iter_mut().find(|t| t.id == id) else {
+            return;
+        };
+        if let Some(disk) = tab.changed_on_disk() {
+            self.raise_disk_conflict(DiskConflict {
+                tab: id,
+                disk,
+                saving: true,
+            });
+        } else if let Err(e) = tab.save(backup) {
+            log::error!("Failed to save file: {}", e);
+        }
+    }
+
+    fn show_disk_conflict(&mut self, ctx: &egui::Context) {
+        self.disk_conflicts
+            .retain(|c| self.tabs.iter().any(|t| t.id == c.tab));
+        let Some(conflict) = self.disk_conflicts.first() else {
+            return;
+        };
+        let (id, saving) = (conflict.tab, conflict.saving);
+        let title = self
+            .tabs
+            .iter()
+            .find(|t| t.id == id)
+            .map(|t| t.title.clone())
+            .unwrap_or_default();
+        let Some(choice) = crate::dialogs::disk_conflict_prompt(ctx, &title, saving) else {
+            return;
+        };
+
+        if choice == DiskConflictChoice::Compare {
+            let Some(tab) = self.tabs.iter().find(|t| t.id == id) else {
+                return;
+            };
+            let diff_title = format!("{} (disk ↔ tab)", tab.title);
+            let (disk, mine) = (
+                self.disk_conflicts[0].disk.content.clone(),
+                tab.content.clone(),
+            );
+            self.open_text_diff(&disk, &mine, ("on disk", "unsaved"), diff_title);
+            return;
+        }
+
+        let conflict = self.disk_conflicts.remove(0);
+        let backup = self.backup();
+        match choice {
+            DiskConflictChoice::Reload => self.reload_tab(id, conflict.disk),
+            DiskConflictChoice::KeepMine | DiskConflictChoice::Overwrite => {
+                if let Some(tab) = self.tabs.iter_mut().find(|t| t.id == id) {
+                    tab.disk_state = conflict.disk.disk_state;
+                    if choice == DiskConflictChoice::Overwrite {
+                        if let Err(e) = tab.save(backup) {
+                            log::error!("Failed to save file: {}", e);
+                        }
+                    }
+                }
+            }
+            DiskConflictChoice::Cancel | DiskConflictChoice::Compare => {}
+        }
+    }
+}
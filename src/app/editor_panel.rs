@@ -25,7 +25,9 @@ impl NotosApp {
 
         let mut hovered_idx_out = None;
 
-        if let Some(tab) = self.tabs.get_mut(idx) {
+        // The other tabs stay readable for the plugins' context menu
+        let (tabs_before, rest) = self.tabs.split_at_mut(idx);
+        if let Some((tab, tabs_after)) = rest.split_first_mut() {
             let mut content_changed = false;
 
             let mut new_cursor_pos = None;
@@ -34,7 +36,9 @@ impl NotosApp {
             let mut deferred_action = DeferredAction::None;
             let previous_char_count_frame = tab.char_count;
 
-            egui::ScrollArea::vertical().id_salt(tab.id).show(ui, |ui| {
+            // A followed file stays scrolled to its end, unless scrolled up
+            let following = self.followers.contains_key(&tab.id);
+            egui::ScrollArea::vertical().id_salt(tab.id).stick_to_bottom(following).show(ui, |ui| {
                 let margin = 10.0;
                 let family = if self.editor_font_family == "Monospace" {
                     egui::FontFamily::Monospace
@@ -131,7 +135,17 @@ impl NotosApp {
                             egui::TextEdit::store_state(ui.ctx(), id, state);
                         }
 
-                        let mut text_edit = egui::TextEdit::multiline(&mut tab.content)
+                        // A read-only tab shows its content through an immutable buffer,
+                        // which egui lets the user select and copy but not edit
+                        let read_only_content = tab.read_only.then(|| std::mem::take(&mut tab.content));
+                        let mut read_only_view = read_only_content.as_deref().unwrap_or_default();
+                        let buffer: &mut dyn egui::TextBuffer = if read_only_content.is_some() {
+                            &mut read_only_view
+                        } else {
+                            &mut tab.content
+                        };
+
+                        let mut text_edit = egui::TextEdit::multiline(buffer)
                             .id(egui::Id::new("editor").with(tab.id))
                             .font(font_id.clone())
                             .frame(false)
@@ -150,6 +164,9 @@ impl NotosApp {
                         }
 
                         let output = text_edit.show(ui);
+                        if let Some(content) = read_only_content {
+                            tab.content = content;
+                        }
 
                         // Render Find Highlight (Undermost Layer) if Dialog Active
                         if self.find_dialog.open && !self.find_dialog.query.is_empty() && !tab.large_file {
@@ -421,6 +438,15 @@ impl NotosApp {
                         selection: tab.cursor_range,
                         hovered_char_idx: hovered_idx_out,
                         file_path: tab.path.as_deref(),
+                        active_tab: Some(tab.id.into()),
+                        tabs: notos_sdk::TabList::new(|| {
+                            tabs_before
+                                .iter()
+                                .chain(std::iter::once(&*tab))
+                                .chain(tabs_after.iter())
+                                .map(super::tab_info)
+                                .collect()
+                        }),
                     };
                     let can_undo = self.undo_manager.can_undo(tab.id);
                     let can_redo = self.undo_manager.can_redo(tab.id);
@@ -479,7 +505,7 @@ impl NotosApp {
                 DeferredAction::None => {}
                 DeferredAction::Plugin(p) => self.handle_plugin_action(p, ctx),
                 DeferredAction::Undo => {
-                    if let Some(tab) = self.tabs.iter_mut().find(|t| Some(t.id) == self.active_tab_id) {
+                    if let Some(tab) = self.tabs.iter_mut().find(|t| Some(t.id) == self.active_tab_id && !t.read_only) {
                         let (curr, _) = tab.cursor_range.unwrap_or((0, 0));
                         if let Some(entry) = self.undo_manager.undo(tab.id, tab.content.clone(), curr) {
                             tab.content = entry.content;
@@ -500,7 +526,7 @@ impl NotosApp {
                     }
                 }
                 DeferredAction::Redo => {
-                    if let Some(tab) = self.tabs.iter_mut().find(|t| Some(t.id) == self.active_tab_id) {
+                    if let Some(tab) = self.tabs.iter_mut().find(|t| Some(t.id) == self.active_tab_id && !t.read_only) {
                         let (curr, _) = tab.cursor_range.unwrap_or((0, 0));
                         if let Some(entry) = self.undo_manager.redo(tab.id, tab.content.clone(), curr) {
                             tab.content = entry.content;
@@ -24,9 +24,10 @@ impl NotosApp {
         self.loading_paths.insert(path.clone());
         let path_clone = path.clone();
         let tx = self.file_load_sender.clone();
+        let encoding = self.open_options.get(&path).and_then(|o| o.encoding);
 
         thread::spawn(move || {
-            let res = EditorTab::from_file(path_clone.clone());
+            let res = EditorTab::from_file_with_encoding(path_clone.clone(), encoding);
             let _ = tx.send((path_clone, res.map_err(|e| e.to_string())));
         });
 
@@ -42,9 +43,8 @@ impl NotosApp {
     pub(crate) fn save_file(&mut self) {
         if let Some(tab) = self.active_tab_mut() {
             if tab.path.is_some() {
-                if let Err(e) = tab.save() {
-                    log::error!("Failed to save file: {}", e);
-                }
+                let id = tab.id;
+                self.save_tab_checked(id);
             } else {
                 self.save_file_as();
             }
@@ -58,6 +58,7 @@ impl NotosApp {
     }
 
     pub(crate) fn save_tab_as_by_id(&mut self, id: TabId) {
+        let backup = self.backup();
         if let Some(tab) = self.tabs.iter_mut().find(|t| t.id == id) {
             if let Some(path) = FileDialog::new()
                 .add_filter("Text", &["txt", "md"])
@@ -71,7 +72,7 @@ impl NotosApp {
                 .save_file()
             {
                 tab.set_path(path.clone());
-                if let Err(e) = tab.save() {
+                if let Err(e) = tab.save(backup) {
                     log::error!("Failed to save file: {}", e);
                 } else {
                     self.add_to_recent(path);
@@ -0,0 +1,114 @@
+//! Follow mode for tabs, see [`crate::follow`].
+
+use eframe::egui;
+
+use crate::editor::{TabId, LARGE_FILE_THRESHOLD};
+use crate::follow::{FollowEvent, TAIL_BYTES};
+
+use super::NotosApp;
+
+impl NotosApp {
+    /// Start or stop following the file of tab `id`. While it is followed the
+    /// tab is read-only, and a tab with unsaved changes cannot be followed.
+    pub(super) fn toggle_follow(&mut self, id: TabId, ctx: &egui::Context) {
+        if let Some(handle) = self.followers.remove(&id) {
+            if let Some(tab) = self.tabs.iter_mut().find(|t| t.id == id) {
+                tab.read_only = handle.was_read_only;
+            }
+            return;
+        }
+        let Some(tab) = self.tabs.iter_mut().find(|t| t.id == id) else {
+            return;
+        };
+        let Some(path) = tab.path.clone().filter(|_| !tab.is_dirty) else {
+            return;
+        };
+        // The tab shows the file as loaded, unless that was only a preview
+        let resume_at = tab
+            .disk_state
+            .as_ref()
+            .filter(|_| !tab.large_file)
+            .map(|state| state.size);
+        let handle = crate::follow::spawn(
+            id,
+            path,
+            tab.encoding,
+            resume_at,
+            tab.read_only,
+            self.follow_sender.clone(),
+            ctx.clone(),
+        );
+        tab.read_only = true;
+        tab.cursor_range = Some((tab.char_count, tab.char_count));
+        tab.scroll_to_cursor = true;
+        self.followers.insert(id, handle);
+    }
+
+    /// For the View menu: whether the active tab is followed, or `None` if it
+    /// cannot be.
+    pub(super) fn follow_state(&self) -> Option<bool> {
+        let tab = self.active_tab()?;
+        if self.followers.contains_key(&tab.id) {
+            Some(true)
+        } else {
+            (tab.path.is_some() && !tab.is_dirty).then_some(false)
+        }
+    }
+
+    /// Apply what the followers read since the last frame.
+    pub(super) fn poll_followers(&mut self) {
+        self.followers
+            .retain(|id, _| self.tabs.iter().any(|t| t.id == *id));
+        while let Ok((id, event)) = self.follow_receiver.try_recv() {
+            // Read before following stopped
+            if !self.followers.contains_key(&id) {
+                continue;
+            }
+            let Some(tab) = self.tabs.iter_mut().find(|t| t.id == id) else {
+                continue;
+            };
+            let restarted = matches!(event, FollowEvent::Restarted { .. });
+            match event {
+                FollowEvent::Appended(text) => tab.append_text(&text),
+                FollowEvent::Restarted { text, whole_file } => {
+                    tab.content = text;
+                    tab.large_file = !whole_file;
+                    // The history is about text that is gone
+                    self.undo_manager.remove_tab(id);
+                }
+            }
+            // Only a new or cut text needs its metadata recomputed
+            let mut refresh = restarted;
+            let mut removed_chars = 0;
+            // Keep only the last part of a log that grew too large to show
+            if tab.content.len() as u64 > LARGE_FILE_THRESHOLD {
+                let keep_from = tab
+                    .content
+                    .floor_char_boundary(tab.content.len() - TAIL_BYTES as usize);
+                let cut = tab.content[keep_from..]
+                    .find('\n')
+                    .map_or(keep_from, |i| keep_from + i + 1);
+                removed_chars += tab.content[..cut].chars().count();
+                tab.content.drain(..cut);
+                tab.large_file = true;
+                refresh = true;
+            }
+            if refresh {
+                tab.refresh_metadata();
+                tab.undo_snapshot = if tab.large_file {
+                    String::new()
+                } else {
+                    tab.content.clone()
+                };
+            }
+            if restarted {
+                tab.cursor_range = Some((tab.char_count, tab.char_count));
+                tab.scroll_to_cursor = true;
+            } else {
+                let (p, s) = tab.cursor_range.unwrap_or((0, 0));
+                let shift = |i: usize| i.saturating_sub(removed_chars).min(tab.char_count);
+                tab.cursor_range = Some((shift(p), shift(s)));
+            }
+        }
+    }
+}
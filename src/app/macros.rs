@@ -0,0 +1,277 @@
+//! Host side of keyboard macros: recording input and commands, and playback.
+
+use eframe::egui;
+
+use crate::commands::{Command, CommandTarget};
+use crate::dialogs::{FindDialog, MacroDialogResult, MacroPrompt};
+use crate::editor::TabId;
+use crate::macros::{self, MacroMenu, MacroStep, Playback, Repeat, MAX_PLAYBACK_REPEATS};
+use crate::ui::MenuAction;
+
+use super::NotosApp;
+
+/// Command id prefix of saved macros.
+const SAVED_MACRO_PREFIX: &str = "macro.saved.";
+
+impl NotosApp {
+    /// Register one command per saved macro, so they can be bound to keys.
+    pub(crate) fn register_macro_commands(&mut self) {
+        self.commands
+            .retain(|c| !c.id.starts_with(SAVED_MACRO_PREFIX));
+        for name in self.macro_store.macros.keys() {
+            self.commands.register(Command {
+                id: format!("{}{}", SAVED_MACRO_PREFIX, name),
+                title: format!("Macro: {}", name),
+                default_keybindings: Vec::new(),
+                keybindings: Vec::new(),
+                target: CommandTarget::Host(MenuAction::PlaySavedMacro(name.clone())),
+            });
+        }
+        self.keymap.apply(&mut self.commands);
+    }
+
+    pub(crate) fn macro_menu(&self) -> MacroMenu {
+        MacroMenu {
+            recording: self.macro_recording.is_some(),
+            playing: self.macro_playback.is_some(),
+            has_macro: !self.last_macro.is_empty(),
+            saved: self.macro_store.macros.keys().cloned().collect(),
+        }
+    }
+
+    pub(crate) fn toggle_macro_recording(&mut self) {
+        if self.macro_playback.is_some() {
+            return;
+        }
+        match self.macro_recording.take() {
+            Some(steps) => {
+                if !steps.is_empty() {
+                    self.last_macro = steps;
+                }
+            }
+            None => self.macro_recording = Some(Vec::new()),
+        }
+    }
+
+    /// Capture this frame's editing input to the active editor. Runs after
+    /// shortcut dispatch, so keys bound to commands are recorded as commands.
+    pub(crate) fn record_macro_input(&mut self, ctx: &egui::Context) {
+        let Some(steps) = &mut self.macro_recording else {
+            return;
+        };
+        let Some(tab_id) = self.active_tab_id else {
+            return;
+        };
+        let editor_id = egui::Id::new("editor").with(tab_id);
+        if !ctx.memory(|m| m.has_focus(editor_id)) {
+            return;
+        }
+        ctx.input(|i| {
+            for step in i.events.iter().filter_map(MacroStep::from_event) {
+                macros::push_step(steps, step);
+            }
+        });
+    }
+
+    /// Record a Find Next jump made from the find dialog.
+    pub(crate) fn record_find_next(&mut self, query: String) {
+        if let Some(steps) = &mut self.macro_recording {
+            steps.push(MacroStep::FindNext(query));
+        }
+    }
+
+    /// Record running the command `id`, if it edits the document.
+    pub(crate) fn record_command(&mut self, id: &str) {
+        if let Some(steps) = &mut self.macro_recording {
+            if macros::is_recordable(id) {
+                steps.push(MacroStep::Command(id.to_string()));
+            }
+        }
+    }
+
+    /// Record a host action by the id of the command that runs it.
+    pub(crate) fn record_menu_action(&mut self, action: &MenuAction) {
+        if self.macro_recording.is_none() {
+            return;
+        }
+        let id = self
+            .commands
+            .iter()
+            .find(|c| matches!(&c.target, CommandTarget::Host(a) if a == action))
+            .map(|c| c.id.clone());
+        if let Some(id) = id {
+            self.record_command(&id);
+        }
+    }
+
+    pub(crate) fn ask_macro(&mut self, prompt: MacroPrompt) {
+        if !self.last_macro.is_empty() {
+            self.macro_dialog.ask(prompt);
+        }
+    }
+
+    pub(crate) fn show_macro_dialog(&mut self, ctx: &egui::Context) {
+        match self.macro_dialog.show(ctx) {
+            Some(MacroDialogResult::Play(n)) => {
+                self.play_macro(self.last_macro.clone(), Repeat::Times(n))
+            }
+            Some(MacroDialogResult::Save(name)) => self.save_macro(name),
+            None => {}
+        }
+    }
+
+    fn save_macro(&mut self, name: String) {
+        self.macro_store
+            .macros
+            .insert(name, self.last_macro.clone());
+        if let Err(e) = self.macro_store.save() {
+            log::error!("Failed to save macros: {}", e);
+        }
+        self.register_macro_commands();
+    }
+
+    pub(crate) fn delete_macro(&mut self, name: &str) {
+        if self.macro_store.macros.remove(name).is_some() {
+            if let Err(e) = self.macro_store.save() {
+                log::error!("Failed to save macros: {}", e);
+            }
+            self.register_macro_commands();
+        }
+    }
+
+    pub(crate) fn play_saved_macro(&mut self, name: &str) {
+        match self.macro_store.macros.get(name) {
+            Some(steps) => self.play_macro(steps.clone(), Repeat::Times(1)),
+            None => log::warn!("Unknown macro: {}", name),
+        }
+    }
+
+    /// Start playing `steps` on the active tab. All its edits undo as one step.
+    pub(crate) fn play_macro(&mut self, steps: Vec<MacroStep>, repeat: Repeat) {
+        if steps.is_empty() || self.macro_recording.is_some() || self.macro_playback.is_some() {
+            return;
+        }
+        let Some(tab_id) = self.active_tab_id else {
+            return;
+        };
+        self.flush_macro_undo(tab_id);
+        self.undo_manager.begin_group(tab_id);
+        self.macro_playback = Some(Playback {
+            steps,
+            tab_id,
+            repeat,
+            pos: 0,
+            done: 0,
+            start_cursor: self.macro_cursor(tab_id),
+        });
+    }
+
+    /// Run the next batch of the playing macro. Called once per frame before
+    /// the editor is drawn, so injected input reaches it in the same frame.
+    pub(crate) fn step_macro_playback(&mut self, ctx: &egui::Context) {
+        let Some(playback) = &mut self.macro_playback else {
+            return;
+        };
+        let tab_id = playback.tab_id;
+        if self.active_tab_id != Some(tab_id) {
+            self.finish_macro_playback();
+            return;
+        }
+
+        if playback.pos == playback.steps.len() {
+            // The last batch was applied during the previous frame
+            playback.done += 1;
+            let again = match playback.repeat {
+                Repeat::Times(n) => playback.done < n,
+                Repeat::ToEnd => {
+                    let start = playback.start_cursor;
+                    let cursor = self.macro_cursor(tab_id);
+                    let len = self
+                        .tabs
+                        .iter()
+                        .find(|t| t.id == tab_id)
+                        .map_or(0, |t| t.content.chars().count());
+                    let playback = self.macro_playback.as_mut().expect("checked above");
+                    playback.start_cursor = cursor;
+                    cursor > start && cursor < len && playback.done < MAX_PLAYBACK_REPEATS
+                }
+            };
+            if !again {
+                self.finish_macro_playback();
+                return;
+            }
+            self.macro_playback.as_mut().expect("checked above").pos = 0;
+        }
+
+        let batch = self
+            .macro_playback
+            .as_mut()
+            .expect("checked above")
+            .next_batch();
+        ctx.request_repaint();
+        match &batch[0] {
+            MacroStep::FindNext(query) => {
+                if let Some(tab) = self.tabs.iter_mut().find(|t| t.id == tab_id) {
+                    FindDialog::find_next(ctx, tab, query);
+                }
+            }
+            MacroStep::Command(id) => self.execute_command(id, ctx),
+            _ => {
+                let editor_id = egui::Id::new("editor").with(tab_id);
+                ctx.memory_mut(|m| m.request_focus(editor_id));
+                ctx.input_mut(|i| {
+                    i.events
+                        .extend(batch.iter().filter_map(MacroStep::to_event))
+                });
+            }
+        }
+    }
+
+    fn finish_macro_playback(&mut self) {
+        let Some(playback) = self.macro_playback.take() else {
+            return;
+        };
+        self.flush_macro_undo(playback.tab_id);
+        if let Some(tab) = self.tabs.iter_mut().find(|t| t.id == playback.tab_id) {
+            tab.last_edit_time = None;
+        }
+        self.undo_manager.end_group(playback.tab_id);
+    }
+
+    /// Push pending typing on the tab as an undo step.
+    fn flush_macro_undo(&mut self, tab_id: TabId) {
+        let Some(tab) = self.tabs.iter_mut().find(|t| t.id == tab_id) else {
+            return;
+        };
+        if tab.content != tab.undo_snapshot {
+            self.undo_manager.push_undo(
+                tab.id,
+                tab.undo_snapshot.clone(),
+                tab.undo_snapshot_cursor,
+                tab.large_file,
+            );
+            let (curr, _) = tab.cursor_range.unwrap_or((0, 0));
+            tab.undo_snapshot = tab.content.clone();
+            tab.undo_snapshot_cursor = curr;
+        }
+    }
+
+    fn macro_cursor(&self, tab_id: TabId) -> usize {
+        self.tabs
+            .iter()
+            .find(|t| t.id == tab_id)
+            .and_then(|t| t.cursor_range)
+            .map_or(0, |(a, b)| a.max(b))
+    }
+
+    /// Status bar text while a macro is being recorded or played.
+    pub(crate) fn macro_status(&self) -> Option<String> {
+        if let Some(steps) = &self.macro_recording {
+            return Some(format!("⏺ Recording macro ({} steps)", steps.len()));
+        }
+        self.macro_playback.as_ref().map(|p| match p.repeat {
+            Repeat::Times(n) if n > 1 => format!("▶ Playing macro ({}/{})", p.done + 1, n),
+            _ => "▶ Playing macro".to_string(),
+        })
+    }
+}
@@ -0,0 +1,263 @@
+//! Host side of plugin-contributed dockable panels.
+//!
+//! Plugins declare panels through `NotosPlugin::panels`; the host decides
+//! whether and where they are shown. Each panel's layout is keyed by
+//! `<plugin id>.<panel id>` and saved with the session, so layouts of plugins
+//! that are temporarily missing survive until they come back.
+
+use eframe::egui;
+use notos_sdk::{PanelLocation, PluginPanel};
+use serde::{Deserialize, Serialize};
+
+use crate::commands::{Command, CommandTarget};
+use crate::ui::MenuAction;
+
+use super::{get_ed_ctx, NotosApp};
+
+/// Command id prefix of the generated "toggle panel" commands.
+pub const TOGGLE_PANEL_PREFIX: &str = "view.toggle_panel.";
+
+/// Persisted layout of one plugin panel.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PanelLayout {
+    pub key: String,
+    pub visible: bool,
+    pub location: PanelLocation,
+    /// Width of side panels, height of bottom panels.
+    pub size: f32,
+    /// Position among panels docked to the same edge; lower is closer to the window edge.
+    pub order: u32,
+}
+
+/// A panel declared by a loaded plugin.
+pub struct RegisteredPanel {
+    pub plugin_id: String,
+    pub panel: PluginPanel,
+}
+
+impl RegisteredPanel {
+    pub fn key(&self) -> String {
+        format!("{}.{}", self.plugin_id, self.panel.id)
+    }
+}
+
+enum PanelChange {
+    Hide,
+    Dock(PanelLocation),
+    TowardEdge,
+    TowardEditor,
+}
+
+impl NotosApp {
+    /// Record the panels declared by loaded plugins and give new ones a default layout.
+    pub(crate) fn register_plugin_panels(&mut self) {
+        self.plugin_panels = self
+            .plugin_manager
+            .panels()
+            .into_iter()
+            .flat_map(|(plugin_id, panels)| {
+                panels.into_iter().map(move |panel| RegisteredPanel {
+                    plugin_id: plugin_id.clone(),
+                    panel,
+                })
+            })
+            .collect();
+
+        for registered in &self.plugin_panels {
+            let key = registered.key();
+            if !self.panel_layouts.iter().any(|l| l.key == key) {
+                let order = next_order(&self.panel_layouts, registered.panel.location);
+                self.panel_layouts.push(PanelLayout {
+                    key: key.clone(),
+                    visible: false,
+                    location: registered.panel.location,
+                    size: registered.panel.default_size,
+                    order,
+                });
+            }
+            self.commands.register(Command {
+                id: format!("{}{}", TOGGLE_PANEL_PREFIX, key),
+                title: format!("View: Toggle {} Panel", registered.panel.title),
+                default_keybindings: Vec::new(),
+                keybindings: Vec::new(),
+                target: CommandTarget::Host(MenuAction::TogglePanel(key)),
+            });
+        }
+    }
+
+    pub(crate) fn toggle_panel(&mut self, key: &str) {
+        if let Some(layout) = self.panel_layouts.iter_mut().find(|l| l.key == key) {
+            layout.visible = !layout.visible;
+        }
+    }
+
+    /// Make a panel visible by its key (see `PluginAction::ShowPanel`, which
+    /// the plugin manager gives the key of the emitting plugin's panel).
+    pub(crate) fn show_panel(&mut self, key: &str) {
+        match self.panel_layouts.iter_mut().find(|l| l.key == key) {
+            Some(layout) => layout.visible = true,
+            None => log::warn!("ShowPanel: no plugin declared a panel {:?}", key),
+        }
+    }
+
+    /// Entries for the View → Panels menu as `(key, title, visible)`.
+    pub(crate) fn panel_menu_entries(&self) -> Vec<(String, String, bool)> {
+        self.plugin_panels
+            .iter()
+            .map(|p| {
+                let key = p.key();
+                let visible = self.panel_layouts.iter().any(|l| l.key == key && l.visible);
+                (key, p.panel.title.clone(), visible)
+            })
+            .collect()
+    }
+
+    /// Draw every visible plugin panel. Must run before the central panel is shown.
+    pub(crate) fn show_plugin_panels(&mut self, ctx: &egui::Context) {
+        let mut visible: Vec<usize> = (0..self.panel_layouts.len())
+            .filter(|&i| self.panel_layouts[i].visible)
+            .collect();
+        visible.sort_by_key(|&i| self.panel_layouts[i].order);
+
+        let mut actions = Vec::new();
+        let mut changes = Vec::new();
+        for idx in visible {
+            let layout = self.panel_layouts[idx].clone();
+            let Some(registered) = self.plugin_panels.iter().find(|p| p.key() == layout.key) else {
+                continue;
+            };
+            let ed_ctx = get_ed_ctx(&self.tabs, self.active_tab_id, self.hovered_char_idx);
+            let plugin_manager = &mut self.plugin_manager;
+            // The dock edge is part of the id so egui does not reuse a stale size
+            let id = egui::Id::new("plugin_panel")
+                .with(&layout.key)
+                .with(layout.location);
+
+            let add_contents = |ui: &mut egui::Ui| {
+                let change = panel_header(ui, &registered.panel.title, layout.location);
+                ui.separator();
+                let action = plugin_manager.panel_ui(
+                    &registered.plugin_id,
+                    &registered.panel.id,
+                    ui,
+                    &ed_ctx,
+                );
+                (change, action)
+            };
+            let response = match layout.location {
+                PanelLocation::Left => egui::SidePanel::left(id)
+                    .resizable(true)
+                    .default_width(layout.size)
+                    .show(ctx, add_contents),
+                PanelLocation::Right => egui::SidePanel::right(id)
+                    .resizable(true)
+                    .default_width(layout.size)
+                    .show(ctx, add_contents),
+                PanelLocation::Bottom => egui::TopBottomPanel::bottom(id)
+                    .resizable(true)
+                    .default_height(layout.size)
+                    .show(ctx, add_contents),
+            };
+
+            let rect = response.response.rect;
+            self.panel_layouts[idx].size = match layout.location {
+                PanelLocation::Bottom => rect.height(),
+                _ => rect.width(),
+            };
+            let (change, action) = response.inner;
+            if let Some(change) = change {
+                changes.push((idx, change, registered.panel.default_size));
+            }
+            actions.push(action);
+        }
+
+        for (idx, change, default_size) in changes {
+            self.apply_panel_change(idx, change, default_size);
+        }
+        for action in actions {
+            self.handle_plugin_action(action, ctx);
+        }
+    }
+
+    fn apply_panel_change(&mut self, idx: usize, change: PanelChange, default_size: f32) {
+        match change {
+            PanelChange::Hide => self.panel_layouts[idx].visible = false,
+            PanelChange::Dock(location) => {
+                let order = next_order(&self.panel_layouts, location);
+                let layout = &mut self.panel_layouts[idx];
+                layout.location = location;
+                layout.order = order;
+                layout.size = default_size;
+            }
+            PanelChange::TowardEdge | PanelChange::TowardEditor => {
+                let location = self.panel_layouts[idx].location;
+                let order = self.panel_layouts[idx].order;
+                let toward_edge = matches!(change, PanelChange::TowardEdge);
+                // Swap with the nearest visible neighbour on the same edge
+                let neighbour = (0..self.panel_layouts.len())
+                    .filter(|&i| {
+                        let l = &self.panel_layouts[i];
+                        i != idx
+                            && l.visible
+                            && l.location == location
+                            && if toward_edge {
+                                l.order < order
+                            } else {
+                                l.order > order
+                            }
+                    })
+                    .min_by_key(|&i| self.panel_layouts[i].order.abs_diff(order));
+                if let Some(n) = neighbour {
+                    self.panel_layouts[idx].order = self.panel_layouts[n].order;
+                    self.panel_layouts[n].order = order;
+                }
+            }
+        }
+    }
+}
+
+fn next_order(layouts: &[PanelLayout], location: PanelLocation) -> u32 {
+    layouts
+        .iter()
+        .filter(|l| l.location == location)
+        .map(|l| l.order + 1)
+        .max()
+        .unwrap_or(0)
+}
+
+fn panel_header(ui: &mut egui::Ui, title: &str, location: PanelLocation) -> Option<PanelChange> {
+    let mut change = None;
+    ui.horizontal(|ui| {
+        ui.strong(title);
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            if ui.small_button("✖").on_hover_text("Hide panel").clicked() {
+                change = Some(PanelChange::Hide);
+            }
+            ui.menu_button("⋯", |ui| {
+                for (target, label) in [
+                    (PanelLocation::Left, "Dock Left"),
+                    (PanelLocation::Right, "Dock Right"),
+                    (PanelLocation::Bottom, "Dock Bottom"),
+                ] {
+                    if ui
+                        .add_enabled(target != location, egui::Button::new(label))
+                        .clicked()
+                    {
+                        change = Some(PanelChange::Dock(target));
+                        ui.close_menu();
+                    }
+                }
+                ui.separator();
+                if ui.button("Move Toward Window Edge").clicked() {
+                    change = Some(PanelChange::TowardEdge);
+                    ui.close_menu();
+                }
+                if ui.button("Move Toward Editor").clicked() {
+                    change = Some(PanelChange::TowardEditor);
+                    ui.close_menu();
+                }
+            });
+        });
+    });
+    change
+}
@@ -0,0 +1,129 @@
+//! Restoring unsaved edits after a crash, see [`crate::recovery`].
+
+use eframe::egui;
+
+use crate::dialogs::RecoveryChoice;
+use crate::editor::EditorTab;
+use crate::recovery::{Journal, RecoveredTab, Recovery};
+
+use super::NotosApp;
+
+impl NotosApp {
+    /// Look for edits that a crashed instance did not save, then start
+    /// journaling this instance's.
+    pub(super) fn start_recovery(&mut self) {
+        if let Some(mut recovery) = Recovery::find() {
+            // Edits that made it into the saved session are already back
+            recovery.tabs.retain(|r| {
+                !self
+                    .tabs
+                    .iter()
+                    .any(|t| t.id == r.tab && t.path == r.path && t.content == r.text)
+            });
+            if recovery.tabs.is_empty() {
+                recovery.finish();
+            } else {
+                self.recovery = Some(recovery);
+            }
+        }
+        match Journal::start() {
+            Ok(journal) => self.journal = Some(journal),
+            Err(e) => log::warn!("Failed to start the recovery journal: {}", e),
+        }
+    }
+
+    /// Journal the edits of this frame, and ask about the first recovered tab.
+    pub(super) fn poll_recovery(&mut self, ctx: &egui::Context) {
+        if let Some(journal) = &mut self.journal {
+            if let Some(wait) = journal.track(&self.tabs) {
+                ctx.request_repaint_after(wait);
+            }
+        }
+
+        let Some(recovered) = self.recovery.as_ref().and_then(|r| r.tabs.first()) else {
+            return;
+        };
+        let remaining = self.recovery.as_ref().map_or(0, |r| r.tabs.len());
+        let Some(choice) = crate::dialogs::recovery_prompt(ctx, &recovered.title, remaining) else {
+            return;
+        };
+
+        if choice == RecoveryChoice::Compare {
+            let (old, label) = self.before_recovery(recovered);
+            let new = recovered.text.clone();
+            let title = format!("{} (recovered)", recovered.title);
+            self.open_text_diff(&old, &new, (label, "recovered"), title);
+            return;
+        }
+
+        let Some(mut recovery) = self.recovery.take() else {
+            return;
+        };
+        let recovered = recovery.tabs.remove(0);
+        if choice == RecoveryChoice::Restore {
+            self.restore_tab(recovered);
+        }
+        if recovery.tabs.is_empty() {
+            recovery.finish();
+        } else {
+            self.recovery = Some(recovery);
+        }
+    }
+
+    /// What `recovered` is compared with: the tab it came from as the session
+    /// restored it, or else its file.
+    fn before_recovery(&self, recovered: &RecoveredTab) -> (String, &'static str) {
+        if let Some(tab) = self.recovered_tab(recovered) {
+            return (tab.content.clone(), "session");
+        }
+        recovered
+            .path
+            .clone()
+            .and_then(|path| EditorTab::from_file(path).ok())
+            .map_or((String::new(), "new"), |tab| (tab.content, "on disk"))
+    }
+
+    /// The tab `recovered` came from, if the session restored it.
+    fn recovered_tab(&self, recovered: &RecoveredTab) -> Option<&EditorTab> {
+        self.tabs
+            .iter()
+            .find(|t| t.id == recovered.tab && t.path == recovered.path)
+    }
+
+    /// Put the recovered edits back as unsaved changes, into the tab they came
+    /// from if it is open (undoably), or else into a new tab.
+    fn restore_tab(&mut self, recovered: RecoveredTab) {
+        if let Some(id) = self.recovered_tab(&recovered).map(|t| t.id) {
+            let Some(tab) = self.tabs.iter_mut().find(|t| t.id == id) else {
+                return;
+            };
+            let (p, s) = tab.cursor_range.unwrap_or((0, 0));
+            let previous = std::mem::replace(&mut tab.content, recovered.text);
+            self.undo_manager
+                .push_undo(tab.id, previous, p, tab.large_file);
+            tab.is_dirty = true;
+            tab.refresh_metadata();
+            tab.undo_snapshot = tab.content.clone();
+            tab.undo_snapshot_cursor = p.min(tab.char_count);
+            tab.cursor_range = Some((p.min(tab.char_count), s.min(tab.char_count)));
+            tab.scroll_to_cursor = true;
+            self.active_tab_id = Some(id);
+            return;
+        }
+
+        // Keep the encoding and line endings of the file, if it still exists
+        let mut tab = recovered
+            .path
+            .clone()
+            .and_then(|path| EditorTab::from_file(path).ok())
+            .filter(|tab| !tab.large_file)
+            .unwrap_or_else(|| EditorTab::new(recovered.path.clone(), String::new()));
+        tab.title = recovered.title;
+        tab.content = recovered.text;
+        tab.is_dirty = true;
+        tab.refresh_metadata();
+        tab.undo_snapshot = tab.content.clone();
+        self.active_tab_id = Some(tab.id);
+        self.tabs.push(tab);
+    }
+}
@@ -0,0 +1,372 @@
+//! Open requests from the command line, either this process's own or one
+//! received from another process over the single-instance socket, and
+//! remote-control calls received the same way.
+
+use eframe::egui;
+use notos_sdk::{PluginAction, TextEdit};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Sender;
+
+use crate::cli::OpenRequest;
+use crate::editor::{char_to_byte, EditorTab, Encoding, TabId};
+use crate::ipc::{Incoming, Reply, Request};
+use crate::rpc::{ActiveTab, Call, Insert, Position, Selection, Target};
+
+use super::NotosApp;
+
+/// How to open a file that is still loading in the background.
+#[derive(Clone, Default)]
+pub(super) struct OpenOptions {
+    pub(super) position: Option<(usize, usize)>,
+    /// Select from `position` to here instead of just moving the cursor.
+    pub(super) selection_end: Option<(usize, usize)>,
+    pub(super) read_only: bool,
+    pub(super) encoding: Option<Encoding>,
+}
+
+/// A `--wait` request, answered once none of its files or tabs is open any more.
+pub(super) struct Waiter {
+    paths: Vec<PathBuf>,
+    /// Tabs without a file, such as standard input or a diff.
+    tabs: Vec<TabId>,
+    /// The waiting process, or `None` when it is this one: then the window closes.
+    notify: Option<Sender<Reply>>,
+    /// Whether any of the files or tabs was open yet. If none ever is, the
+    /// wait ends with an error instead.
+    opened: bool,
+}
+
+impl NotosApp {
+    pub(crate) fn handle_ipc_request(&mut self, incoming: Incoming, ctx: &egui::Context) {
+        match incoming.request {
+            Request::Open(open) => {
+                self.open_request(open, incoming.connection, incoming.reply);
+                ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+            }
+            Request::Input { text } => self.append_input(incoming.connection, &text),
+            Request::InputEnd => {
+                self.input_tabs.remove(&incoming.connection);
+            }
+            Request::Call(call) => {
+                let reply = match self.remote_call(call, ctx) {
+                    Ok(result) => Reply::Result { result },
+                    Err(message) => Reply::Error { message },
+                };
+                if let Some(reply_tx) = incoming.reply {
+                    let _ = reply_tx.send(reply);
+                }
+            }
+        }
+        ctx.request_repaint();
+    }
+
+    /// Open the files of `request`, received on `connection`. Returns whether
+    /// anything was opened.
+    pub(super) fn open_request(
+        &mut self,
+        request: OpenRequest,
+        connection: u64,
+        notify: Option<Sender<Reply>>,
+    ) -> bool {
+        let mut opened_any = false;
+        let mut tabs = Vec::new();
+        for file in &request.files {
+            let options = OpenOptions {
+                position: file.line.map(|line| (line, file.column.unwrap_or(1))),
+                selection_end: None,
+                read_only: request.read_only,
+                encoding: request.encoding,
+            };
+            opened_any |= self.open_path_with(file.path.clone(), options);
+        }
+        if let Some((a, b)) = &request.diff {
+            tabs.extend(self.open_diff(a, b, request.encoding));
+        }
+        if request.stdin {
+            let tab = EditorTab {
+                title: "stdin".to_string(),
+                read_only: request.read_only,
+                ..EditorTab::default()
+            };
+            self.input_tabs.insert(connection, tab.id);
+            self.active_tab_id = Some(tab.id);
+            tabs.push(tab.id);
+            self.tabs.push(tab);
+        }
+        opened_any |= !tabs.is_empty();
+        if request.wait {
+            self.waiters.push(Waiter {
+                paths: request.wait_paths(),
+                tabs,
+                notify,
+                opened: false,
+            });
+        }
+        opened_any
+    }
+
+    /// Carry out a remote-control call, see [`crate::rpc`].
+    fn remote_call(
+        &mut self,
+        call: Call,
+        ctx: &egui::Context,
+    ) -> Result<serde_json::Value, String> {
+        match call {
+            Call::Goto(target) => self.show_target(target, false, ctx),
+            Call::Highlight(target) => self.show_target(target, true, ctx),
+            Call::Insert(insert) => self.remote_insert(insert, ctx),
+            Call::Active => self.active_state(),
+        }
+    }
+
+    /// Move the cursor to `target`, or select it, opening its file if needed.
+    fn show_target(
+        &mut self,
+        target: Target,
+        highlight: bool,
+        ctx: &egui::Context,
+    ) -> Result<serde_json::Value, String> {
+        let options = OpenOptions {
+            position: Some((target.line, target.column.unwrap_or(1))),
+            // A highlight without an end goes to the end of its last line
+            selection_end: highlight.then(|| {
+                let end_line = target.end_line.unwrap_or(target.line);
+                (end_line, target.end_column.unwrap_or(usize::MAX))
+            }),
+            ..OpenOptions::default()
+        };
+        match target.path {
+            Some(path) => {
+                if !path.is_absolute() {
+                    return Err(format!("not an absolute path: {}", path.display()));
+                }
+                let open = self.tabs.iter().any(|t| t.path.as_ref() == Some(&path));
+                if !open && !path.is_file() {
+                    return Err(format!("no such file: {}", path.display()));
+                }
+                self.open_path_with(path, options);
+            }
+            None => {
+                let tab = self.active_tab_mut().ok_or("no active tab")?;
+                apply_open_options(tab, &options);
+            }
+        }
+        ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+        Ok(serde_json::Value::Null)
+    }
+
+    /// Insert text into an open tab, at a position or over the selection.
+    fn remote_insert(
+        &mut self,
+        insert: Insert,
+        ctx: &egui::Context,
+    ) -> Result<serde_json::Value, String> {
+        let tab = match &insert.path {
+            Some(path) => self
+                .tabs
+                .iter()
+                .find(|t| t.path.as_ref() == Some(path))
+                .ok_or_else(|| format!("not open: {}", path.display()))?,
+            None => self.active_tab().ok_or("no active tab")?,
+        };
+        if tab.read_only {
+            return Err(format!("{} is read-only", tab.title));
+        }
+        let id = tab.id;
+        let action = match insert.line {
+            Some(line) => {
+                let char_idx = tab.char_index(line, insert.column.unwrap_or(1));
+                let byte = char_to_byte(&tab.content, char_idx);
+                PluginAction::ApplyEdits(vec![TextEdit::new(byte, byte, insert.text)])
+            }
+            None => PluginAction::ReplaceSelection(insert.text),
+        };
+        self.apply_plugin_action(action, Some(id), ctx);
+        Ok(serde_json::Value::Null)
+    }
+
+    /// The file and selection of the active tab.
+    fn active_state(&self) -> Result<serde_json::Value, String> {
+        let tab = self.active_tab().ok_or("no active tab")?;
+        let (a, b) = tab.cursor_range.unwrap_or((0, 0));
+        let (start, end) = (a.min(b), a.max(b));
+        let position = |char_idx| {
+            let (line, column) = tab.position_of(char_idx);
+            Position { line, column }
+        };
+        let text = &tab.content[char_to_byte(&tab.content, start)..char_to_byte(&tab.content, end)];
+        let state = ActiveTab {
+            path: tab.path.clone(),
+            title: tab.title.clone(),
+            dirty: tab.is_dirty,
+            read_only: tab.read_only,
+            selection: Selection {
+                start: position(start),
+                end: position(end),
+                text: text.to_string(),
+            },
+        };
+        serde_json::to_value(state).map_err(|e| e.to_string())
+    }
+
+    /// Append standard input received on `connection` to its tab. The view
+    /// follows the new text while the cursor is at the end.
+    fn append_input(&mut self, connection: u64, text: &str) {
+        let Some(&id) = self.input_tabs.get(&connection) else {
+            return;
+        };
+        let Some(tab) = self.tabs.iter_mut().find(|t| t.id == id) else {
+            self.input_tabs.remove(&connection);
+            return;
+        };
+        let at_end = tab
+            .cursor_range
+            .is_none_or(|(p, s)| p == s && p >= tab.char_count);
+        tab.append_text(text);
+        tab.is_dirty = !tab.read_only;
+        if at_end {
+            tab.cursor_range = Some((tab.char_count, tab.char_count));
+            tab.scroll_to_cursor = true;
+        }
+    }
+
+    /// Whether `id` is a tab that standard input is still being read into.
+    pub(super) fn is_input_tab(&self, id: TabId) -> bool {
+        self.input_tabs.values().any(|&tab| tab == id)
+    }
+
+    /// Open `path` with `options`. A file that does not exist yet gets an
+    /// empty tab and is created on save.
+    fn open_path_with(&mut self, path: PathBuf, options: OpenOptions) -> bool {
+        if path.is_dir() {
+            log::warn!("Cannot open a directory: {:?}", path);
+            return false;
+        }
+        if !path.exists() && !self.tabs.iter().any(|t| t.path.as_ref() == Some(&path)) {
+            let mut tab = EditorTab::new(Some(path), String::new());
+            if let Some(encoding) = options.encoding {
+                tab.encoding = encoding;
+            }
+            apply_open_options(&mut tab, &options);
+            self.active_tab_id = Some(tab.id);
+            self.tabs.push(tab);
+            return true;
+        }
+        self.open_options.insert(path.clone(), options);
+        let opened = self.open_path(path.clone());
+        // Already open: nothing to wait for
+        if let Some(tab) = self
+            .tabs
+            .iter_mut()
+            .find(|t| t.path.as_ref() == Some(&path))
+        {
+            if let Some(options) = self.open_options.remove(&path) {
+                apply_open_options(tab, &options);
+            }
+        }
+        opened
+    }
+
+    /// Options to apply to `path` once it has loaded.
+    pub(super) fn take_open_options(&mut self, path: &Path) -> Option<OpenOptions> {
+        self.open_options.remove(path)
+    }
+
+    /// Open a read-only tab with the unified diff of two files.
+    fn open_diff(&mut self, a: &Path, b: &Path, encoding: Option<Encoding>) -> Option<TabId> {
+        let load = |path: &Path| {
+            EditorTab::from_file_with_encoding(path.to_path_buf(), encoding)
+                .map(|tab| tab.content)
+                .map_err(|e| log::error!("Failed to read {:?} for diff: {}", path, e))
+        };
+        let (Ok(old), Ok(new)) = (load(a), load(b)) else {
+            return None;
+        };
+
+        let name = |path: &Path| {
+            path.file_name().map_or_else(
+                || path.display().to_string(),
+                |n| n.to_string_lossy().into_owned(),
+            )
+        };
+        let labels = (a.display().to_string(), b.display().to_string());
+        let title = format!("{} ↔ {}", name(a), name(b));
+        Some(self.open_text_diff(&old, &new, (&labels.0, &labels.1), title))
+    }
+
+    /// Open a read-only tab with the unified diff of two texts, headed by `labels`.
+    pub(super) fn open_text_diff(
+        &mut self,
+        old: &str,
+        new: &str,
+        labels: (&str, &str),
+        title: String,
+    ) -> TabId {
+        let diff = similar::TextDiff::from_lines(old, new);
+        let mut text = diff.unified_diff().header(labels.0, labels.1).to_string();
+        if text.is_empty() {
+            text = "Files are identical.\n".to_string();
+        }
+
+        let mut tab = EditorTab::new(None, text);
+        tab.title = title;
+        tab.read_only = true;
+        let id = tab.id;
+        self.active_tab_id = Some(id);
+        self.tabs.push(tab);
+        id
+    }
+
+    /// Answer the `--wait` requests whose files and tabs have all been closed,
+    /// or could not be opened in the first place.
+    pub(super) fn poll_waiters(&mut self, ctx: &egui::Context) {
+        for mut waiter in std::mem::take(&mut self.waiters) {
+            let file_open = waiter
+                .paths
+                .iter()
+                .any(|path| self.tabs.iter().any(|t| t.path.as_ref() == Some(path)));
+            let tab_open = self.tabs.iter().any(|t| waiter.tabs.contains(&t.id));
+            let loading = waiter
+                .paths
+                .iter()
+                .any(|path| self.loading_paths.contains(path));
+            waiter.opened |= file_open || tab_open;
+            if file_open || tab_open || loading {
+                self.waiters.push(waiter);
+                continue;
+            }
+            let reply = if waiter.opened {
+                Reply::Closed
+            } else {
+                Reply::Error {
+                    message: "nothing to wait for: no file could be opened".to_string(),
+                }
+            };
+            match waiter.notify {
+                Some(notify) => {
+                    let _ = notify.send(reply);
+                }
+                None => {
+                    if let Reply::Error { message } = reply {
+                        // This process was started from the command line
+                        crate::utils::attach_console();
+                        eprintln!("notos: {}", message);
+                    }
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                }
+            }
+        }
+    }
+}
+
+pub(super) fn apply_open_options(tab: &mut EditorTab, options: &OpenOptions) {
+    if options.read_only {
+        tab.read_only = true;
+    }
+    if let Some((line, column)) = options.position {
+        match options.selection_end {
+            Some(end) => tab.select((line, column), end),
+            None => tab.go_to(line, column),
+        }
+    }
+}
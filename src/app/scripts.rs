@@ -0,0 +1,189 @@
+//! Host side of user scripts: command registration, running and applying results.
+
+use eframe::egui;
+use std::time::{Duration, Instant};
+
+use crate::commands::{Command, CommandTarget, Keybinding};
+use crate::scripting::{self, ScriptInput, ScriptJob, SCRIPT_COMMAND_PREFIX};
+use crate::ui::MenuAction;
+
+use super::NotosApp;
+
+/// How long a script's result or error stays in the status bar.
+const SCRIPT_NOTICE_TIME: Duration = Duration::from_secs(6);
+
+impl NotosApp {
+    /// (Re-)read the scripts folder and register one command per script.
+    pub(crate) fn register_scripts(&mut self) {
+        self.scripts = scripting::discover();
+        self.commands
+            .retain(|c| !c.id.starts_with(SCRIPT_COMMAND_PREFIX));
+        for script in &self.scripts {
+            let keybindings: Vec<Keybinding> = script
+                .keybinding
+                .as_deref()
+                .and_then(|k| {
+                    let binding = Keybinding::parse(k);
+                    if binding.is_none() {
+                        log::warn!("Script {} declares invalid keybinding {:?}", script.id, k);
+                    }
+                    binding
+                })
+                .into_iter()
+                .collect();
+            self.commands.register(Command {
+                id: script.command_id(),
+                title: format!("Script: {}", script.title),
+                default_keybindings: keybindings.clone(),
+                keybindings,
+                target: CommandTarget::Host(MenuAction::RunScript(script.id.clone())),
+            });
+        }
+        self.keymap.apply(&mut self.commands);
+    }
+
+    /// Start the script `id` on the active tab. Only one script runs at a time.
+    pub(crate) fn run_script(&mut self, id: &str, ctx: &egui::Context) {
+        if let Some(job) = &self.script_job {
+            self.set_script_notice(format!("\"{}\" is still running", job.title));
+            return;
+        }
+        let Some(script) = self.scripts.iter().find(|s| s.id == id) else {
+            log::warn!("Unknown script: {}", id);
+            return;
+        };
+        let Some(tab) = self.tabs.iter().find(|t| Some(t.id) == self.active_tab_id) else {
+            return;
+        };
+        let (a, b) = tab.cursor_range.unwrap_or((0, 0));
+        let input = ScriptInput {
+            text: tab.content.clone(),
+            selection: (a.min(b), a.max(b)),
+            path: tab.path.clone(),
+        };
+        self.script_job = Some(ScriptJob::spawn(script, tab.id, input, ctx.clone()));
+    }
+
+    pub(crate) fn cancel_script(&mut self) {
+        if let Some(job) = &self.script_job {
+            job.cancel();
+        }
+    }
+
+    pub(crate) fn new_script(&mut self) {
+        match scripting::create_script() {
+            Ok(path) => {
+                self.open_path(path);
+                self.register_scripts();
+            }
+            Err(e) => log::error!("Failed to create script: {}", e),
+        }
+    }
+
+    /// Apply the result of the running script once it has finished.
+    pub(crate) fn poll_script(&mut self, ctx: &egui::Context) {
+        let Some(result) = self.script_job.as_ref().and_then(|job| job.poll()) else {
+            return;
+        };
+        let job = self.script_job.take().expect("polled job exists");
+        let output = match result {
+            Ok(output) => output,
+            Err(e) => {
+                log::warn!("Script \"{}\" failed: {}", job.title, e);
+                self.set_script_notice(format!("Script \"{}\": {}", job.title, e));
+                return;
+            }
+        };
+
+        let backup = self.backup();
+        let Some(tab) = self.tabs.iter_mut().find(|t| t.id == job.tab_id) else {
+            self.set_script_notice(format!("Script \"{}\": its tab was closed", job.title));
+            return;
+        };
+        if output.text.is_some() && scripting::hash_text(&tab.content) != job.text_hash {
+            self.set_script_notice(format!(
+                "Script \"{}\": the document changed while it ran, result discarded",
+                job.title
+            ));
+            return;
+        }
+
+        if let Some(text) = output.text {
+            if tab.read_only && text != tab.content {
+                self.set_script_notice(format!(
+                    "Script \"{}\": the document is read-only, result discarded",
+                    job.title
+                ));
+                return;
+            }
+            if text != tab.content {
+                let (curr, _) = tab.cursor_range.unwrap_or((0, 0));
+                self.undo_manager
+                    .push_undo(tab.id, tab.content.clone(), curr, tab.large_file);
+                tab.content = text;
+                tab.is_dirty = true;
+                tab.refresh_metadata();
+                tab.undo_snapshot = tab.content.clone();
+                tab.undo_snapshot_cursor = curr;
+            }
+        }
+        let len = tab.content.chars().count();
+        let selection = output
+            .selection
+            .or(tab.cursor_range)
+            .map(|(a, b)| (a.min(len), b.min(len)));
+        if let Some((start, end)) = selection {
+            let id = egui::Id::new("editor").with(tab.id);
+            let mut state = egui::TextEdit::load_state(ctx, id).unwrap_or_default();
+            state
+                .cursor
+                .set_char_range(Some(egui::text::CCursorRange::two(
+                    egui::text::CCursor::new(start),
+                    egui::text::CCursor::new(end),
+                )));
+            egui::TextEdit::store_state(ctx, id, state);
+            tab.cursor_range = Some((start, end));
+            tab.scroll_to_cursor = true;
+        }
+
+        match output.save {
+            Some(Some(path)) => {
+                tab.set_path(path.clone());
+                match tab.save(backup) {
+                    Ok(()) => self.add_to_recent(path),
+                    Err(e) => log::error!("Failed to save file: {}", e),
+                }
+            }
+            Some(None) if tab.path.is_some() => {
+                let id = tab.id;
+                self.save_tab_checked(id);
+            }
+            Some(None) => {
+                let id = tab.id;
+                self.save_tab_as_by_id(id);
+            }
+            None => {}
+        }
+        for path in output.open {
+            self.open_path(path);
+        }
+        if let Some(message) = output.message {
+            self.set_script_notice(message);
+        }
+    }
+
+    fn set_script_notice(&mut self, notice: String) {
+        self.script_notice = Some((notice, Instant::now()));
+    }
+
+    /// Status bar text about scripts: the running script, or a recent result.
+    pub(crate) fn script_status(&self) -> Option<String> {
+        if let Some(job) = &self.script_job {
+            return Some(format!("⏳ Running \"{}\"...", job.title));
+        }
+        self.script_notice
+            .as_ref()
+            .filter(|(_, at)| at.elapsed() < SCRIPT_NOTICE_TIME)
+            .map(|(notice, _)| notice.clone())
+    }
+}
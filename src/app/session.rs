@@ -27,6 +27,8 @@ pub struct SessionState {
     #[serde(default)]
     pub recent_files: Vec<std::path::PathBuf>,
     #[serde(default)]
+    pub panels: Vec<super::PanelLayout>,
+    #[serde(default)]
     pub undo_state: crate::undo_manager::PersistentUndoState,
 }
 
@@ -59,6 +61,7 @@ impl SessionState {
         editor_font_family: &str,
         custom_fonts: &std::collections::HashMap<String, Vec<u8>>,
         recent_files: &[std::path::PathBuf],
+        panels: &[super::PanelLayout],
         undo_state: crate::undo_manager::PersistentUndoState,
     ) -> std::result::Result<(), Box<dyn std::error::Error>> {
         const MAX_TAB_SIZE: usize = 128 * 1024 * 1024; // 128 MB
@@ -89,6 +92,7 @@ impl SessionState {
             editor_font_family: editor_font_family.to_string(),
             custom_fonts: custom_fonts.clone(),
             recent_files: recent_files.to_vec(),
+            panels: panels.to_vec(),
             undo_state,
         };
 
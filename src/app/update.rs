@@ -1,6 +1,7 @@
 use eframe::egui;
 use rfd::FileDialog;
 
+use crate::dialogs::KeybindingEdit;
 use crate::editor::EditorTab;
 
 use super::get_ed_ctx;
@@ -11,13 +12,8 @@ use super::NotosApp;
 impl eframe::App for NotosApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         // Handle IPC messages (files opened from other instances)
-        while let Ok(path_str) = self.ipc_receiver.try_recv() {
-            let path = std::path::PathBuf::from(path_str);
-            if path.exists() && path.is_file() {
-                self.open_path(path);
-                ctx.request_repaint();
-                ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
-            }
+        while let Ok(incoming) = self.ipc_receiver.try_recv() {
+            self.handle_ipc_request(incoming, ctx);
         }
 
         // Handle background file loads
@@ -25,17 +21,26 @@ impl eframe::App for NotosApp {
             self.loading_paths.remove(&path);
             match result {
                 Ok(mut tab) => {
-                    self.add_to_recent(path);
                     tab.scroll_to_cursor = true;
+                    if let Some(options) = self.take_open_options(&path) {
+                        super::remote::apply_open_options(&mut tab, &options);
+                    }
+                    self.add_to_recent(path);
                     self.active_tab_id = Some(tab.id);
                     self.tabs.push(tab);
                     ctx.request_repaint();
                 }
                 Err(e) => {
+                    self.take_open_options(&path);
                     log::error!("Background load failed for {:?}: {}", path, e);
                 }
             }
         }
+        self.poll_waiters(ctx);
+        self.poll_followers();
+        self.poll_disk_changes(ctx);
+        self.poll_recovery(ctx);
+        self.poll_autosave(ctx);
 
         // Periodic session save (every 30 seconds)
         if self.last_session_save.elapsed() >= std::time::Duration::from_secs(30) {
@@ -94,13 +99,17 @@ impl eframe::App for NotosApp {
         }
 
         self.handle_shortcuts(ctx);
+        self.record_macro_input(ctx);
+        self.step_macro_playback(ctx);
 
         // Dialogs
         let active_tab = self
             .tabs
             .iter_mut()
             .find(|t| Some(t.id) == self.active_tab_id);
-        self.find_dialog.show(ctx, active_tab, &mut self.undo_manager);
+        if let Some(query) = self.find_dialog.show(ctx, active_tab, &mut self.undo_manager) {
+            self.record_find_next(query);
+        }
 
         let active_tab = self
             .tabs
@@ -108,10 +117,58 @@ impl eframe::App for NotosApp {
             .find(|t| Some(t.id) == self.active_tab_id);
         self.goto_dialog.show(ctx, active_tab);
 
+        self.show_macro_dialog(ctx);
+
+        if let Some(id) = self.command_palette.show(ctx, &self.commands) {
+            self.execute_command(&id, ctx);
+        }
+
+        self.settings_window
+            .show(ctx, &self.settings_schemas, &mut self.plugin_settings);
+        self.plugin_settings.save_if_dirty();
+        self.poll_script(ctx);
+        for action in self.plugin_manager.take_job_results() {
+            self.handle_plugin_action(action, ctx);
+        }
+
+        if let Some(plugin) = self.plugin_manager.pending_trust() {
+            let path = plugin.path.clone();
+            if let Some(decision) = crate::dialogs::trust_prompt(ctx, plugin) {
+                if self.plugin_manager.resolve_trust(&path, decision, ctx, &mut self.plugin_settings) {
+                    self.register_plugin_contributions();
+                }
+            }
+        }
+
+        if self
+            .plugin_manager
+            .poll_dev_reload(ctx, &mut self.plugin_settings)
+        {
+            self.register_plugin_contributions();
+        }
+
+        if let Some(edit) = self
+            .keybinding_editor
+            .show(ctx, &self.commands, &self.keymap)
+        {
+            match edit {
+                KeybindingEdit::Set(id, bindings) => self.keymap.set(&id, bindings),
+                KeybindingEdit::Reset(id) => self.keymap.reset(&id),
+            }
+            self.keymap.apply(&mut self.commands);
+            if let Err(e) = self.keymap.save() {
+                log::error!("Failed to save keybindings: {}", e);
+            }
+        }
+
         // Close Confirmation
+        let backup = self.backup();
         let save_fn = |tab: &mut EditorTab| -> std::result::Result<(), Box<dyn std::error::Error>> {
-            if tab.path.is_some() {
-                tab.save()
+            if tab.path.is_some() && tab.changed_on_disk().is_some() {
+                // Answered by the conflict prompt first
+                Err("The file was changed by another program".into())
+            } else if tab.path.is_some() {
+                tab.save(backup)
             } else if let Some(path) = FileDialog::new()
                 .add_filter("Text", &["txt", "md"])
                 .add_filter("Rust", &["rs", "toml"])
@@ -124,7 +181,7 @@ impl eframe::App for NotosApp {
                 .save_file()
             {
                 tab.set_path(path);
-                tab.save()
+                tab.save(backup)
             } else {
                 Err("Cancelled".into())
             }
@@ -145,10 +202,15 @@ impl eframe::App for NotosApp {
         let mut plugin_action_to_run_top = notos_sdk::PluginAction::None;
         let mut tab_action_to_run = None;
 
+        let panel_entries = self.panel_menu_entries();
+        let running_script = self.script_job.as_ref().map(|job| job.title.clone());
+        let macro_menu = self.macro_menu();
+        let following = self.follow_state();
         let plugin_manager = &mut self.plugin_manager;
-        let word_wrap = &mut self.word_wrap;
-        let show_line_numbers = &mut self.show_line_numbers;
-        let dark_mode = &mut self.dark_mode;
+        let commands = &self.commands;
+        let word_wrap = self.word_wrap;
+        let show_line_numbers = self.show_line_numbers;
+        let dark_mode = self.dark_mode;
         let editor_font_family = &self.editor_font_family;
         let custom_fonts = &self.custom_fonts;
         let recent_files = &self.recent_files;
@@ -164,12 +226,20 @@ impl eframe::App for NotosApp {
             )
             .show(ctx, |ui| {
                 let ed_ctx = get_ed_ctx(tabs, active_tab_id, self.hovered_char_idx);
-                let (m, p) = crate::ui::menu_bar(
-                    ui,
-                    plugin_manager,
+                let menu_state = crate::ui::MenuState {
+                    commands,
                     word_wrap,
                     show_line_numbers,
                     dark_mode,
+                    following,
+                    panels: &panel_entries,
+                    running_script: running_script.as_deref(),
+                    macros: &macro_menu,
+                };
+                let (m, p) = crate::ui::menu_bar(
+                    ui,
+                    plugin_manager,
+                    menu_state,
                     editor_font_family,
                     custom_fonts,
                     recent_files,
@@ -219,6 +289,16 @@ impl eframe::App for NotosApp {
         }
 
         // Bottom Panel: Status Bar
+        let mut status_plugin_action = notos_sdk::PluginAction::None;
+        let dev_mode = self.plugin_manager.dev_mode();
+        let script_status = self
+            .macro_status()
+            .or_else(|| self.script_status())
+            .or_else(|| self.autosave_status());
+        if script_status.is_some() && self.script_job.is_none() {
+            // Clear the notice once it expires
+            ctx.request_repaint_after(std::time::Duration::from_secs(1));
+        }
         egui::TopBottomPanel::bottom("bottom_panel")
             .frame(
                 egui::Frame::default()
@@ -226,13 +306,27 @@ impl eframe::App for NotosApp {
                     .inner_margin(egui::Margin::symmetric(8.0, 4.0)),
             )
             .show(ctx, |ui| {
-                if let Some(action) = crate::ui::status_bar(
-                    ui,
-                    &self.tabs,
-                    self.active_tab_id,
-                    self.current_cursor_pos,
-                    self.editor_font_size,
-                ) {
+                let (action, plugin_action) = {
+                    let ed_ctx = get_ed_ctx(&self.tabs, self.active_tab_id, self.hovered_char_idx);
+                    crate::ui::status_bar(
+                        ui,
+                        &mut self.plugin_manager,
+                        &ed_ctx,
+                        &self.tabs,
+                        self.active_tab_id,
+                        self.current_cursor_pos,
+                        self.editor_font_size,
+                        self.keymap
+                            .pending()
+                            .map(|k| format!("({}) was pressed. Waiting for second key...", k))
+                            .or(script_status)
+                            .or_else(|| dev_mode.then(|| "🔧 Plugin dev mode".to_string()))
+                            .as_deref(),
+                        self.active_tab_id.is_some_and(|id| self.followers.contains_key(&id)),
+                    )
+                };
+                status_plugin_action = plugin_action;
+                if let Some(action) = action {
                     match action {
                         crate::ui::StatusBarAction::SwitchTab(id) => self.active_tab_id = Some(id),
                         crate::ui::StatusBarAction::CloseTab(id) => self.close_tab(id),
@@ -242,6 +336,8 @@ impl eframe::App for NotosApp {
                                 tab.is_dirty = true;
                             }
                         }
+                        crate::ui::StatusBarAction::CancelJob(id) => self.plugin_manager.cancel_job(id),
+                        crate::ui::StatusBarAction::ToggleFollow(id) => self.toggle_follow(id, ctx),
                         crate::ui::StatusBarAction::SetEncoding(id, enc) => {
                             if let Some(tab) = self.tabs.iter_mut().find(|t| t.id == id) {
                                 tab.encoding = enc;
@@ -251,6 +347,10 @@ impl eframe::App for NotosApp {
                     }
                 }
             });
+        self.handle_plugin_action(status_plugin_action, ctx);
+
+        // Plugin panels sit between the status bar and the editor
+        self.show_plugin_panels(ctx);
 
         // Central Panel: Editor
         egui::CentralPanel::default()
@@ -272,8 +372,10 @@ impl eframe::App for NotosApp {
                 self.show_editor_panel(ctx, ui);
             });
 
-        let ed_ctx = get_ed_ctx(&self.tabs, self.active_tab_id, self.hovered_char_idx);
-        let plugin_action = self.plugin_manager.ui(ctx, &ed_ctx);
+        let plugin_action = {
+            let ed_ctx = get_ed_ctx(&self.tabs, self.active_tab_id, self.hovered_char_idx);
+            self.plugin_manager.ui(ctx, &ed_ctx)
+        };
         if let notos_sdk::PluginAction::UnderlineRegion(start, end) = plugin_action {
             self.next_underline = Some((start, end));
             ctx.request_repaint();
@@ -285,5 +387,10 @@ impl eframe::App for NotosApp {
 
     fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
         self.plugin_manager.on_unload();
+        self.plugin_settings.save_if_dirty();
+        // The session has everything unsaved now
+        if let Some(journal) = &mut self.journal {
+            journal.close();
+        }
     }
 }
@@ -0,0 +1,439 @@
+//! Command-line parsing.
+//!
+//! Everything that decides which files to open and how ends up in an
+//! [`OpenRequest`], which is also what a second invocation sends to the
+//! running instance, so both paths handle the options the same way.
+
+use serde::{Deserialize, Serialize};
+use std::ffi::OsString;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::editor::{Encoding, StreamDecoder};
+
+pub const USAGE: &str = "\
+Usage: notos [OPTIONS] [FILE[:LINE[:COLUMN]]]...
+       notos --headless --run COMMAND [FILE] [-o OUTPUT]
+       notos --call METHOD [PARAMS]
+
+Use - as FILE to read standard input into a new tab, e.g. `tail -f log | notos -`.
+
+Options:
+  +LINE[:COLUMN]       Put the cursor at this position in the next file
+  -n, --new-window     Open a new window instead of using the running one
+  -w, --wait           Return only after the files have been closed (for $EDITOR)
+  -r, --readonly       Open the files read-only
+  -e, --encoding NAME  Decode the files as NAME (utf-8, windows-1252, utf-16le, utf-16be)
+  -d, --diff A B       Show the differences between files A and B
+      --plugin PATH    Also load plugins from PATH (file or directory)
+      --headless       Run COMMAND on FILE (or standard input) without a window
+      --run COMMAND    The plugin command to run, e.g. notos_json_format.format
+  -o, --output PATH    Where --headless writes the result (default: standard output)
+      --call METHOD    Send a remote-control call to the running instance, with
+                       optional JSON PARAMS, and print the result
+  -h, --help           Print this help
+  -V, --version        Print the version
+";
+
+/// A file to open, with an optional 1-based cursor position.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FileArg {
+    pub path: PathBuf,
+    #[serde(default)]
+    pub line: Option<usize>,
+    #[serde(default)]
+    pub column: Option<usize>,
+}
+
+/// Files to open and how to open them.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct OpenRequest {
+    pub files: Vec<FileArg>,
+    #[serde(default)]
+    pub read_only: bool,
+    /// Decode with this encoding instead of detecting it.
+    #[serde(default)]
+    pub encoding: Option<Encoding>,
+    /// Report back once all the files have been closed.
+    #[serde(default)]
+    pub wait: bool,
+    /// Show the differences between two files.
+    #[serde(default)]
+    pub diff: Option<(PathBuf, PathBuf)>,
+    /// Open a tab with standard input, which keeps growing while input arrives.
+    #[serde(default)]
+    pub stdin: bool,
+}
+
+impl OpenRequest {
+    /// Make every path absolute, since the running instance may have another
+    /// working directory. Files that do not exist yet are kept so they can be
+    /// created on save.
+    pub fn resolve_paths(&mut self) {
+        for file in &mut self.files {
+            file.path = absolute(&file.path);
+        }
+        if let Some((a, b)) = &mut self.diff {
+            *a = absolute(a);
+            *b = absolute(b);
+        }
+    }
+
+    /// Paths the request waits on with `--wait`.
+    pub fn wait_paths(&self) -> Vec<PathBuf> {
+        self.files.iter().map(|f| f.path.clone()).collect()
+    }
+}
+
+fn absolute(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path)
+        .or_else(|_| std::path::absolute(path))
+        .unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[derive(Debug, Default)]
+pub struct Cli {
+    pub open: OpenRequest,
+    pub plugin_paths: Vec<PathBuf>,
+    pub new_window: bool,
+    pub headless: bool,
+    /// Command id to run in headless mode.
+    pub run: Option<String>,
+    pub output: Option<PathBuf>,
+    /// Remote-control method and parameters for the running instance.
+    pub call: Option<(String, serde_json::Value)>,
+}
+
+#[derive(Debug)]
+pub enum Command {
+    Run(Box<Cli>),
+    Help,
+    Version,
+}
+
+/// Parse the arguments (without the program name).
+pub fn parse(args: impl IntoIterator<Item = OsString>) -> Result<Command, String> {
+    let mut cli = Cli::default();
+    let mut args = args.into_iter().peekable();
+    let mut position: Option<(usize, Option<usize>)> = None;
+    let mut only_files = false;
+
+    while let Some(arg) = args.next() {
+        let text = arg.to_string_lossy();
+        if text == "-" && !only_files {
+            cli.open.stdin = true;
+            continue;
+        }
+        if only_files || !(text.starts_with('-') || text.starts_with('+')) {
+            let mut file = file_arg(arg);
+            if let Some((line, column)) = position.take() {
+                file.line = Some(line);
+                file.column = column;
+            }
+            cli.open.files.push(file);
+            continue;
+        }
+        if let Some(pos) = text.strip_prefix('+') {
+            position =
+                Some(parse_position(pos).ok_or_else(|| format!("invalid position: {}", text))?);
+            continue;
+        }
+
+        let (flag, inline) = match text.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => {
+                (flag.to_string(), Some(value.to_string()))
+            }
+            _ => (text.to_string(), None),
+        };
+        let mut value = |what: &str| {
+            inline
+                .clone()
+                .map(OsString::from)
+                .or_else(|| args.next())
+                .ok_or_else(|| format!("{} needs {}", flag, what))
+        };
+        match flag.as_str() {
+            "--" => only_files = true,
+            "-h" | "--help" => return Ok(Command::Help),
+            "-V" | "--version" => return Ok(Command::Version),
+            "-n" | "--new-window" => cli.new_window = true,
+            "-w" | "--wait" => cli.open.wait = true,
+            "-r" | "--readonly" | "--read-only" => cli.open.read_only = true,
+            "-e" | "--encoding" => {
+                let name = value("an encoding name")?;
+                let name = name.to_string_lossy();
+                cli.open.encoding = Some(
+                    Encoding::from_name(&name)
+                        .ok_or_else(|| format!("unknown encoding: {}", name))?,
+                );
+            }
+            "-d" | "--diff" => {
+                let a = value("two files")?;
+                let b = value("two files")?;
+                cli.open.diff = Some((a.into(), b.into()));
+            }
+            "--plugin" => cli.plugin_paths.push(value("a path")?.into()),
+            "--headless" => cli.headless = true,
+            "--run" => cli.run = Some(value("a command")?.to_string_lossy().into_owned()),
+            "-o" | "--output" => cli.output = Some(value("a path")?.into()),
+            "--call" => {
+                let method = value("a method")?.to_string_lossy().into_owned();
+                let params = match args.next_if(|a| a.to_string_lossy().starts_with('{')) {
+                    Some(json) => call_params(&json.to_string_lossy())?,
+                    None => serde_json::Value::Null,
+                };
+                cli.call = Some((method, params));
+            }
+            _ => return Err(format!("unknown option: {}", flag)),
+        }
+    }
+
+    if position.is_some() {
+        return Err("+LINE must be followed by a file".to_string());
+    }
+    if cli.headless {
+        if cli.run.is_none() {
+            return Err("--headless needs --run COMMAND".to_string());
+        }
+        if cli.open.files.len() + cli.open.stdin as usize > 1 {
+            return Err("--headless takes a single input".to_string());
+        }
+    } else if cli.run.is_some() || cli.output.is_some() {
+        return Err("--run and --output only work with --headless".to_string());
+    }
+    if cli.call.is_some() && (cli.headless || !cli.open.files.is_empty() || cli.open.stdin) {
+        return Err("--call takes no files".to_string());
+    }
+    let nothing_to_open = cli.open.files.iter().all(|f| f.path.is_dir())
+        && !cli.open.stdin
+        && cli.open.diff.is_none();
+    if cli.open.wait && nothing_to_open {
+        return Err("--wait needs a file to open".to_string());
+    }
+    Ok(Command::Run(Box::new(cli)))
+}
+
+/// Parameters of `--call`, with a relative `path` made absolute.
+fn call_params(json: &str) -> Result<serde_json::Value, String> {
+    let mut params: serde_json::Value =
+        serde_json::from_str(json).map_err(|e| format!("invalid parameters: {}", e))?;
+    if let Some(path) = params.get_mut("path") {
+        if let Some(text) = path.as_str() {
+            *path = absolute(Path::new(text))
+                .to_string_lossy()
+                .into_owned()
+                .into();
+        }
+    }
+    Ok(params)
+}
+
+/// `LINE` or `LINE:COLUMN`.
+fn parse_position(text: &str) -> Option<(usize, Option<usize>)> {
+    match text.split_once(':') {
+        Some((line, column)) => Some((line.parse().ok()?, Some(column.parse().ok()?))),
+        None => Some((text.parse().ok()?, None)),
+    }
+}
+
+/// A file argument, splitting off a `:LINE` or `:LINE:COLUMN` suffix unless a
+/// file with the literal name exists.
+fn file_arg(arg: OsString) -> FileArg {
+    let path = PathBuf::from(&arg);
+    let whole = FileArg {
+        path: path.clone(),
+        line: None,
+        column: None,
+    };
+    let Some(text) = arg.to_str() else {
+        return whole;
+    };
+    if path.exists() {
+        return whole;
+    }
+    let mut parts = text.rsplitn(3, ':');
+    let last = parts.next().and_then(|p| p.parse::<usize>().ok());
+    let middle = parts.next();
+    let rest = parts.next();
+    match (last, middle, rest) {
+        (Some(column), Some(line), Some(name)) if !name.is_empty() => match line.parse() {
+            Ok(line) => FileArg {
+                path: name.into(),
+                line: Some(line),
+                column: Some(column),
+            },
+            // Only one number, e.g. `C:\notes.txt:12`
+            Err(_) => FileArg {
+                path: format!("{}:{}", name, line).into(),
+                line: Some(column),
+                column: None,
+            },
+        },
+        (Some(line), Some(name), None) if !name.is_empty() => FileArg {
+            path: name.into(),
+            line: Some(line),
+            column: None,
+        },
+        _ => whole,
+    }
+}
+
+/// Read standard input until it ends, passing the text on as it arrives.
+/// CRLF is normalized to LF. Stops early when `sink` returns false.
+pub fn stream_stdin(encoding: Option<Encoding>, mut sink: impl FnMut(String) -> bool) {
+    let mut decoder = StreamDecoder::new(encoding.unwrap_or_default());
+    let mut stdin = std::io::stdin().lock();
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        let n = match stdin.read(&mut buf) {
+            Ok(n) => n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => {
+                log::warn!("Failed to read standard input: {}", e);
+                0
+            }
+        };
+        let last = n == 0;
+        let text = decoder.decode(&buf[..n], last);
+        if !text.is_empty() && !sink(text) {
+            return;
+        }
+        if last {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_args(args: &[&str]) -> Result<Command, String> {
+        parse(args.iter().map(OsString::from))
+    }
+
+    fn cli(args: &[&str]) -> Cli {
+        match parse_args(args) {
+            Ok(Command::Run(cli)) => *cli,
+            other => panic!("{:?} parsed as {:?}", args, other),
+        }
+    }
+
+    fn file(path: &str, line: Option<usize>, column: Option<usize>) -> FileArg {
+        FileArg {
+            path: path.into(),
+            line,
+            column,
+        }
+    }
+
+    #[test]
+    fn file_with_line_and_column() {
+        let cli = cli(&["missing.rs:12:5", "other.rs:7", "plain.rs"]);
+        assert_eq!(
+            cli.open.files,
+            [
+                file("missing.rs", Some(12), Some(5)),
+                file("other.rs", Some(7), None),
+                file("plain.rs", None, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn position_applies_to_the_next_file() {
+        let cli = cli(&["+3", "a.txt", "b.txt", "+4:2", "c.txt"]);
+        assert_eq!(
+            cli.open.files,
+            [
+                file("a.txt", Some(3), None),
+                file("b.txt", None, None),
+                file("c.txt", Some(4), Some(2)),
+            ]
+        );
+        assert!(parse_args(&["+x", "a.txt"]).is_err());
+        assert!(parse_args(&["a.txt", "+3"]).is_err());
+    }
+
+    #[test]
+    fn double_dash_ends_the_options() {
+        let cli = cli(&["-r", "--", "-w", "+3", "-"]);
+        assert!(cli.open.read_only);
+        assert!(!cli.open.wait);
+        assert!(!cli.open.stdin);
+        assert_eq!(
+            cli.open.files,
+            [
+                file("-w", None, None),
+                file("+3", None, None),
+                file("-", None, None)
+            ]
+        );
+    }
+
+    #[test]
+    fn option_values_inline_or_separate() {
+        let inline = cli(&["--encoding=utf-16le", "--plugin=plugins", "a.txt"]);
+        let separate = cli(&["-e", "utf-16le", "--plugin", "plugins", "a.txt"]);
+        for cli in [inline, separate] {
+            assert_eq!(cli.open.encoding, Encoding::from_name("utf-16le"));
+            assert_eq!(cli.plugin_paths, [PathBuf::from("plugins")]);
+            assert_eq!(cli.open.files, [file("a.txt", None, None)]);
+        }
+        assert!(parse_args(&["--encoding"]).is_err());
+        assert!(parse_args(&["--encoding=klingon"]).is_err());
+        assert!(parse_args(&["--bogus"]).is_err());
+    }
+
+    #[test]
+    fn drive_letter_is_not_a_line() {
+        let cli = cli(&[r"C:\notes.txt:12", r"C:\notes.txt:12:3"]);
+        assert_eq!(
+            cli.open.files,
+            [
+                file(r"C:\notes.txt", Some(12), None),
+                file(r"C:\notes.txt", Some(12), Some(3)),
+            ]
+        );
+    }
+
+    // Windows does not allow ':' in file names
+    #[cfg(unix)]
+    #[test]
+    fn existing_file_keeps_its_literal_name() {
+        let dir = std::env::temp_dir().join(format!("notos-cli-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let literal = dir.join("notes.txt:12");
+        std::fs::write(&literal, "").unwrap();
+        let arg = literal.to_str().unwrap();
+
+        let cli = cli(&[arg]);
+        assert_eq!(cli.open.files, [file(arg, None, None)]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn wait_needs_a_file_to_open() {
+        let dir = std::env::temp_dir();
+        let dir = dir.to_str().unwrap();
+        assert!(parse_args(&["--wait"]).is_err());
+        assert!(parse_args(&["--wait", dir]).is_err());
+        assert!(cli(&["--wait", "a.txt"]).open.wait);
+        assert!(cli(&["--wait", "-"]).open.stdin);
+    }
+
+    #[test]
+    fn headless_and_call_combinations() {
+        assert!(parse_args(&["--headless", "a.txt"]).is_err());
+        assert!(parse_args(&["--headless", "--run", "x.y", "a.txt", "b.txt"]).is_err());
+        assert!(parse_args(&["--run", "x.y", "a.txt"]).is_err());
+        assert!(parse_args(&["--call", "active", "a.txt"]).is_err());
+        let call = cli(&["--call", "goto", r#"{"line": 3}"#]);
+        assert_eq!(
+            call.call,
+            Some(("goto".to_string(), serde_json::json!({"line": 3})))
+        );
+        assert!(matches!(parse_args(&["-h", "--bogus"]), Ok(Command::Help)));
+        assert!(matches!(parse_args(&["--version"]), Ok(Command::Version)));
+    }
+}
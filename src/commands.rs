@@ -0,0 +1,483 @@
+//! Central registry of named commands.
+//!
+//! Every action that can be triggered from a keyboard shortcut or the command
+//! palette is registered here under a stable id such as `file.save`. Host
+//! commands map to a `MenuAction`; plugin commands are namespaced as
+//! `<plugin id>.<command id>` and dispatched back to the owning plugin.
+
+use crate::ui::MenuAction;
+use eframe::egui;
+use notos_sdk::PluginCommand;
+
+/// A single key press with modifiers, e.g. `Ctrl+Shift+P`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyChord {
+    pub modifiers: egui::Modifiers,
+    pub key: egui::Key,
+}
+
+impl KeyChord {
+    pub const fn new(modifiers: egui::Modifiers, key: egui::Key) -> Self {
+        Self { modifiers, key }
+    }
+
+    /// Parse a chord such as `"Ctrl+Shift+P"` or `"F5"`. Case-insensitive.
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut modifiers = egui::Modifiers::NONE;
+        let mut key = None;
+        for part in text.split('+').map(str::trim) {
+            match part.to_lowercase().as_str() {
+                "ctrl" | "control" | "cmd" => modifiers.command = true,
+                "shift" => modifiers.shift = true,
+                "alt" | "option" => modifiers.alt = true,
+                // "Ctrl++" splits into an empty trailing part
+                "" => key = Some(egui::Key::Plus),
+                name => key = Some(parse_key(name)?),
+            }
+        }
+        key.map(|key| Self { modifiers, key })
+    }
+
+    /// Number of modifiers held; used to test more specific chords first.
+    pub fn specificity(&self) -> u8 {
+        self.modifiers.command as u8 + self.modifiers.shift as u8 + self.modifiers.alt as u8
+    }
+}
+
+impl std::fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.modifiers.command || self.modifiers.ctrl {
+            f.write_str("Ctrl+")?;
+        }
+        if self.modifiers.alt {
+            f.write_str("Alt+")?;
+        }
+        if self.modifiers.shift {
+            f.write_str("Shift+")?;
+        }
+        f.write_str(self.key.name())
+    }
+}
+
+/// One or two chords pressed in sequence, e.g. `Ctrl+K Ctrl+C`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Keybinding(pub Vec<KeyChord>);
+
+impl Keybinding {
+    /// Longest chord sequence the dispatcher will wait for.
+    pub const MAX_CHORDS: usize = 2;
+
+    /// Parse a space-separated chord sequence such as `"Ctrl+K Ctrl+C"`.
+    pub fn parse(text: &str) -> Option<Self> {
+        let chords = text
+            .split_whitespace()
+            .map(KeyChord::parse)
+            .collect::<Option<Vec<_>>>()?;
+        if chords.is_empty() || chords.len() > Self::MAX_CHORDS {
+            return None;
+        }
+        Some(Self(chords))
+    }
+
+    pub fn first(&self) -> KeyChord {
+        self.0[0]
+    }
+
+    pub fn is_sequence(&self) -> bool {
+        self.0.len() > 1
+    }
+}
+
+impl From<KeyChord> for Keybinding {
+    fn from(chord: KeyChord) -> Self {
+        Self(vec![chord])
+    }
+}
+
+impl std::fmt::Display for Keybinding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, chord) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{}", chord)?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_key(name: &str) -> Option<egui::Key> {
+    let key = match name {
+        "plus" => egui::Key::Plus,
+        "=" | "equals" => egui::Key::Equals,
+        "-" | "minus" => egui::Key::Minus,
+        "esc" => egui::Key::Escape,
+        "del" => egui::Key::Delete,
+        "return" => egui::Key::Enter,
+        _ => {
+            return egui::Key::ALL.iter().copied().find(|k| {
+                k.name().eq_ignore_ascii_case(name) || k.symbol_or_name().eq_ignore_ascii_case(name)
+            })
+        }
+    };
+    Some(key)
+}
+
+/// What happens when a command is executed.
+#[derive(Clone)]
+pub enum CommandTarget {
+    Host(MenuAction),
+    Plugin {
+        plugin_id: String,
+        command_id: String,
+    },
+}
+
+#[derive(Clone)]
+pub struct Command {
+    pub id: String,
+    pub title: String,
+    /// Bindings the command ships with (host table or plugin declaration).
+    pub default_keybindings: Vec<Keybinding>,
+    /// Bindings in effect after applying the user's keymap.
+    pub keybindings: Vec<Keybinding>,
+    pub target: CommandTarget,
+}
+
+pub struct CommandRegistry {
+    commands: Vec<Command>,
+}
+
+impl CommandRegistry {
+    /// Create a registry pre-populated with the built-in host commands.
+    pub fn new() -> Self {
+        use egui::{Key, Modifiers};
+        const CTRL: Modifiers = Modifiers::COMMAND;
+        const CTRL_SHIFT: Modifiers = Modifiers::COMMAND.plus(Modifiers::SHIFT);
+        let host: Vec<(&str, &str, MenuAction, Vec<KeyChord>)> = vec![
+            (
+                "file.new",
+                "File: New Tab",
+                MenuAction::NewTab,
+                vec![KeyChord::new(CTRL, Key::N), KeyChord::new(CTRL, Key::T)],
+            ),
+            (
+                "file.open",
+                "File: Open",
+                MenuAction::Open,
+                vec![KeyChord::new(CTRL, Key::O)],
+            ),
+            (
+                "file.save",
+                "File: Save",
+                MenuAction::Save,
+                vec![KeyChord::new(CTRL, Key::S)],
+            ),
+            (
+                "file.save_as",
+                "File: Save As",
+                MenuAction::SaveAs,
+                vec![KeyChord::new(CTRL_SHIFT, Key::S)],
+            ),
+            (
+                "file.close_tab",
+                "File: Close Tab",
+                MenuAction::CloseTab,
+                vec![KeyChord::new(CTRL, Key::W)],
+            ),
+            (
+                "file.clear_recent",
+                "File: Clear Recent Files",
+                MenuAction::ClearHistory,
+                vec![],
+            ),
+            (
+                "app.settings",
+                "File: Settings",
+                MenuAction::Settings,
+                vec![KeyChord::new(CTRL, Key::Comma)],
+            ),
+            ("app.exit", "File: Exit", MenuAction::Exit, vec![]),
+            (
+                "edit.undo",
+                "Edit: Undo",
+                MenuAction::Undo,
+                vec![KeyChord::new(CTRL, Key::Z)],
+            ),
+            (
+                "edit.redo",
+                "Edit: Redo",
+                MenuAction::Redo,
+                vec![KeyChord::new(CTRL, Key::Y)],
+            ),
+            (
+                "edit.find",
+                "Edit: Find",
+                MenuAction::Find,
+                vec![KeyChord::new(CTRL, Key::F)],
+            ),
+            (
+                "edit.replace",
+                "Edit: Replace",
+                MenuAction::Replace,
+                vec![KeyChord::new(CTRL, Key::H)],
+            ),
+            (
+                "edit.goto_line",
+                "Edit: Go To Line",
+                MenuAction::GotoLine,
+                vec![KeyChord::new(CTRL, Key::G)],
+            ),
+            (
+                "edit.insert_date_time",
+                "Edit: Insert Time/Date",
+                MenuAction::TimeDate,
+                vec![KeyChord::new(Modifiers::NONE, Key::F5)],
+            ),
+            (
+                "edit.select_all",
+                "Edit: Select All",
+                MenuAction::SelectAll,
+                vec![KeyChord::new(CTRL, Key::A)],
+            ),
+            (
+                "plugins.toggle_trust",
+                "Plugins: Require Approval for Plugins",
+                MenuAction::TogglePluginTrust,
+                vec![],
+            ),
+            (
+                "scripts.cancel",
+                "Scripts: Cancel Running Script",
+                MenuAction::CancelScript,
+                vec![],
+            ),
+            (
+                "scripts.new",
+                "Scripts: New Script...",
+                MenuAction::NewScript,
+                vec![],
+            ),
+            (
+                "scripts.reload",
+                "Scripts: Reload Scripts",
+                MenuAction::ReloadScripts,
+                vec![],
+            ),
+            (
+                "macro.toggle_record",
+                "Macro: Start/Stop Recording",
+                MenuAction::ToggleMacroRecording,
+                vec![KeyChord::new(CTRL_SHIFT, Key::R)],
+            ),
+            (
+                "macro.play",
+                "Macro: Play",
+                MenuAction::PlayMacro,
+                vec![KeyChord::new(CTRL_SHIFT, Key::M)],
+            ),
+            (
+                "macro.play_times",
+                "Macro: Play N Times...",
+                MenuAction::PlayMacroTimes,
+                vec![],
+            ),
+            (
+                "macro.play_to_end",
+                "Macro: Play to End of File",
+                MenuAction::PlayMacroToEnd,
+                vec![],
+            ),
+            (
+                "macro.save",
+                "Macro: Save Macro As...",
+                MenuAction::SaveMacro,
+                vec![],
+            ),
+            (
+                "view.command_palette",
+                "View: Command Palette",
+                MenuAction::CommandPalette,
+                vec![KeyChord::new(CTRL_SHIFT, Key::P)],
+            ),
+            (
+                "view.keyboard_shortcuts",
+                "View: Keyboard Shortcuts",
+                MenuAction::KeyboardShortcuts,
+                vec![],
+            ),
+            (
+                "view.toggle_word_wrap",
+                "View: Toggle Word Wrap",
+                MenuAction::ToggleWordWrap,
+                vec![],
+            ),
+            (
+                "view.toggle_line_numbers",
+                "View: Toggle Line Numbers",
+                MenuAction::ToggleLineNumbers,
+                vec![],
+            ),
+            (
+                "view.toggle_follow",
+                "View: Follow File",
+                MenuAction::ToggleFollow,
+                vec![],
+            ),
+            (
+                "view.toggle_dark_mode",
+                "View: Toggle Dark Mode",
+                MenuAction::ToggleDarkMode,
+                vec![],
+            ),
+            (
+                "view.zoom_in",
+                "View: Zoom In",
+                MenuAction::ZoomIn,
+                vec![
+                    KeyChord::new(CTRL, Key::Plus),
+                    KeyChord::new(CTRL, Key::Equals),
+                ],
+            ),
+            (
+                "view.zoom_out",
+                "View: Zoom Out",
+                MenuAction::ZoomOut,
+                vec![KeyChord::new(CTRL, Key::Minus)],
+            ),
+            (
+                "view.reset_zoom",
+                "View: Reset Zoom",
+                MenuAction::ResetZoom,
+                vec![KeyChord::new(CTRL, Key::Num0)],
+            ),
+            (
+                "view.load_font",
+                "View: Load Font File...",
+                MenuAction::LoadFont,
+                vec![],
+            ),
+        ];
+
+        let commands = host
+            .into_iter()
+            .map(|(id, title, action, chords)| {
+                let keybindings: Vec<Keybinding> =
+                    chords.into_iter().map(Keybinding::from).collect();
+                Command {
+                    id: id.to_string(),
+                    title: title.to_string(),
+                    default_keybindings: keybindings.clone(),
+                    keybindings,
+                    target: CommandTarget::Host(action),
+                }
+            })
+            .collect();
+        Self { commands }
+    }
+
+    /// Register a command, replacing any existing command with the same id.
+    pub fn register(&mut self, command: Command) {
+        if let Some(existing) = self.commands.iter_mut().find(|c| c.id == command.id) {
+            *existing = command;
+        } else {
+            self.commands.push(command);
+        }
+    }
+
+    /// Register the commands a plugin contributes, namespaced by its id.
+    pub fn register_plugin_commands(
+        &mut self,
+        plugin_id: &str,
+        plugin_name: &str,
+        commands: Vec<PluginCommand>,
+    ) {
+        for cmd in commands {
+            let keybindings = cmd
+                .keybinding
+                .as_deref()
+                .and_then(|k| {
+                    let binding = Keybinding::parse(k);
+                    if binding.is_none() {
+                        log::warn!("Plugin {} declared invalid keybinding {:?}", plugin_id, k);
+                    }
+                    binding
+                })
+                .into_iter()
+                .collect::<Vec<_>>();
+            self.register(Command {
+                id: format!("{}.{}", plugin_id, cmd.id),
+                title: format!("{}: {}", plugin_name, cmd.title),
+                default_keybindings: keybindings.clone(),
+                keybindings,
+                target: CommandTarget::Plugin {
+                    plugin_id: plugin_id.to_string(),
+                    command_id: cmd.id,
+                },
+            });
+        }
+    }
+
+    /// Keep only the commands for which `keep` returns `true`.
+    pub fn retain(&mut self, keep: impl FnMut(&Command) -> bool) {
+        self.commands.retain(keep);
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Command> {
+        self.commands.iter().find(|c| c.id == id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Command> {
+        self.commands.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Command> {
+        self.commands.iter_mut()
+    }
+
+    /// Display text of the first keybinding of a command, for menus.
+    pub fn shortcut_text(&self, id: &str) -> String {
+        self.get(id)
+            .and_then(|c| c.keybindings.first())
+            .map(|k| k.to_string())
+            .unwrap_or_default()
+    }
+
+    /// All (binding, command id) pairs, most specific first chords first so
+    /// that `Ctrl+Shift+S` is tested before `Ctrl+S`.
+    pub fn bindings(&self) -> Vec<(Keybinding, String)> {
+        let mut out: Vec<(Keybinding, String)> = self
+            .commands
+            .iter()
+            .flat_map(|c| c.keybindings.iter().map(move |k| (k.clone(), c.id.clone())))
+            .collect();
+        out.sort_by_key(|(k, _)| std::cmp::Reverse(k.first().specificity()));
+        out
+    }
+}
+
+/// Score how well `query` fuzzy-matches `text`. Every query character must appear
+/// in order; consecutive runs and word starts score higher. `None` means no match.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let text: Vec<char> = text.chars().flat_map(char::to_lowercase).collect();
+    let mut score = 0;
+    let mut pos = 0;
+    let mut prev_match: Option<usize> = None;
+    for qc in query.chars().flat_map(char::to_lowercase) {
+        if qc.is_whitespace() {
+            continue;
+        }
+        let found = (pos..text.len()).find(|&i| text[i] == qc)?;
+        score += 1;
+        if prev_match.is_some_and(|p| p + 1 == found) {
+            score += 5;
+        }
+        if found == 0 || !text[found - 1].is_alphanumeric() {
+            score += 3;
+        }
+        prev_match = Some(found);
+        pos = found + 1;
+    }
+    // Prefer shorter candidates when scores tie
+    Some(score * 100 - text.len() as i32)
+}
@@ -13,9 +13,11 @@ pub struct FindDialog {
 }
 
 impl FindDialog {
-    pub fn show(&mut self, ctx: &egui::Context, mut active_tab: Option<&mut EditorTab>, undo_manager: &mut crate::undo_manager::UndoManager) {
+    /// Show the dialog. Returns the query when it jumped to the next match.
+    pub fn show(&mut self, ctx: &egui::Context, mut active_tab: Option<&mut EditorTab>, undo_manager: &mut crate::undo_manager::UndoManager) -> Option<String> {
         let mut open = self.open;
         let mut find_next_clicked = false;
+        let read_only = active_tab.as_ref().is_some_and(|t| t.read_only);
 
         if open {
             let title = if self.replace_mode { "Replace" } else { "Find" };
@@ -85,7 +87,7 @@ impl FindDialog {
                     ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                         let button_size = egui::vec2(80.0, 24.0);
 
-                        if self.replace_mode {
+                        if self.replace_mode && !read_only {
                             if ui
                                 .add_sized(button_size, egui::Button::new("Replace All"))
                                 .clicked()
@@ -116,9 +118,13 @@ impl FindDialog {
         }
         self.open = open;
 
-        if find_next_clicked {
-            self.perform_find_next(ctx, active_tab);
+        if find_next_clicked && !self.query.is_empty() {
+            if let Some(tab) = active_tab {
+                Self::find_next(ctx, tab, &self.query);
+                return Some(self.query.clone());
+            }
         }
+        None
     }
 
     fn perform_replace(
@@ -201,54 +207,52 @@ impl FindDialog {
         }
     }
 
-    fn perform_find_next(&self, ctx: &egui::Context, active_tab: Option<&mut EditorTab>) {
-        let query = &self.query;
-
-        if !query.is_empty() {
-            if let Some(tab) = active_tab {
-                let text = &tab.content;
-                let id = egui::Id::new("editor").with(tab.id);
+    /// Select the next match of `query` after the cursor, wrapping around.
+    pub fn find_next(ctx: &egui::Context, tab: &mut EditorTab, query: &str) {
+        if query.is_empty() {
+            return;
+        }
+        let text = &tab.content;
+        let id = egui::Id::new("editor").with(tab.id);
 
-                // egui cursor gives char count; convert to byte offset for str::find
-                let mut start_byte = 0usize;
-                if let Some(state) = egui::TextEdit::load_state(ctx, id) {
-                    if let Some(range) = state.cursor.char_range() {
-                        let char_pos = range.primary.index.max(range.secondary.index);
-                        start_byte = text
-                            .char_indices()
-                            .nth(char_pos)
-                            .map(|(i, _)| i)
-                            .unwrap_or(text.len());
-                    }
-                }
+        // egui cursor gives char count; convert to byte offset for str::find
+        let mut start_byte = 0usize;
+        if let Some(state) = egui::TextEdit::load_state(ctx, id) {
+            if let Some(range) = state.cursor.char_range() {
+                let char_pos = range.primary.index.max(range.secondary.index);
+                start_byte = text
+                    .char_indices()
+                    .nth(char_pos)
+                    .map(|(i, _)| i)
+                    .unwrap_or(text.len());
+            }
+        }
 
-                // Find next match by byte offset
-                let found_byte = text[start_byte..]
-                    .find(query)
-                    .map(|i| start_byte + i)
-                    .or_else(|| text.find(query)); // wrap around
+        // Find next match by byte offset
+        let found_byte = text[start_byte..]
+            .find(query)
+            .map(|i| start_byte + i)
+            .or_else(|| text.find(query)); // wrap around
 
-                if let Some(byte_idx) = found_byte {
-                    let byte_end = byte_idx + query.len();
-                    // Convert byte offsets to char counts for egui CCursor
-                    let char_idx = text[..byte_idx].chars().count();
-                    let char_end = text[..byte_end].chars().count();
+        if let Some(byte_idx) = found_byte {
+            let byte_end = byte_idx + query.len();
+            // Convert byte offsets to char counts for egui CCursor
+            let char_idx = text[..byte_idx].chars().count();
+            let char_end = text[..byte_end].chars().count();
 
-                    if let Some(mut state) = egui::TextEdit::load_state(ctx, id) {
-                        state
-                            .cursor
-                            .set_char_range(Some(egui::text::CCursorRange::two(
-                                egui::text::CCursor::new(char_idx),
-                                egui::text::CCursor::new(char_end),
-                            )));
-                        egui::TextEdit::store_state(ctx, id, state);
-                        // Store char counts so editor_panel uses consistent units
-                        tab.cursor_range = Some((char_idx, char_end));
-                        tab.scroll_to_cursor = true;
-                        tab.center_cursor = true;
-                        ctx.request_repaint();
-                    }
-                }
+            if let Some(mut state) = egui::TextEdit::load_state(ctx, id) {
+                state
+                    .cursor
+                    .set_char_range(Some(egui::text::CCursorRange::two(
+                        egui::text::CCursor::new(char_idx),
+                        egui::text::CCursor::new(char_end),
+                    )));
+                egui::TextEdit::store_state(ctx, id, state);
+                // Store char counts so editor_panel uses consistent units
+                tab.cursor_range = Some((char_idx, char_end));
+                tab.scroll_to_cursor = true;
+                tab.center_cursor = true;
+                ctx.request_repaint();
             }
         }
     }
@@ -478,3 +482,718 @@ impl CloseConfirmationDialog {
         }
     }
 }
+
+#[derive(Default)]
+pub struct CommandPalette {
+    pub open: bool,
+    pub query: String,
+    pub selected: usize,
+    pub just_opened: bool,
+}
+
+impl CommandPalette {
+    /// Shows the palette and returns the id of the command the user picked, if any.
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        commands: &crate::commands::CommandRegistry,
+    ) -> Option<String> {
+        if !self.open {
+            return None;
+        }
+
+        let mut matches: Vec<(i32, &crate::commands::Command)> = commands
+            .iter()
+            .filter_map(|c| {
+                let score = crate::commands::fuzzy_score(&self.query, &c.title)
+                    .max(crate::commands::fuzzy_score(&self.query, &c.id));
+                score.map(|s| (s, c))
+            })
+            .collect();
+        if self.query.trim().is_empty() {
+            matches.sort_by(|a, b| a.1.title.cmp(&b.1.title));
+        } else {
+            matches.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.title.cmp(&b.1.title)));
+        }
+
+        let mut moved = false;
+        if ctx.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown)) {
+            self.selected += 1;
+            moved = true;
+        }
+        if ctx.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp)) {
+            self.selected = self.selected.saturating_sub(1);
+            moved = true;
+        }
+        self.selected = self.selected.min(matches.len().saturating_sub(1));
+        let escape = ctx.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::Escape));
+
+        let mut chosen = None;
+        egui::Window::new("Command Palette")
+            .id(egui::Id::new("command_palette_v1"))
+            .title_bar(false)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 60.0))
+            .fixed_size([480.0, 0.0])
+            .show(ctx, |ui| {
+                let res = ui.add(
+                    egui::TextEdit::singleline(&mut self.query)
+                        .hint_text("Type a command...")
+                        .desired_width(f32::INFINITY),
+                );
+                if self.just_opened {
+                    res.request_focus();
+                    self.just_opened = false;
+                }
+                if res.changed() {
+                    self.selected = 0;
+                }
+                if res.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                    chosen = matches.get(self.selected).map(|(_, c)| c.id.clone());
+                }
+
+                ui.separator();
+
+                egui::ScrollArea::vertical()
+                    .max_height(320.0)
+                    .show(ui, |ui| {
+                        if matches.is_empty() {
+                            ui.label(
+                                egui::RichText::new("No matching commands")
+                                    .color(ui.visuals().weak_text_color()),
+                            );
+                        }
+                        for (i, (_, cmd)) in matches.iter().enumerate() {
+                            let is_selected = i == self.selected;
+                            let shortcut = cmd
+                                .keybindings
+                                .first()
+                                .map(|k| k.to_string())
+                                .unwrap_or_default();
+                            let res = ui
+                                .horizontal(|ui| {
+                                    let res = ui.add_sized(
+                                        egui::vec2(ui.available_width() - 110.0, 20.0),
+                                        egui::SelectableLabel::new(is_selected, &cmd.title),
+                                    );
+                                    ui.with_layout(
+                                        egui::Layout::right_to_left(egui::Align::Center),
+                                        |ui| {
+                                            ui.label(
+                                                egui::RichText::new(shortcut)
+                                                    .color(ui.visuals().weak_text_color()),
+                                            );
+                                        },
+                                    );
+                                    res
+                                })
+                                .inner
+                                .on_hover_text(&cmd.id);
+                            if is_selected && moved {
+                                res.scroll_to_me(None);
+                            }
+                            if res.clicked() {
+                                chosen = Some(cmd.id.clone());
+                            }
+                        }
+                    });
+            });
+
+        if chosen.is_some() || escape {
+            self.open = false;
+        }
+        chosen
+    }
+}
+
+/// Change requested from the keybinding editor.
+pub enum KeybindingEdit {
+    /// Replace the bindings of a command (empty = unbound).
+    Set(String, Vec<crate::commands::Keybinding>),
+    /// Drop the user override and return to the default bindings.
+    Reset(String),
+}
+
+#[derive(Default)]
+pub struct KeybindingEditor {
+    pub open: bool,
+    pub filter: String,
+    /// Command being recorded and the chords captured so far.
+    recording: Option<(String, Vec<crate::commands::KeyChord>)>,
+}
+
+impl KeybindingEditor {
+    /// While recording, key presses belong to the editor and must not trigger commands.
+    pub fn is_recording(&self) -> bool {
+        self.open && self.recording.is_some()
+    }
+
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        commands: &crate::commands::CommandRegistry,
+        keymap: &crate::keymap::Keymap,
+    ) -> Option<KeybindingEdit> {
+        use crate::commands::{KeyChord, Keybinding};
+
+        if !self.open {
+            self.recording = None;
+            return None;
+        }
+
+        let mut edit = None;
+        if let Some((id, chords)) = &mut self.recording {
+            let presses: Vec<(egui::Key, egui::Modifiers)> = ctx.input_mut(|i| {
+                let presses = i
+                    .events
+                    .iter()
+                    .filter_map(|e| match e {
+                        egui::Event::Key {
+                            key,
+                            pressed: true,
+                            repeat: false,
+                            modifiers,
+                            ..
+                        } => Some((*key, *modifiers)),
+                        _ => None,
+                    })
+                    .collect();
+                i.events
+                    .retain(|e| !matches!(e, egui::Event::Key { .. } | egui::Event::Text(_)));
+                presses
+            });
+            let mut done = false;
+            for (key, modifiers) in presses {
+                if modifiers.is_none() && key == egui::Key::Escape {
+                    chords.clear();
+                    done = true;
+                } else if modifiers.is_none() && key == egui::Key::Enter && !chords.is_empty() {
+                    done = true;
+                } else {
+                    let mut normalized = egui::Modifiers::NONE;
+                    normalized.command = modifiers.command || modifiers.ctrl;
+                    normalized.shift = modifiers.shift;
+                    normalized.alt = modifiers.alt;
+                    chords.push(KeyChord::new(normalized, key));
+                    done = chords.len() == Keybinding::MAX_CHORDS;
+                }
+                if done {
+                    break;
+                }
+            }
+            if done {
+                if !chords.is_empty() {
+                    let mut bindings = commands
+                        .get(id)
+                        .map(|c| c.keybindings.clone())
+                        .unwrap_or_default();
+                    let recorded = Keybinding(std::mem::take(chords));
+                    if !bindings.contains(&recorded) {
+                        bindings.push(recorded);
+                    }
+                    edit = Some(KeybindingEdit::Set(id.clone(), bindings));
+                }
+                self.recording = None;
+            }
+        }
+
+        let conflicts = crate::keymap::conflicts(commands);
+        let mut rows: Vec<&crate::commands::Command> = commands
+            .iter()
+            .filter(|c| {
+                self.filter.trim().is_empty()
+                    || crate::commands::fuzzy_score(&self.filter, &c.title).is_some()
+                    || crate::commands::fuzzy_score(&self.filter, &c.id).is_some()
+                    || c.keybindings.iter().any(|k| {
+                        k.to_string()
+                            .to_lowercase()
+                            .contains(&self.filter.trim().to_lowercase())
+                    })
+            })
+            .collect();
+        rows.sort_by(|a, b| a.title.cmp(&b.title));
+
+        let mut open = self.open;
+        egui::Window::new("Keyboard Shortcuts")
+            .id(egui::Id::new("keybinding_editor_v1"))
+            .open(&mut open)
+            .collapsible(false)
+            .default_size([560.0, 420.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Search:");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.filter)
+                            .hint_text("Command, id or key")
+                            .desired_width(f32::INFINITY),
+                    );
+                });
+                if let Some((_, chords)) = &self.recording {
+                    let so_far = Keybinding(chords.clone()).to_string();
+                    ui.label(
+                        egui::RichText::new(format!(
+                            "Recording: {}  (press up to {} chords, Enter to finish, Esc to cancel)",
+                            if chords.is_empty() { "..." } else { &so_far },
+                            Keybinding::MAX_CHORDS
+                        ))
+                        .color(ui.visuals().warn_fg_color),
+                    );
+                }
+                if !conflicts.is_empty() {
+                    ui.label(
+                        egui::RichText::new(format!("{} conflicting keybinding(s)", conflicts.len()))
+                            .color(ui.visuals().error_fg_color),
+                    );
+                }
+                ui.separator();
+
+                egui::ScrollArea::vertical()
+                    .auto_shrink([false, false])
+                    .show(ui, |ui| {
+                        egui::Grid::new("keybinding_grid")
+                            .num_columns(3)
+                            .striped(true)
+                            .spacing([12.0, 4.0])
+                            .show(ui, |ui| {
+                                for cmd in rows {
+                                    ui.label(&cmd.title).on_hover_text(&cmd.id);
+
+                                    ui.horizontal_wrapped(|ui| {
+                                        if cmd.keybindings.is_empty() {
+                                            ui.label(
+                                                egui::RichText::new("—")
+                                                    .color(ui.visuals().weak_text_color()),
+                                            );
+                                        }
+                                        for binding in &cmd.keybindings {
+                                            let clash = conflicts.iter().find(|c| {
+                                                c.commands.contains(&cmd.id)
+                                                    && (c.binding == *binding
+                                                        || c.binding.first() == binding.first())
+                                            });
+                                            let mut text = egui::RichText::new(binding.to_string());
+                                            if clash.is_some() {
+                                                text = text.color(ui.visuals().error_fg_color);
+                                            }
+                                            let res = ui.label(text);
+                                            if let Some(clash) = clash {
+                                                let others: Vec<&str> = clash
+                                                    .commands
+                                                    .iter()
+                                                    .filter(|id| **id != cmd.id)
+                                                    .map(String::as_str)
+                                                    .collect();
+                                                res.on_hover_text(format!(
+                                                    "Conflicts with: {}",
+                                                    others.join(", ")
+                                                ));
+                                            }
+                                        }
+                                        if keymap.is_overridden(&cmd.id) {
+                                            ui.label(
+                                                egui::RichText::new("(user)")
+                                                    .color(ui.visuals().weak_text_color()),
+                                            );
+                                        }
+                                    });
+
+                                    ui.horizontal(|ui| {
+                                        if ui
+                                            .small_button("➕")
+                                            .on_hover_text("Record a new keybinding")
+                                            .clicked()
+                                        {
+                                            self.recording = Some((cmd.id.clone(), Vec::new()));
+                                        }
+                                        if ui
+                                            .add_enabled(
+                                                !cmd.keybindings.is_empty(),
+                                                egui::Button::new("✖").small(),
+                                            )
+                                            .on_hover_text("Remove all keybindings")
+                                            .clicked()
+                                        {
+                                            edit = Some(KeybindingEdit::Set(cmd.id.clone(), Vec::new()));
+                                        }
+                                        if ui
+                                            .add_enabled(
+                                                keymap.is_overridden(&cmd.id),
+                                                egui::Button::new("↺").small(),
+                                            )
+                                            .on_hover_text("Reset to default")
+                                            .clicked()
+                                        {
+                                            edit = Some(KeybindingEdit::Reset(cmd.id.clone()));
+                                        }
+                                    });
+                                    ui.end_row();
+                                }
+                            });
+                    });
+
+                ui.separator();
+                ui.label(
+                    egui::RichText::new(crate::keymap::Keymap::path().display().to_string())
+                        .small()
+                        .color(ui.visuals().weak_text_color()),
+                );
+            });
+        self.open = open;
+        edit
+    }
+}
+
+/// Unified window for the editor's settings and those plugins declare in
+/// `NotosPlugin::settings_schema`.
+#[derive(Default)]
+pub struct SettingsWindow {
+    pub open: bool,
+}
+
+impl SettingsWindow {
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        schemas: &[(String, String, Vec<notos_sdk::SettingSpec>)],
+        store: &mut crate::settings::SettingsStore,
+    ) {
+        if !self.open {
+            return;
+        }
+
+        let mut open = self.open;
+        egui::Window::new("⚙ Settings")
+            .id(egui::Id::new("settings_window_v1"))
+            .open(&mut open)
+            .collapsible(false)
+            .default_size([440.0, 360.0])
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical()
+                    .auto_shrink([false, false])
+                    .show(ui, |ui| {
+                        for (plugin_id, plugin_name, specs) in schemas {
+                            let handle = store.handle(plugin_id);
+                            egui::CollapsingHeader::new(plugin_name)
+                                .id_salt(plugin_id)
+                                .default_open(true)
+                                .show(ui, |ui| {
+                                    egui::Grid::new(("settings_grid", plugin_id))
+                                        .num_columns(3)
+                                        .spacing([12.0, 6.0])
+                                        .show(ui, |ui| {
+                                            for spec in specs {
+                                                setting_row(ui, spec, &handle);
+                                                ui.end_row();
+                                            }
+                                        });
+                                });
+                        }
+                    });
+            });
+        self.open = open;
+    }
+}
+
+fn setting_row(
+    ui: &mut egui::Ui,
+    spec: &notos_sdk::SettingSpec,
+    handle: &notos_sdk::SettingsHandle,
+) {
+    use notos_sdk::{SettingKind, SettingValue};
+
+    let label = ui.label(&spec.label);
+    if let Some(description) = &spec.description {
+        label.on_hover_text(description);
+    }
+
+    let stored = handle.read(|s| s.get(&spec.key).cloned());
+    let current = stored.clone().unwrap_or_else(|| spec.default.clone());
+    match &spec.kind {
+        SettingKind::Bool => {
+            let mut value = current.as_bool().unwrap_or(false);
+            if ui.checkbox(&mut value, "").changed() {
+                handle.set(&spec.key, value);
+            }
+        }
+        SettingKind::String => {
+            let mut value = current.as_str().unwrap_or_default().to_string();
+            if ui.text_edit_singleline(&mut value).changed() {
+                handle.set(&spec.key, value);
+            }
+        }
+        SettingKind::Number { min, max } => {
+            let mut value = current.as_f64().unwrap_or(*min);
+            if ui
+                .add(egui::DragValue::new(&mut value).range(*min..=*max))
+                .changed()
+            {
+                handle.set(&spec.key, value);
+            }
+        }
+        SettingKind::Enum(options) => {
+            let selected = current.as_str().unwrap_or_default().to_string();
+            egui::ComboBox::from_id_salt(("setting_enum", &spec.key))
+                .selected_text(&selected)
+                .show_ui(ui, |ui| {
+                    for option in options {
+                        if ui.selectable_label(*option == selected, option).clicked() {
+                            handle.set(&spec.key, SettingValue::String(option.clone()));
+                        }
+                    }
+                });
+        }
+    }
+
+    let is_default = stored.as_ref().is_none_or(|v| *v == spec.default);
+    if ui
+        .add_enabled(!is_default, egui::Button::new("↺").small())
+        .on_hover_text("Reset to default")
+        .clicked()
+    {
+        handle.write(|s| s.remove(&spec.key));
+    }
+}
+
+/// What the macro dialog asks for.
+#[derive(Default, Clone, Copy, PartialEq)]
+pub enum MacroPrompt {
+    /// How many times to play the last macro.
+    #[default]
+    PlayTimes,
+    /// A name to save the last macro under.
+    SaveAs,
+}
+
+pub enum MacroDialogResult {
+    Play(usize),
+    Save(String),
+}
+
+#[derive(Default)]
+pub struct MacroDialog {
+    pub open: bool,
+    pub prompt: MacroPrompt,
+    pub input: String,
+    just_opened: bool,
+}
+
+impl MacroDialog {
+    pub fn ask(&mut self, prompt: MacroPrompt) {
+        self.open = true;
+        self.prompt = prompt;
+        self.input = match prompt {
+            MacroPrompt::PlayTimes => "2".to_string(),
+            MacroPrompt::SaveAs => String::new(),
+        };
+        self.just_opened = true;
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<MacroDialogResult> {
+        let mut open = self.open;
+        let mut confirmed = false;
+        let (title, label, button) = match self.prompt {
+            MacroPrompt::PlayTimes => ("Play Macro", "Times:", "Play"),
+            MacroPrompt::SaveAs => ("Save Macro", "Name:", "Save"),
+        };
+        egui::Window::new(title)
+            .id(egui::Id::new("macro_dialog"))
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .default_size([220.0, 80.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(label);
+                    let res =
+                        ui.add(egui::TextEdit::singleline(&mut self.input).desired_width(140.0));
+                    if std::mem::take(&mut self.just_opened) {
+                        res.request_focus();
+                    }
+                    if res.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                        confirmed = true;
+                    }
+                });
+                ui.add_space(4.0);
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if ui
+                        .add_sized(egui::vec2(80.0, 24.0), egui::Button::new(button))
+                        .clicked()
+                    {
+                        confirmed = true;
+                    }
+                });
+            });
+        self.open = open;
+
+        if !confirmed {
+            return None;
+        }
+        let input = self.input.trim();
+        let result = match self.prompt {
+            MacroPrompt::PlayTimes => input
+                .parse()
+                .ok()
+                .filter(|&n| n > 0)
+                .map(MacroDialogResult::Play),
+            MacroPrompt::SaveAs => Some(input.to_string())
+                .filter(|n| !n.is_empty())
+                .map(MacroDialogResult::Save),
+        };
+        if result.is_some() {
+            self.open = false;
+        }
+        result
+    }
+}
+
+/// "Trust this plugin?" prompt for a plugin file that is not on the allowlist.
+/// Closing the window postpones the decision to the next start.
+pub fn trust_prompt(
+    ctx: &egui::Context,
+    plugin: &crate::trust::UntrustedPlugin,
+) -> Option<crate::trust::TrustDecision> {
+    use crate::trust::TrustDecision;
+
+    let mut open = true;
+    let mut decision = None;
+    let name = plugin
+        .path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy();
+    egui::Window::new("🛡 Trust this plugin?")
+        .id(egui::Id::new("trust_prompt"))
+        .open(&mut open)
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+        .show(ctx, |ui| {
+            ui.label(format!(
+                "\"{}\" is new or has changed since you last approved it.",
+                name
+            ));
+            ui.label("A trusted plugin runs with the same rights as Notos.");
+            ui.add_space(6.0);
+            egui::Grid::new("trust_prompt_grid")
+                .num_columns(2)
+                .spacing([12.0, 4.0])
+                .show(ui, |ui| {
+                    ui.label("File:");
+                    ui.label(plugin.path.display().to_string());
+                    ui.end_row();
+                    ui.label("SHA-256:");
+                    ui.label(egui::RichText::new(&plugin.sha256).monospace().small());
+                    ui.end_row();
+                });
+            ui.add_space(6.0);
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if ui
+                    .add_sized(egui::vec2(80.0, 24.0), egui::Button::new("Reject"))
+                    .clicked()
+                {
+                    decision = Some(TrustDecision::Reject);
+                }
+                if ui
+                    .add_sized(egui::vec2(80.0, 24.0), egui::Button::new("Trust"))
+                    .clicked()
+                {
+                    decision = Some(TrustDecision::Trust);
+                }
+            });
+        });
+    if !open {
+        decision = Some(TrustDecision::Later);
+    }
+    decision
+}
+
+/// What to do about a file that another program changed.
+#[derive(Clone, Copy, PartialEq)]
+pub enum DiskConflictChoice {
+    /// Replace the tab's content with the file.
+    Reload,
+    /// Keep the unsaved edits; the next save overwrites the file.
+    KeepMine,
+    /// Save over the file.
+    Overwrite,
+    /// Do not save.
+    Cancel,
+    /// Show the differences between the file and the tab.
+    Compare,
+}
+
+/// Prompt for a tab with unsaved edits whose file another program changed, or,
+/// when `saving`, for saving over a file that changed since it was loaded.
+pub fn disk_conflict_prompt(ctx: &egui::Context, title: &str, saving: bool) -> Option<DiskConflictChoice> {
+    let mut choice = None;
+    egui::Window::new("⚠ File Changed on Disk")
+        .id(egui::Id::new("disk_conflict_prompt"))
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+        .show(ctx, |ui| {
+            ui.label(format!("\"{}\" was changed by another program.", title));
+            if saving {
+                ui.label("Saving replaces those changes with yours.");
+            } else {
+                ui.label("The tab has unsaved changes of its own.");
+            }
+            ui.add_space(6.0);
+            let buttons: [(&str, DiskConflictChoice); 3] = if saving {
+                [("Cancel", DiskConflictChoice::Cancel), ("Compare", DiskConflictChoice::Compare), ("Overwrite", DiskConflictChoice::Overwrite)]
+            } else {
+                [("Keep Mine", DiskConflictChoice::KeepMine), ("Compare", DiskConflictChoice::Compare), ("Reload", DiskConflictChoice::Reload)]
+            };
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                for (label, value) in buttons {
+                    if ui.add_sized(egui::vec2(80.0, 24.0), egui::Button::new(label)).clicked() {
+                        choice = Some(value);
+                    }
+                }
+            });
+        });
+    choice
+}
+
+/// What to do with the unsaved edits of a tab recovered after a crash.
+#[derive(Clone, Copy, PartialEq)]
+pub enum RecoveryChoice {
+    /// Put the edits back into the tab, as unsaved changes.
+    Restore,
+    /// Throw the edits away.
+    Discard,
+    /// Show the differences between the tab and the edits.
+    Compare,
+}
+
+/// Prompt for the first of `remaining` tabs with edits recovered from an
+/// instance that did not shut down cleanly.
+pub fn recovery_prompt(ctx: &egui::Context, title: &str, remaining: usize) -> Option<RecoveryChoice> {
+    let mut choice = None;
+    egui::Window::new("🩹 Recover Unsaved Edits")
+        .id(egui::Id::new("recovery_prompt"))
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+        .show(ctx, |ui| {
+            ui.label("Notos did not shut down cleanly last time.");
+            ui.label(format!("\"{}\" had edits that were not saved.", title));
+            if remaining > 1 {
+                ui.label(
+                    egui::RichText::new(format!("{} more tabs to go.", remaining - 1))
+                        .color(ui.visuals().weak_text_color()),
+                );
+            }
+            ui.add_space(6.0);
+            let buttons = [("Restore", RecoveryChoice::Restore), ("Compare", RecoveryChoice::Compare), ("Discard", RecoveryChoice::Discard)];
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                for (label, value) in buttons {
+                    if ui.add_sized(egui::vec2(80.0, 24.0), egui::Button::new(label)).clicked() {
+                        choice = Some(value);
+                    }
+                }
+            });
+        });
+    choice
+}
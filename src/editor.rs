@@ -1,8 +1,10 @@
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+use notos_sdk::TextEdit;
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::io::{Read, Write, BufReader};
-use std::path::PathBuf;
+use std::io::{Read, BufReader};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
 /// Files above this threshold (10 MB) are opened in large-file mode.
 /// In large-file mode, undo/redo is disabled to avoid cloning huge strings.
@@ -10,6 +12,44 @@ pub const LARGE_FILE_THRESHOLD: u64 = 10 * 1024 * 1024;
 
 
 
+/// What a file looked like when it was last loaded or saved, to notice
+/// changes made by other programs.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DiskState {
+    pub modified: Option<SystemTime>,
+    pub size: u64,
+    /// SHA-256 of the bytes read or written; in large-file mode, of the preview.
+    pub hash: [u8; 32],
+}
+
+impl DiskState {
+    fn new(metadata: &fs::Metadata, bytes: &[u8]) -> Self {
+        use sha2::{Digest, Sha256};
+        Self {
+            modified: metadata.modified().ok(),
+            size: metadata.len(),
+            hash: Sha256::digest(bytes).into(),
+        }
+    }
+
+    /// Whether the metadata still matches, so the file need not be read.
+    pub fn matches(&self, metadata: &fs::Metadata) -> bool {
+        self.size == metadata.len() && self.modified == metadata.modified().ok()
+    }
+
+    pub fn same_content(&self, other: &DiskState) -> bool {
+        self.size == other.size && self.hash == other.hash
+    }
+}
+
+/// A file found changed by [`EditorTab::check_disk`].
+pub enum DiskChange {
+    /// Only the metadata changed, e.g. by `touch`.
+    Touched(DiskState),
+    /// The file as it is now.
+    Modified(Box<EditorTab>),
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum LineEnding {
     Crlf,
@@ -39,6 +79,18 @@ pub struct TabId(pub usize);
 
 static TAB_ID_COUNTER: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(1);
 
+impl From<TabId> for notos_sdk::TabId {
+    fn from(id: TabId) -> Self {
+        notos_sdk::TabId(id.0)
+    }
+}
+
+impl From<notos_sdk::TabId> for TabId {
+    fn from(id: notos_sdk::TabId) -> Self {
+        TabId(id.0)
+    }
+}
+
 pub fn next_tab_id() -> TabId {
     TabId(TAB_ID_COUNTER.fetch_add(1, std::sync::atomic::Ordering::Relaxed))
 }
@@ -74,6 +126,17 @@ impl Encoding {
             Encoding::Utf16Be => encoding_rs::UTF_16BE,
         }
     }
+
+    /// Parse an encoding name as given on the command line, e.g. `utf-8` or `cp1252`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().replace('_', "-").as_str() {
+            "utf-8" | "utf8" => Some(Encoding::Utf8),
+            "windows-1252" | "cp1252" | "latin1" | "iso-8859-1" => Some(Encoding::Windows1252),
+            "utf-16le" | "utf16le" => Some(Encoding::Utf16Le),
+            "utf-16be" | "utf16be" => Some(Encoding::Utf16Be),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -100,6 +163,12 @@ pub struct EditorTab {
     /// Original file size in bytes (used for UI hints).
     #[serde(default)]
     pub file_size: u64,
+    /// Opened for viewing only: the editor and all editing commands leave the content alone.
+    #[serde(default)]
+    pub read_only: bool,
+    /// The file as last loaded or saved; `None` until the tab is backed by a file.
+    #[serde(default)]
+    pub disk_state: Option<DiskState>,
     /// Cached line offsets for performance
     #[serde(skip)]
     pub line_offsets: Vec<usize>,
@@ -147,6 +216,8 @@ impl Default for EditorTab {
             cursor_range: Some((0, 0)),
             large_file: false,
             file_size: 0,
+            read_only: false,
+            disk_state: None,
             line_offsets: vec![0],
             line_count: 1,
             char_count: 0,
@@ -191,6 +262,8 @@ impl EditorTab {
             cursor_range: Some((0, 0)),
             large_file: is_large,
             file_size: size,
+            read_only: false,
+            disk_state: None,
             line_offsets,
             line_count,
             char_count,
@@ -202,6 +275,11 @@ impl EditorTab {
     }
 
     pub fn from_file(path: PathBuf) -> Result<Self> {
+        Self::from_file_with_encoding(path, None)
+    }
+
+    /// Load a file, decoding it as `encoding` instead of detecting the encoding.
+    pub fn from_file_with_encoding(path: PathBuf, encoding: Option<Encoding>) -> Result<Self> {
         // Get file size first to pre-allocate and detect large files
         let metadata = fs::metadata(&path)?;
         let file_size = metadata.len();
@@ -223,8 +301,36 @@ impl EditorTab {
             reader.read_to_end(&mut bytes)?;
         }
 
+        let disk_state = DiskState::new(&metadata, &bytes);
+        let mut tab = Self::from_bytes(Some(path), bytes, encoding);
+        tab.disk_state = Some(disk_state);
+        tab.large_file = is_large;
+        tab.file_size = file_size;
+
+        if is_large {
+            tab.content.push_str("\n\n... [File truncated: Cannot fully load files over 10MB in memory preview] ...");
+        }
+
+        Ok(tab)
+    }
+
+    /// Decode raw file contents: detect (or apply) the encoding and line
+    /// ending, and normalize line endings to LF for editing.
+    pub fn from_bytes(path: Option<PathBuf>, bytes: Vec<u8>, encoding: Option<Encoding>) -> Self {
+        let is_large = bytes.len() as u64 >= LARGE_FILE_THRESHOLD;
+
         // Try to detect encoding or fallback to UTF-8
-        let (content, encoding, _had_errors) = if bytes.starts_with(b"\xFF\xFE") {
+        let (content, encoding, _had_errors) = if let Some(forced) = encoding {
+            let bom: &[u8] = match forced {
+                Encoding::Utf8 => b"\xEF\xBB\xBF",
+                Encoding::Utf16Le => b"\xFF\xFE",
+                Encoding::Utf16Be => b"\xFE\xFF",
+                Encoding::Windows1252 => b"",
+            };
+            let body = bytes.strip_prefix(bom).unwrap_or(&bytes);
+            let (res, had_errors) = forced.to_encoding().decode_without_bom_handling(body);
+            (res.into_owned(), forced, had_errors)
+        } else if bytes.starts_with(b"\xFF\xFE") {
             let (res, _enc, had_errors) = encoding_rs::UTF_16LE.decode(&bytes[2..]);
             (res.into_owned(), Encoding::Utf16Le, had_errors)
         } else if bytes.starts_with(b"\xFE\xFF") {
@@ -279,45 +385,22 @@ impl EditorTab {
             }
         }
 
-        let mut tab = Self::new(Some(path), content);
+        let mut tab = Self::new(path, content);
         tab.line_ending = line_ending;
         tab.encoding = encoding;
-        tab.large_file = is_large;
-        tab.file_size = file_size;
-
-        if is_large {
-            tab.content.push_str("\n\n... [File truncated: Cannot fully load files over 10MB in memory preview] ...");
-        }
-
-        Ok(tab)
+        tab
     }
 
-    pub fn save(&mut self) -> Result<()> {
+    /// Write the tab to its file, keeping the previous version as `backup` says.
+    pub fn save(&mut self, backup: crate::save::Backup) -> Result<()> {
         if self.large_file {
             return Err("File is too large and was loaded in truncated preview mode. Saving is disabled to prevent data loss.".into());
         }
 
         if let Some(path) = &self.path {
-            let mut file = fs::File::create(path)?;
-
-            // Convert LF to target line ending
-            let content_to_save = if self.line_ending == LineEnding::Lf {
-                std::borrow::Cow::Borrowed(&self.content)
-            } else {
-                std::borrow::Cow::Owned(self.content.replace('\n', self.line_ending.as_str()))
-            };
-
-            // Encode content to target encoding
-            let (bytes, _enc, _had_errors) = self.encoding.to_encoding().encode(&content_to_save);
-
-            // Add BOM if needed for UTF-16
-            if self.encoding == Encoding::Utf16Le {
-                file.write_all(b"\xFF\xFE")?;
-            } else if self.encoding == Encoding::Utf16Be {
-                file.write_all(b"\xFE\xFF")?;
-            }
-
-            file.write_all(&bytes)?;
+            let bytes = self.encoded_bytes();
+            crate::save::write_file(path, &bytes, backup)?;
+            self.disk_state = fs::metadata(path).ok().map(|m| DiskState::new(&m, &bytes));
             self.is_dirty = false;
             Ok(())
         } else {
@@ -325,6 +408,56 @@ impl EditorTab {
         }
     }
 
+    /// Check whether `path` changed since it looked like `known`, reading it
+    /// with `encoding` only when its metadata differs. A file that cannot be
+    /// read, e.g. because it was deleted, counts as unchanged.
+    pub fn check_disk(path: &Path, known: &DiskState, encoding: Encoding) -> Option<DiskChange> {
+        let metadata = fs::metadata(path).ok()?;
+        if known.matches(&metadata) {
+            return None;
+        }
+        let tab = Self::from_file_with_encoding(path.to_path_buf(), Some(encoding)).ok()?;
+        let state = tab.disk_state.clone()?;
+        if state.same_content(known) {
+            Some(DiskChange::Touched(state))
+        } else {
+            Some(DiskChange::Modified(Box::new(tab)))
+        }
+    }
+
+    /// The file as it is now, if another program changed it since the tab
+    /// loaded or saved it.
+    pub fn changed_on_disk(&self) -> Option<EditorTab> {
+        let (Some(path), Some(known)) = (&self.path, &self.disk_state) else {
+            return None;
+        };
+        match Self::check_disk(path, known, self.encoding)? {
+            DiskChange::Modified(tab) => Some(*tab),
+            DiskChange::Touched(_) => None,
+        }
+    }
+
+    /// The content as it is written to disk, with the tab's line ending and encoding.
+    pub fn encoded_bytes(&self) -> Vec<u8> {
+        // Convert LF to target line ending
+        let content_to_save = if self.line_ending == LineEnding::Lf {
+            std::borrow::Cow::Borrowed(&self.content)
+        } else {
+            std::borrow::Cow::Owned(self.content.replace('\n', self.line_ending.as_str()))
+        };
+
+        // Encode content to target encoding
+        let (bytes, _enc, _had_errors) = self.encoding.to_encoding().encode(&content_to_save);
+
+        // Add BOM if needed for UTF-16
+        let bom: &[u8] = match self.encoding {
+            Encoding::Utf16Le => b"\xFF\xFE",
+            Encoding::Utf16Be => b"\xFE\xFF",
+            _ => b"",
+        };
+        [bom, &bytes[..]].concat()
+    }
+
     pub fn set_path(&mut self, path: PathBuf) {
         self.title = path
             .file_name()
@@ -336,6 +469,45 @@ impl EditorTab {
 
 
 
+    /// Move the cursor to a 1-based line and column, clamped to the content,
+    /// and scroll it into the middle of the view.
+    pub fn go_to(&mut self, line: usize, column: usize) {
+        let char_idx = self.char_index(line, column);
+        self.cursor_range = Some((char_idx, char_idx));
+        self.scroll_to_cursor = true;
+        self.center_cursor = true;
+    }
+
+    /// Select from one 1-based (line, column) position to another and scroll
+    /// the selection into the middle of the view.
+    pub fn select(&mut self, start: (usize, usize), end: (usize, usize)) {
+        let start = self.char_index(start.0, start.1);
+        let end = self.char_index(end.0, end.1);
+        self.cursor_range = Some((start, end));
+        self.scroll_to_cursor = true;
+        self.center_cursor = true;
+    }
+
+    /// Char index of a 1-based line and column, clamped to the content.
+    pub fn char_index(&self, line: usize, column: usize) -> usize {
+        let line_idx = line.saturating_sub(1).min(self.line_offsets.len().saturating_sub(1));
+        let line_start = self.line_offsets.get(line_idx).copied().unwrap_or(0);
+        let line_text = self.content[line_start..].split('\n').next().unwrap_or("");
+        let column_bytes = line_text
+            .char_indices()
+            .nth(column.saturating_sub(1))
+            .map_or(line_text.len(), |(i, _)| i);
+        byte_to_char(&self.content, line_start + column_bytes)
+    }
+
+    /// 1-based line and column of a char index.
+    pub fn position_of(&self, char_idx: usize) -> (usize, usize) {
+        let byte = char_to_byte(&self.content, char_idx);
+        let line_idx = self.line_offsets.partition_point(|&offset| offset <= byte).max(1) - 1;
+        let line_start = self.line_offsets.get(line_idx).copied().unwrap_or(0).min(byte);
+        (line_idx + 1, self.content[line_start..byte].chars().count() + 1)
+    }
+
     pub fn calculate_line_offsets(content: &str) -> Vec<usize> {
         let mut offsets = vec![0];
         for (i, b) in content.as_bytes().iter().enumerate() {
@@ -351,4 +523,289 @@ impl EditorTab {
         self.line_count = self.content.lines().count().max(1) + if self.content.ends_with('\n') { 1 } else { 0 };
         self.char_count = self.content.chars().count();
     }
+
+    /// Append `text` to the end, for text that keeps arriving such as piped
+    /// input. The metadata is extended rather than recomputed, and the undo
+    /// snapshot gets the text as well, so it does not become an undo step.
+    pub fn append_text(&mut self, text: &str) {
+        let start = self.content.len();
+        self.content.push_str(text);
+        self.line_offsets.extend(text.match_indices('\n').map(|(i, _)| start + i + 1));
+        self.line_count = self.line_offsets.len();
+        self.char_count += text.chars().count();
+        if !self.large_file {
+            self.undo_snapshot.push_str(text);
+        }
+    }
+
+    /// Apply a batch of byte-range edits in a single pass.
+    ///
+    /// The edits are validated up front (in bounds, on char boundaries, non-overlapping),
+    /// so on error the content is left untouched. On success the cursor range is rebased
+    /// onto the new content and the cached metadata is refreshed.
+    pub fn apply_edits(&mut self, mut edits: Vec<TextEdit>) -> Result<()> {
+        // Stable sort keeps multiple inserts at the same offset in the order they were given
+        edits.sort_by_key(|e| (e.start, e.end));
+
+        let mut prev_end = 0;
+        for edit in &edits {
+            if edit.start > edit.end || edit.end > self.content.len() {
+                return Err(format!("Edit range {}..{} is out of bounds", edit.start, edit.end).into());
+            }
+            if !self.content.is_char_boundary(edit.start) || !self.content.is_char_boundary(edit.end) {
+                return Err(format!("Edit range {}..{} is not on a character boundary", edit.start, edit.end).into());
+            }
+            if edit.start < prev_end {
+                return Err(format!("Edit range {}..{} overlaps a previous edit", edit.start, edit.end).into());
+            }
+            prev_end = edit.end;
+        }
+
+        // Cursor is stored as char indices; rebase it in byte space
+        let cursor_bytes = self.cursor_range.map(|(p, s)| {
+            (char_to_byte(&self.content, p), char_to_byte(&self.content, s))
+        });
+
+        let removed: usize = edits.iter().map(|e| e.end - e.start).sum();
+        let inserted: usize = edits.iter().map(|e| e.text.len()).sum();
+        let mut new_content = String::with_capacity(self.content.len() - removed + inserted);
+        let mut pos = 0;
+        for edit in &edits {
+            new_content.push_str(&self.content[pos..edit.start]);
+            new_content.push_str(&edit.text);
+            pos = edit.end;
+        }
+        new_content.push_str(&self.content[pos..]);
+
+        self.content = new_content;
+        self.cursor_range = cursor_bytes.map(|(p, s)| {
+            (
+                byte_to_char(&self.content, rebase_offset(p, &edits)),
+                byte_to_char(&self.content, rebase_offset(s, &edits)),
+            )
+        });
+        self.is_dirty = true;
+        self.refresh_metadata();
+        Ok(())
+    }
+}
+
+/// Map a byte offset in the old content to the equivalent offset after `edits` (sorted).
+/// Offsets inside a replaced range stay inside the replacement text.
+fn rebase_offset(offset: usize, edits: &[TextEdit]) -> usize {
+    let mut delta: isize = 0;
+    for edit in edits {
+        if edit.end <= offset {
+            delta += edit.text.len() as isize - (edit.end - edit.start) as isize;
+        } else if edit.start < offset {
+            let inside = (offset - edit.start).min(edit.text.len());
+            return (edit.start as isize + delta) as usize + inside;
+        } else {
+            break;
+        }
+    }
+    (offset as isize + delta) as usize
+}
+
+/// Decodes text that arrives in chunks, such as standard input or a file
+/// that keeps growing, normalizing CRLF to LF.
+pub struct StreamDecoder {
+    decoder: encoding_rs::Decoder,
+    /// A CR at the end of a chunk may be the first half of a CRLF.
+    held_cr: bool,
+}
+
+impl StreamDecoder {
+    pub fn new(encoding: Encoding) -> Self {
+        Self {
+            decoder: encoding.to_encoding().new_decoder(),
+            held_cr: false,
+        }
+    }
+
+    /// Decode the next chunk; `last` flushes what is held back.
+    pub fn decode(&mut self, bytes: &[u8], last: bool) -> String {
+        let capacity = self.decoder.max_utf8_buffer_length(bytes.len()).unwrap_or(bytes.len() * 3) + 1;
+        let mut text = String::with_capacity(capacity);
+        if self.held_cr {
+            text.push('\r');
+        }
+        let _ = self.decoder.decode_to_string(bytes, &mut text, last);
+        self.held_cr = !last && text.ends_with('\r');
+        if self.held_cr {
+            text.pop();
+        }
+        text.replace("\r\n", "\n")
+    }
+}
+
+pub fn char_to_byte(text: &str, char_idx: usize) -> usize {
+    text.char_indices().nth(char_idx).map_or(text.len(), |(i, _)| i)
+}
+
+fn byte_to_char(text: &str, byte_idx: usize) -> usize {
+    text[..text.floor_char_boundary(byte_idx.min(text.len()))].chars().count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tab(content: &str) -> EditorTab {
+        EditorTab::new(None, content.to_string())
+    }
+
+    #[test]
+    fn appending_matches_a_full_refresh() {
+        let mut t = tab("first");
+        for piece in ["", " line\n", "sécond\r\n", "\n", "thîrd", "\n"] {
+            t.append_text(piece);
+            let mut refreshed = tab(&t.content);
+            refreshed.refresh_metadata();
+            assert_eq!(t.line_offsets, refreshed.line_offsets);
+            assert_eq!(t.line_count, refreshed.line_count);
+            assert_eq!(t.char_count, refreshed.char_count);
+            assert_eq!(t.undo_snapshot, t.content);
+        }
+    }
+
+    #[test]
+    fn edits_apply_in_one_pass() {
+        let mut t = tab("hello world");
+        t.apply_edits(vec![TextEdit::new(6, 11, "there"), TextEdit::new(0, 5, "hi")])
+            .unwrap();
+        assert_eq!(t.content, "hi there");
+        assert!(t.is_dirty);
+        assert_eq!(t.char_count, 8);
+    }
+
+    #[test]
+    fn invalid_edits_leave_the_content_alone() {
+        let mut t = tab("héllo");
+        let overlapping = vec![TextEdit::new(0, 3, "a"), TextEdit::new(2, 4, "b")];
+        assert!(t.apply_edits(overlapping).is_err());
+        // Inside the two bytes of "é"
+        assert!(t.apply_edits(vec![TextEdit::delete(2, 3)]).is_err());
+        assert!(t.apply_edits(vec![TextEdit::insert(7, "!")]).is_err());
+        assert!(t.apply_edits(vec![TextEdit::new(3, 1, "")]).is_err());
+        assert_eq!(t.content, "héllo");
+        assert!(!t.is_dirty);
+    }
+
+    #[test]
+    fn adjacent_edits_do_not_overlap() {
+        let mut t = tab("abcdef");
+        t.apply_edits(vec![TextEdit::new(3, 6, "X"), TextEdit::new(0, 3, "Y"), TextEdit::insert(3, "-")])
+            .unwrap();
+        assert_eq!(t.content, "Y-X");
+    }
+
+    #[test]
+    fn inserts_at_one_offset_keep_their_order() {
+        let mut t = tab("ac");
+        t.apply_edits(vec![TextEdit::insert(1, "1"), TextEdit::insert(1, "2"), TextEdit::insert(1, "3")])
+            .unwrap();
+        assert_eq!(t.content, "a123c");
+    }
+
+    #[test]
+    fn cursor_follows_the_edits() {
+        let edits = || vec![TextEdit::new(2, 4, "XYZ"), TextEdit::insert(6, "é")];
+        // (cursor before, cursor after) in chars, for "abcdefgh"
+        let cases = [
+            (1, 1), // before all edits
+            (2, 2), // at the start of a replacement
+            (3, 3), // inside a replacement
+            (4, 5), // at the end of a replacement
+            (5, 6), // between the edits
+            (6, 8), // at an insert
+            (8, 10), // after all edits
+        ];
+        for (before, after) in cases {
+            let mut t = tab("abcdefgh");
+            t.cursor_range = Some((before, before));
+            t.apply_edits(edits()).unwrap();
+            assert_eq!(t.content, "abXYZefégh");
+            assert_eq!(t.cursor_range, Some((after, after)), "cursor at {}", before);
+        }
+
+        let mut t = tab("abcdefgh");
+        t.cursor_range = Some((7, 1));
+        t.apply_edits(edits()).unwrap();
+        assert_eq!(t.cursor_range, Some((9, 1)));
+    }
+
+    #[test]
+    fn offsets_inside_a_shrunk_replacement_stay_inside_it() {
+        let edits = [TextEdit::new(2, 8, "x")];
+        assert_eq!(rebase_offset(1, &edits), 1);
+        assert_eq!(rebase_offset(5, &edits), 3);
+        assert_eq!(rebase_offset(8, &edits), 3);
+        assert_eq!(rebase_offset(10, &edits), 5);
+    }
+
+    /// Set the modification time of `path` to `secs` seconds from now.
+    fn set_modified(path: &Path, secs: u64) {
+        let time = SystemTime::now() + std::time::Duration::from_secs(secs);
+        fs::File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(time)
+            .unwrap();
+    }
+
+    #[test]
+    fn touched_file_is_not_a_change() {
+        let path = crate::utils::test_dir("editor-touch").join("notes.txt");
+        fs::write(&path, "hello").unwrap();
+        let t = EditorTab::from_file(path.clone()).unwrap();
+        let known = t.disk_state.clone().unwrap();
+        assert!(EditorTab::check_disk(&path, &known, t.encoding).is_none());
+
+        set_modified(&path, 60);
+        let Some(DiskChange::Touched(state)) = EditorTab::check_disk(&path, &known, t.encoding)
+        else {
+            panic!("expected only the metadata to change");
+        };
+        assert_ne!(state.modified, known.modified);
+        assert!(t.changed_on_disk().is_none());
+        // Once the new metadata is known the file is not read again
+        assert!(EditorTab::check_disk(&path, &state, t.encoding).is_none());
+    }
+
+    #[test]
+    fn rewritten_file_is_a_change() {
+        let path = crate::utils::test_dir("editor-modify").join("notes.txt");
+        fs::write(&path, "hello").unwrap();
+        let t = EditorTab::from_file(path.clone()).unwrap();
+
+        // Same size, so only the hash tells
+        fs::write(&path, "world").unwrap();
+        set_modified(&path, 60);
+        let Some(DiskChange::Modified(now)) =
+            EditorTab::check_disk(&path, t.disk_state.as_ref().unwrap(), t.encoding)
+        else {
+            panic!("expected a change");
+        };
+        assert_eq!(now.content, "world");
+        assert_eq!(t.changed_on_disk().unwrap().content, "world");
+
+        // A deleted file is left alone
+        fs::remove_file(&path).unwrap();
+        assert!(t.changed_on_disk().is_none());
+    }
+
+    #[test]
+    fn saving_updates_the_known_state() {
+        let path = crate::utils::test_dir("editor-save").join("notes.txt");
+        fs::write(&path, "hello").unwrap();
+        let mut t = EditorTab::from_file(path.clone()).unwrap();
+        t.content = "hello again".to_string();
+        t.save(crate::save::Backup::Off).unwrap();
+        assert!(t.changed_on_disk().is_none());
+
+        fs::write(&path, "changed by another program").unwrap();
+        assert!(t.changed_on_disk().is_some());
+    }
 }
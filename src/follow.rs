@@ -0,0 +1,282 @@
+//! Follow mode: keep reading a file as it grows, like `tail -F`.
+//!
+//! A background thread per followed file checks its size a few times a
+//! second and reads only the bytes added since, so the tab is never reloaded
+//! as a whole. A file that shrinks (truncated) or is replaced by another one
+//! (rotated) starts over. Files too large to show at once, and bursts too
+//! large to append, start over with their last part.
+
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::editor::{Encoding, StreamDecoder, TabId, LARGE_FILE_THRESHOLD};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How much of a large file is shown when following starts over.
+pub const TAIL_BYTES: u64 = 1024 * 1024;
+
+#[derive(Debug, PartialEq)]
+pub enum FollowEvent {
+    /// Text added to the end of the file.
+    Appended(String),
+    /// The file was truncated, rotated or grew too fast: show this instead.
+    Restarted {
+        text: String,
+        /// Whether `text` is the whole file rather than its last part.
+        whole_file: bool,
+    },
+}
+
+/// Stops the thread when dropped.
+pub struct FollowHandle {
+    stop: Arc<AtomicBool>,
+    /// Whether the tab was read-only before following made it so.
+    pub was_read_only: bool,
+}
+
+impl Drop for FollowHandle {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+struct Follower {
+    path: PathBuf,
+    encoding: Encoding,
+    decoder: StreamDecoder,
+    /// Bytes read so far.
+    offset: u64,
+    identity: Option<Identity>,
+}
+
+impl Follower {
+    fn poll(&mut self) -> std::io::Result<Option<FollowEvent>> {
+        let mut file = File::open(&self.path)?;
+        let metadata = file.metadata()?;
+        let len = metadata.len();
+        let identity = identity(&metadata);
+        let rotated = self.identity.is_some() && identity != self.identity;
+        self.identity = identity;
+
+        if rotated || len < self.offset || len - self.offset > LARGE_FILE_THRESHOLD {
+            return self.restart(&mut file, len).map(Some);
+        }
+        if len == self.offset {
+            return Ok(None);
+        }
+        let text = self.read_to(&mut file, len)?;
+        Ok(Some(FollowEvent::Appended(text)))
+    }
+
+    /// Read the whole file again, or its last [`TAIL_BYTES`] if it is large.
+    fn restart(&mut self, file: &mut File, len: u64) -> std::io::Result<FollowEvent> {
+        self.decoder = StreamDecoder::new(self.encoding);
+        let whole_file = len < LARGE_FILE_THRESHOLD;
+        self.offset = if whole_file { 0 } else { len - TAIL_BYTES };
+        if matches!(self.encoding, Encoding::Utf16Le | Encoding::Utf16Be) {
+            self.offset &= !1;
+        }
+        let mut text = self.read_to(file, len)?;
+        if !whole_file {
+            // Start at a whole line
+            let start = text.find('\n').map_or(0, |i| i + 1);
+            text.drain(..start);
+        }
+        Ok(FollowEvent::Restarted { text, whole_file })
+    }
+
+    fn read_to(&mut self, file: &mut File, len: u64) -> std::io::Result<String> {
+        file.seek(SeekFrom::Start(self.offset))?;
+        let mut bytes = Vec::with_capacity((len - self.offset) as usize);
+        file.take(len - self.offset).read_to_end(&mut bytes)?;
+        self.offset += bytes.len() as u64;
+        Ok(self.decoder.decode(&bytes, false))
+    }
+}
+
+/// Start following `path` for tab `id`, sending what is read to `tx`. With
+/// `resume_at`, the tab already shows the file up to that byte; otherwise
+/// the first event restarts it.
+pub fn spawn(
+    id: TabId,
+    path: PathBuf,
+    encoding: Encoding,
+    resume_at: Option<u64>,
+    was_read_only: bool,
+    tx: Sender<(TabId, FollowEvent)>,
+    ctx: egui::Context,
+) -> FollowHandle {
+    let stop = Arc::new(AtomicBool::new(false));
+    let stopped = stop.clone();
+    std::thread::spawn(move || {
+        let mut follower = Follower {
+            identity: fs::metadata(&path).ok().and_then(|m| identity(&m)),
+            path,
+            encoding,
+            decoder: StreamDecoder::new(encoding),
+            offset: resume_at.unwrap_or(u64::MAX),
+        };
+        while !stopped.load(Ordering::Relaxed) {
+            match follower.poll() {
+                Ok(Some(event)) => {
+                    if tx.send((id, event)).is_err() {
+                        return;
+                    }
+                    ctx.request_repaint();
+                }
+                Ok(None) => {}
+                // Gone for a moment while being rotated
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => log::warn!("Failed to follow {:?}: {}", follower.path, e),
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    });
+    FollowHandle {
+        stop,
+        was_read_only,
+    }
+}
+
+/// What tells a rotated file from the one that was followed.
+#[cfg(unix)]
+type Identity = (u64, u64);
+#[cfg(not(unix))]
+type Identity = std::time::SystemTime;
+
+#[cfg(unix)]
+fn identity(metadata: &fs::Metadata) -> Option<Identity> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn identity(metadata: &fs::Metadata) -> Option<Identity> {
+    metadata.created().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_dir;
+    use std::io::Write;
+
+    /// A follower that has read `path` up to its current end.
+    fn follower(path: &std::path::Path) -> Follower {
+        let metadata = fs::metadata(path).unwrap();
+        Follower {
+            path: path.to_path_buf(),
+            encoding: Encoding::Utf8,
+            decoder: StreamDecoder::new(Encoding::Utf8),
+            offset: metadata.len(),
+            identity: identity(&metadata),
+        }
+    }
+
+    fn append(path: &std::path::Path, bytes: &[u8]) {
+        let mut file = fs::OpenOptions::new().append(true).open(path).unwrap();
+        file.write_all(bytes).unwrap();
+    }
+
+    fn appended(text: &str) -> Option<FollowEvent> {
+        Some(FollowEvent::Appended(text.to_string()))
+    }
+
+    #[test]
+    fn reads_only_what_was_appended() {
+        let path = test_dir("follow-append").join("app.log");
+        fs::write(&path, "one\n").unwrap();
+        let mut follower = follower(&path);
+        assert_eq!(follower.poll().unwrap(), None);
+
+        append(&path, b"two\n");
+        assert_eq!(follower.poll().unwrap(), appended("two\n"));
+        assert_eq!(follower.poll().unwrap(), None);
+
+        // A character split between two writes comes out whole
+        append(&path, &[0xc3]);
+        assert_eq!(follower.poll().unwrap(), appended(""));
+        append(&path, &[0xa9, b'\n']);
+        assert_eq!(follower.poll().unwrap(), appended("é\n"));
+    }
+
+    #[test]
+    fn truncated_file_starts_over() {
+        let path = test_dir("follow-truncate").join("app.log");
+        fs::write(&path, "one\ntwo\n").unwrap();
+        let mut follower = follower(&path);
+
+        // Truncated in place, as by `> app.log`
+        fs::OpenOptions::new()
+            .write(true)
+            .truncate(true)
+            .open(&path)
+            .unwrap()
+            .write_all(b"x\n")
+            .unwrap();
+        let restarted = FollowEvent::Restarted {
+            text: "x\n".to_string(),
+            whole_file: true,
+        };
+        assert_eq!(follower.poll().unwrap(), Some(restarted));
+        append(&path, b"y\n");
+        assert_eq!(follower.poll().unwrap(), appended("y\n"));
+    }
+
+    // Windows may give a file recreated under the same name the old creation time
+    #[cfg(unix)]
+    #[test]
+    fn rotated_file_starts_over() {
+        let dir = test_dir("follow-rotate");
+        let path = dir.join("app.log");
+        fs::write(&path, "old\n").unwrap();
+        let mut follower = follower(&path);
+
+        // Gone for a moment, then replaced by a new file longer than the old one
+        fs::rename(&path, dir.join("app.log.1")).unwrap();
+        assert_eq!(
+            follower.poll().unwrap_err().kind(),
+            std::io::ErrorKind::NotFound
+        );
+        fs::write(&path, "new file\n").unwrap();
+        let restarted = FollowEvent::Restarted {
+            text: "new file\n".to_string(),
+            whole_file: true,
+        };
+        assert_eq!(follower.poll().unwrap(), Some(restarted));
+        append(&path, b"more\n");
+        assert_eq!(follower.poll().unwrap(), appended("more\n"));
+    }
+
+    #[test]
+    fn burst_over_the_large_file_threshold_shows_the_tail() {
+        let path = test_dir("follow-burst").join("app.log");
+        fs::write(&path, "start\n").unwrap();
+        let mut follower = follower(&path);
+
+        let mut burst = String::new();
+        let mut n = 0;
+        while burst.len() as u64 <= LARGE_FILE_THRESHOLD {
+            burst.push_str(&format!("line {}\n", n));
+            n += 1;
+        }
+        append(&path, burst.as_bytes());
+        let Some(FollowEvent::Restarted { text, whole_file }) = follower.poll().unwrap() else {
+            panic!("expected a restart");
+        };
+        assert!(!whole_file);
+        assert!(text.len() as u64 <= TAIL_BYTES);
+        // Starts at a whole line and ends with the last one
+        assert!(burst.contains(&format!("\n{}", text)));
+        assert!(text.ends_with(&format!("line {}\n", n - 1)));
+
+        append(&path, b"after\n");
+        assert_eq!(follower.poll().unwrap(), appended("after\n"));
+    }
+}
@@ -0,0 +1,133 @@
+//! Batch mode: `notos --headless --run <command> [FILE] [-o OUTPUT]` runs a
+//! plugin command on a file or standard input and writes the result, without
+//! opening a window. Input is decoded and output encoded like a tab that is
+//! opened and saved, so the encoding and line endings are kept.
+
+use std::io::{Read, Write};
+use std::path::Path;
+
+use notos_sdk::{PluginAction, TextEdit};
+
+use crate::cli::Cli;
+use crate::editor::{char_to_byte, EditorTab};
+use crate::plugin::PluginManager;
+use crate::settings::SettingsStore;
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+/// Run the command described by `cli`, returning the process exit code.
+pub fn run(cli: Cli) -> i32 {
+    match transform(cli) {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("notos: {}", e);
+            1
+        }
+    }
+}
+
+fn transform(cli: Cli) -> Result<()> {
+    let command = cli.run.unwrap_or_default();
+    let input = cli.open.files.first().map(|f| f.path.clone());
+    let bytes = match &input {
+        Some(path) => std::fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?,
+        None => {
+            let mut bytes = Vec::new();
+            std::io::stdin().read_to_end(&mut bytes)?;
+            bytes
+        }
+    };
+    let mut tab = EditorTab::from_bytes(input, bytes, cli.open.encoding);
+    // Unlike the editor, batch mode always has the whole input in memory
+    tab.large_file = false;
+
+    let ctx = egui::Context::default();
+    let mut settings = SettingsStore::load();
+    let mut plugins = PluginManager::new(cli.plugin_paths);
+    plugins.load_plugins();
+    plugins.attach(&ctx, &mut settings);
+    plugins.on_load(&ctx);
+
+    let result = run_command(&mut plugins, &command, &mut tab);
+    plugins.on_unload();
+    if !result? {
+        return Err(format!("{} did not produce a result", command).into());
+    }
+
+    match cli.output.filter(|path| path != Path::new("-")) {
+        Some(path) => {
+            tab.set_path(path.clone());
+            let backup = crate::settings::backup(&settings.handle(crate::settings::EDITOR_ID));
+            tab.save(backup)
+                .map_err(|e| format!("{}: {}", path.display(), e))?;
+        }
+        None => {
+            let mut stdout = std::io::stdout().lock();
+            stdout.write_all(&tab.encoded_bytes())?;
+            stdout.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// Run the plugin command `id` (`<plugin id>.<command id>`) on `tab`, waiting
+/// for any background jobs it starts. Returns whether the text was changed.
+fn run_command(plugins: &mut PluginManager, id: &str, tab: &mut EditorTab) -> Result<bool> {
+    let (plugin_id, command_id) = plugins
+        .commands()
+        .into_iter()
+        .find_map(|(plugin_id, _, commands)| {
+            let command = commands
+                .into_iter()
+                .find(|c| format!("{}.{}", plugin_id, c.id) == id)?;
+            Some((plugin_id, command.id))
+        })
+        .ok_or_else(|| match plugins.pending_trust() {
+            // Batch mode cannot ask whether to trust a new plugin
+            Some(_) => format!(
+                "unknown command: {} (some plugins await approval in the editor)",
+                id
+            ),
+            None => format!("unknown command: {}", id),
+        })?;
+
+    let tabs = std::slice::from_ref(&*tab);
+    let mut actions = {
+        let ed = crate::app::get_ed_ctx(tabs, Some(tab.id), None);
+        vec![plugins.run_command(&plugin_id, &command_id, &ed)]
+    };
+    while !plugins.running_jobs().is_empty() {
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+    actions.extend(plugins.take_job_results());
+
+    let mut changed = false;
+    for action in actions {
+        changed |= apply(tab, action)?;
+    }
+    Ok(changed)
+}
+
+/// Apply the text actions of a plugin to `tab`; others have no meaning here.
+fn apply(tab: &mut EditorTab, action: PluginAction) -> Result<bool> {
+    match action {
+        PluginAction::ReplaceAll(text) => {
+            tab.content = text;
+            tab.refresh_metadata();
+            Ok(true)
+        }
+        PluginAction::ReplaceSelection(text) => {
+            let (a, b) = tab.cursor_range.unwrap_or((0, 0));
+            let start = char_to_byte(&tab.content, a.min(b));
+            let end = char_to_byte(&tab.content, a.max(b));
+            tab.apply_edits(vec![TextEdit::new(start, end, text)])?;
+            Ok(true)
+        }
+        PluginAction::ApplyEdits(edits) => {
+            tab.apply_edits(edits)?;
+            Ok(true)
+        }
+        PluginAction::InTab(_, action) => apply(tab, *action),
+        _ => Ok(false),
+    }
+}
@@ -0,0 +1,311 @@
+//! Single-instance IPC.
+//!
+//! The first Notos a user starts listens on a per-user local socket: a Unix
+//! socket in `$XDG_RUNTIME_DIR` (or the config directory), or a named pipe on
+//! Windows. Later invocations connect to it and hand over their request
+//! instead of opening a second window. A connection carries one request and
+//! its replies as JSON lines, each tagged with the protocol version. The first
+//! reply comes right away; a request that waits for something (`--wait`) gets
+//! a second one when that happens. An `Open` request that reads standard input
+//! is followed by `Input` messages with the text as it arrives and `InputEnd`.
+//! A connection that starts with a JSON-RPC request is a remote-control
+//! session instead, see [`crate::rpc`].
+
+use interprocess::local_socket::{prelude::*, ListenerOptions, Name, Stream};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::time::Duration;
+
+use crate::cli::OpenRequest;
+use crate::rpc::Call;
+
+/// Version of the message format. A peer speaking another version is refused.
+pub const PROTOCOL_VERSION: u32 = 2;
+
+/// How long either side waits for the other to send its message.
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Longest accepted message.
+const MAX_MESSAGE_LEN: u64 = 64 * 1024 * 1024;
+
+/// Connection id of requests from this process's own command line.
+pub const LOCAL_CONNECTION: u64 = 0;
+
+static NEXT_CONNECTION: AtomicU64 = AtomicU64::new(LOCAL_CONNECTION + 1);
+
+/// What a second invocation asks the running instance to do.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Request {
+    /// Open files as given on the command line (with absolute paths) and
+    /// bring the window to the front.
+    Open(OpenRequest),
+    /// More text for the tab opened from standard input on this connection.
+    Input { text: String },
+    /// Standard input has ended.
+    InputEnd,
+    /// A remote-control call, answered with `Result` or `Error`.
+    Call(Call),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum Reply {
+    Ok,
+    /// The result of a `Call`.
+    Result {
+        result: serde_json::Value,
+    },
+    /// All files and tabs of a waiting `Open` request have been closed.
+    Closed,
+    Error {
+        message: String,
+    },
+}
+
+/// A request received by the server, passed to the UI thread.
+pub struct Incoming {
+    /// Identifies the connection, so `Input` can be matched with its `Open`.
+    pub connection: u64,
+    pub request: Request,
+    /// Later replies for the client, if there is one. The connection stays
+    /// open until this is dropped.
+    pub reply: Option<Sender<Reply>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Envelope<T> {
+    version: u32,
+    #[serde(flatten)]
+    body: T,
+}
+
+/// Listening side of the single-instance socket, owned by the first instance.
+pub struct Server {
+    listener: interprocess::local_socket::Listener,
+}
+
+impl Server {
+    /// Claim the socket. Fails with `AddrInUse` if another instance has it.
+    pub fn bind() -> std::io::Result<Self> {
+        Self::create(false)
+    }
+
+    /// Claim the socket left behind by an instance that is no longer running.
+    pub fn bind_stale() -> std::io::Result<Self> {
+        Self::create(true)
+    }
+
+    fn create(overwrite: bool) -> std::io::Result<Self> {
+        Self::bind_name(socket_name()?, overwrite)
+    }
+
+    /// Listen on `name` instead of the per-user socket.
+    pub(crate) fn bind_name(name: Name<'static>, overwrite: bool) -> std::io::Result<Self> {
+        let options = ListenerOptions::new().name(name).try_overwrite(overwrite);
+        // Only the current user may connect
+        #[cfg(unix)]
+        let options = {
+            use interprocess::os::unix::local_socket::ListenerOptionsExt;
+            options.mode(0o600)
+        };
+        Ok(Self {
+            listener: options.create_sync()?,
+        })
+    }
+
+    /// Accept connections on a background thread, serving each on its own
+    /// thread, passing requests to `tx` and waking the UI.
+    pub fn spawn(self, tx: Sender<Incoming>, ctx: egui::Context) {
+        std::thread::spawn(move || {
+            for conn in self.listener.incoming() {
+                match conn {
+                    Ok(conn) => {
+                        let tx = tx.clone();
+                        let ctx = ctx.clone();
+                        std::thread::spawn(move || {
+                            if let Err(e) = serve(conn, &tx, &ctx) {
+                                log::warn!("IPC connection failed: {}", e);
+                            }
+                        });
+                    }
+                    Err(e) => log::warn!("IPC accept failed: {}", e),
+                }
+            }
+        });
+    }
+}
+
+fn serve(conn: Stream, tx: &Sender<Incoming>, ctx: &egui::Context) -> std::io::Result<()> {
+    conn.set_recv_timeout(Some(TIMEOUT))?;
+    conn.set_send_timeout(Some(TIMEOUT))?;
+    let mut conn = BufReader::new(conn);
+    let first = match read_line(&mut conn) {
+        Ok(line) => line,
+        Err(e) => {
+            let message = e.to_string();
+            return write_message(conn.get_mut(), &Reply::Error { message });
+        }
+    };
+
+    let connection = NEXT_CONNECTION.fetch_add(1, Ordering::Relaxed);
+    let forward = |request, reply| {
+        let sent = tx.send(Incoming {
+            connection,
+            request,
+            reply,
+        });
+        ctx.request_repaint();
+        sent.is_ok()
+    };
+
+    if crate::rpc::is_rpc(&first) {
+        return crate::rpc::serve_session(first, &mut conn, forward);
+    }
+
+    let request = match serde_json::from_str::<Envelope<Request>>(&first) {
+        Ok(Envelope { version, .. }) if version != PROTOCOL_VERSION => {
+            let message = format!(
+                "unsupported protocol version {}, expected {}",
+                version, PROTOCOL_VERSION
+            );
+            return write_message(conn.get_mut(), &Reply::Error { message });
+        }
+        Ok(Envelope { body, .. }) => body,
+        Err(e) => {
+            let message = e.to_string();
+            return write_message(conn.get_mut(), &Reply::Error { message });
+        }
+    };
+
+    let reads_input = matches!(&request, Request::Open(open) if open.stdin);
+    let (reply_tx, reply_rx) = mpsc::channel();
+    if !forward(request, Some(reply_tx)) {
+        let message = "the editor is shutting down".to_string();
+        return write_message(conn.get_mut(), &Reply::Error { message });
+    }
+    write_message(conn.get_mut(), &Reply::Ok)?;
+
+    if reads_input {
+        // Producers like `tail -f` can be quiet for a long time
+        conn.get_ref().set_recv_timeout(None)?;
+        let result = loop {
+            match read_message::<Envelope<Request>>(&mut conn) {
+                Ok(Envelope {
+                    body: input @ Request::Input { .. },
+                    ..
+                }) => {
+                    forward(input, None);
+                }
+                Ok(_) => break Ok(()),
+                // The client went away without saying so
+                Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break Ok(()),
+                Err(e) => break Err(e),
+            }
+        };
+        forward(Request::InputEnd, None);
+        result?;
+    }
+
+    for reply in reply_rx {
+        write_message(conn.get_mut(), &reply)?;
+    }
+    Ok(())
+}
+
+/// Connection to the running instance.
+pub struct Client {
+    conn: BufReader<Stream>,
+}
+
+impl Client {
+    /// Fails with `ConnectionRefused` or `NotFound` when no instance is listening.
+    pub fn connect() -> std::io::Result<Self> {
+        let conn = Stream::connect(socket_name()?)?;
+        conn.set_recv_timeout(Some(TIMEOUT))?;
+        conn.set_send_timeout(Some(TIMEOUT))?;
+        Ok(Self {
+            conn: BufReader::new(conn),
+        })
+    }
+
+    /// Send `request` and read the first reply. Fails with `TimedOut` or
+    /// `WouldBlock` when the instance does not answer.
+    pub fn send(&mut self, request: &Request) -> std::io::Result<Reply> {
+        write_message(self.conn.get_mut(), request)?;
+        self.read_reply()
+    }
+
+    /// Send a request that gets no reply, e.g. [`Request::Input`].
+    pub fn post(&mut self, request: &Request) -> std::io::Result<()> {
+        write_message(self.conn.get_mut(), request)
+    }
+
+    /// Wait as long as it takes for the next reply, e.g. [`Reply::Closed`].
+    pub fn wait(&mut self) -> std::io::Result<Reply> {
+        self.conn.get_ref().set_recv_timeout(None)?;
+        self.read_reply()
+    }
+
+    fn read_reply(&mut self) -> std::io::Result<Reply> {
+        read_message::<Envelope<Reply>>(&mut self.conn).map(|envelope| envelope.body)
+    }
+}
+
+fn write_message<T: Serialize>(conn: &mut Stream, body: &T) -> std::io::Result<()> {
+    write_line(
+        conn,
+        &Envelope {
+            version: PROTOCOL_VERSION,
+            body,
+        },
+    )
+}
+
+fn read_message<T: for<'de> Deserialize<'de>>(conn: &mut BufReader<Stream>) -> std::io::Result<T> {
+    serde_json::from_str(&read_line(conn)?).map_err(std::io::Error::from)
+}
+
+/// Write `value` as one line of JSON.
+pub(crate) fn write_line<T: Serialize>(conn: &mut Stream, value: &T) -> std::io::Result<()> {
+    let mut line = serde_json::to_vec(value)?;
+    line.push(b'\n');
+    conn.write_all(&line)?;
+    conn.flush()
+}
+
+/// Read one line. Fails with `UnexpectedEof` when the peer closed the connection.
+pub(crate) fn read_line(conn: &mut BufReader<Stream>) -> std::io::Result<String> {
+    let mut line = String::new();
+    conn.by_ref().take(MAX_MESSAGE_LEN).read_line(&mut line)?;
+    if !line.ends_with('\n') {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::UnexpectedEof,
+            "incomplete message",
+        ));
+    }
+    Ok(line)
+}
+
+/// The per-user socket: `notos.sock` in the runtime directory on Unix, a
+/// named pipe including the user name on Windows.
+pub(crate) fn socket_name() -> std::io::Result<Name<'static>> {
+    #[cfg(unix)]
+    {
+        use interprocess::local_socket::GenericFilePath;
+        let dir = std::env::var_os("XDG_RUNTIME_DIR")
+            .map(PathBuf::from)
+            .filter(|d| d.is_dir())
+            .unwrap_or_else(crate::utils::config_dir);
+        dir.join("notos.sock").to_fs_name::<GenericFilePath>()
+    }
+    #[cfg(windows)]
+    {
+        use interprocess::local_socket::GenericNamespaced;
+        let user = std::env::var("USERNAME").unwrap_or_default();
+        format!("notos-{}", user).to_ns_name::<GenericNamespaced>()
+    }
+}
@@ -0,0 +1,207 @@
+//! User keybinding overrides and chord-sequence dispatch.
+//!
+//! `keybindings.json` in the config directory is a list of
+//! `{ "key": "Ctrl+K Ctrl+C", "command": "edit.toggle_comment" }` entries.
+//! A command listed in the file has its default bindings replaced by the
+//! listed keys; `"key": null` leaves it unbound. Commands not mentioned keep
+//! the defaults declared by the host or the owning plugin.
+
+use crate::commands::{CommandRegistry, KeyChord, Keybinding};
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+/// How long the second chord of a sequence is awaited.
+const CHORD_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Serialize, Deserialize)]
+struct KeymapEntry {
+    key: Option<String>,
+    command: String,
+}
+
+/// A keybinding that more than one command claims, or a single chord that
+/// shadows the first chord of a sequence.
+pub struct Conflict {
+    pub binding: Keybinding,
+    pub commands: Vec<String>,
+}
+
+#[derive(Default)]
+pub struct Keymap {
+    overrides: BTreeMap<String, Vec<Keybinding>>,
+    pending: Option<(KeyChord, Instant)>,
+}
+
+impl Keymap {
+    pub fn path() -> std::path::PathBuf {
+        crate::utils::config_dir().join("keybindings.json")
+    }
+
+    /// Load the user's keymap. A missing file yields an empty keymap; invalid
+    /// entries are logged and skipped.
+    pub fn load() -> Self {
+        let mut keymap = Self::default();
+        let text = match std::fs::read_to_string(Self::path()) {
+            Ok(text) => text,
+            Err(_) => return keymap,
+        };
+        let entries: Vec<KeymapEntry> = match serde_json::from_str(&text) {
+            Ok(entries) => entries,
+            Err(e) => {
+                log::warn!("Ignoring invalid keybindings.json: {}", e);
+                return keymap;
+            }
+        };
+        for entry in entries {
+            let bindings = keymap.overrides.entry(entry.command.clone()).or_default();
+            let Some(key) = entry.key else { continue };
+            match Keybinding::parse(&key) {
+                Some(binding) => bindings.push(binding),
+                None => log::warn!("Invalid keybinding {:?} for {}", key, entry.command),
+            }
+        }
+        keymap
+    }
+
+    pub fn save(&self) -> std::result::Result<(), Box<dyn std::error::Error>> {
+        let mut entries = Vec::new();
+        for (command, bindings) in &self.overrides {
+            if bindings.is_empty() {
+                entries.push(KeymapEntry {
+                    key: None,
+                    command: command.clone(),
+                });
+            }
+            for binding in bindings {
+                entries.push(KeymapEntry {
+                    key: Some(binding.to_string()),
+                    command: command.clone(),
+                });
+            }
+        }
+        std::fs::write(Self::path(), serde_json::to_string_pretty(&entries)?)?;
+        Ok(())
+    }
+
+    /// Recompute the effective bindings of every registered command.
+    pub fn apply(&self, registry: &mut CommandRegistry) {
+        for command in registry.iter_mut() {
+            command.keybindings = self
+                .overrides
+                .get(&command.id)
+                .unwrap_or(&command.default_keybindings)
+                .clone();
+        }
+    }
+
+    pub fn set(&mut self, command_id: &str, bindings: Vec<Keybinding>) {
+        self.overrides.insert(command_id.to_string(), bindings);
+    }
+
+    pub fn reset(&mut self, command_id: &str) {
+        self.overrides.remove(command_id);
+    }
+
+    pub fn is_overridden(&self, command_id: &str) -> bool {
+        self.overrides.contains_key(command_id)
+    }
+
+    /// First chord of a sequence that is waiting for its second chord.
+    pub fn pending(&self) -> Option<KeyChord> {
+        self.pending.map(|(chord, _)| chord)
+    }
+
+    /// Consume this frame's key presses and return the command they trigger.
+    pub fn dispatch(&mut self, ctx: &egui::Context, registry: &CommandRegistry) -> Option<String> {
+        let bindings = registry.bindings();
+
+        if let Some((first, started)) = self.pending {
+            if started.elapsed() > CHORD_TIMEOUT {
+                self.pending = None;
+            } else {
+                let mut candidates: Vec<&(Keybinding, String)> = bindings
+                    .iter()
+                    .filter(|(b, _)| b.is_sequence() && b.first() == first)
+                    .collect();
+                candidates.sort_by_key(|(b, _)| std::cmp::Reverse(b.0[1].specificity()));
+                for (binding, id) in candidates {
+                    if consume_chord(ctx, binding.0[1]) {
+                        self.pending = None;
+                        return Some(id.clone());
+                    }
+                }
+                // Any other key press abandons the sequence
+                if ctx.input(|i| {
+                    i.events
+                        .iter()
+                        .any(|e| matches!(e, egui::Event::Key { pressed: true, .. }))
+                }) {
+                    self.pending = None;
+                }
+                return None;
+            }
+        }
+
+        for (binding, id) in &bindings {
+            if consume_chord(ctx, binding.first()) {
+                if binding.is_sequence() {
+                    self.pending = Some((binding.first(), Instant::now()));
+                    return None;
+                }
+                return Some(id.clone());
+            }
+        }
+        None
+    }
+}
+
+fn consume_chord(ctx: &egui::Context, chord: KeyChord) -> bool {
+    ctx.input_mut(|i| {
+        if !i.consume_key(chord.modifiers, chord.key) {
+            return false;
+        }
+        // Keep a bound Ctrl+C/X/V from also reaching the editor as a clipboard event
+        if chord.modifiers.command {
+            i.events.retain(|e| {
+                !matches!(
+                    e,
+                    egui::Event::Copy | egui::Event::Cut | egui::Event::Paste(_)
+                )
+            });
+        }
+        true
+    })
+}
+
+/// Find bindings claimed by more than one command, including single chords
+/// that make a sequence with the same first chord unreachable.
+pub fn conflicts(registry: &CommandRegistry) -> Vec<Conflict> {
+    let mut by_binding: Vec<Conflict> = Vec::new();
+    for (binding, id) in registry.bindings() {
+        match by_binding.iter_mut().find(|c| c.binding == binding) {
+            Some(conflict) => conflict.commands.push(id),
+            None => by_binding.push(Conflict {
+                binding,
+                commands: vec![id],
+            }),
+        }
+    }
+
+    let singles: Vec<(Keybinding, Vec<String>)> = by_binding
+        .iter()
+        .filter(|c| !c.binding.is_sequence())
+        .map(|c| (c.binding.clone(), c.commands.clone()))
+        .collect();
+    for conflict in by_binding.iter_mut().filter(|c| c.binding.is_sequence()) {
+        for (single, ids) in &singles {
+            if single.first() == conflict.binding.first() {
+                conflict.commands.extend(ids.iter().cloned());
+            }
+        }
+    }
+
+    by_binding.retain(|c| c.commands.len() > 1);
+    by_binding
+}
@@ -0,0 +1,198 @@
+//! Keyboard macros.
+//!
+//! While recording, editing input sent to the active tab (typed text, keys,
+//! clipboard events), Find Next jumps and executed commands are captured as
+//! [`MacroStep`]s. Playback feeds the steps back one batch per frame. Named
+//! macros are saved in `macros.json` in the config directory.
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::commands::KeyChord;
+
+/// Upper bound on repetitions of "Play to End of File".
+pub const MAX_PLAYBACK_REPEATS: usize = 10_000;
+
+/// One recorded action.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum MacroStep {
+    /// Typed text.
+    Text(String),
+    /// A key press such as `"Ctrl+ArrowLeft"` or `"Enter"`.
+    Key(String),
+    Paste(String),
+    Cut,
+    Copy,
+    /// Find Next with this query.
+    FindNext(String),
+    /// A registered command, by id.
+    Command(String),
+}
+
+impl MacroStep {
+    /// Capture an input event aimed at the editor, if it is one a macro replays.
+    pub fn from_event(event: &egui::Event) -> Option<Self> {
+        match event {
+            egui::Event::Text(text) => Some(Self::Text(text.clone())),
+            egui::Event::Paste(text) => Some(Self::Paste(text.clone())),
+            egui::Event::Cut => Some(Self::Cut),
+            egui::Event::Copy => Some(Self::Copy),
+            // Plain character keys also arrive as `Text`; only keep keys the editor acts on
+            egui::Event::Key {
+                key,
+                pressed: true,
+                modifiers,
+                ..
+            } if modifiers.command || modifiers.alt || is_editing_key(*key) => {
+                Some(Self::Key(KeyChord::new(*modifiers, *key).to_string()))
+            }
+            _ => None,
+        }
+    }
+
+    /// The input event this step replays, for editing steps.
+    pub fn to_event(&self) -> Option<egui::Event> {
+        match self {
+            Self::Text(text) => Some(egui::Event::Text(text.clone())),
+            Self::Paste(text) => Some(egui::Event::Paste(text.clone())),
+            Self::Cut => Some(egui::Event::Cut),
+            Self::Copy => Some(egui::Event::Copy),
+            Self::Key(chord) => {
+                let chord = KeyChord::parse(chord)?;
+                let command = chord.modifiers.command;
+                let mac = cfg!(target_os = "macos");
+                Some(egui::Event::Key {
+                    key: chord.key,
+                    physical_key: None,
+                    pressed: true,
+                    repeat: false,
+                    modifiers: egui::Modifiers {
+                        ctrl: command && !mac,
+                        mac_cmd: command && mac,
+                        ..chord.modifiers
+                    },
+                })
+            }
+            Self::FindNext(_) | Self::Command(_) => None,
+        }
+    }
+}
+
+fn is_editing_key(key: egui::Key) -> bool {
+    use egui::Key;
+    matches!(
+        key,
+        Key::ArrowDown
+            | Key::ArrowLeft
+            | Key::ArrowRight
+            | Key::ArrowUp
+            | Key::Home
+            | Key::End
+            | Key::PageUp
+            | Key::PageDown
+            | Key::Backspace
+            | Key::Delete
+            | Key::Enter
+            | Key::Tab
+    )
+}
+
+/// Append `step` to a recording, merging consecutive typed text.
+pub fn push_step(steps: &mut Vec<MacroStep>, step: MacroStep) {
+    if let (Some(MacroStep::Text(last)), MacroStep::Text(text)) = (steps.last_mut(), &step) {
+        last.push_str(text);
+        return;
+    }
+    steps.push(step);
+}
+
+/// Whether running `command` is recorded into a macro. Only commands acting on
+/// the document are: view and application commands, dialogs, macros and
+/// (background) scripts are left out.
+pub fn is_recordable(command: &str) -> bool {
+    let excluded_prefixes = ["view.", "app.", "macro.", "script.", "scripts."];
+    !(excluded_prefixes.iter().any(|p| command.starts_with(p))
+        || matches!(
+            command,
+            "file.open" | "edit.find" | "edit.replace" | "edit.goto_line"
+        ))
+}
+
+/// Named macros, persisted in `macros.json`.
+#[derive(Default)]
+pub struct MacroStore {
+    pub macros: BTreeMap<String, Vec<MacroStep>>,
+}
+
+impl MacroStore {
+    fn path() -> std::path::PathBuf {
+        crate::utils::config_dir().join("macros.json")
+    }
+
+    pub fn load() -> Self {
+        let text = match std::fs::read_to_string(Self::path()) {
+            Ok(text) => text,
+            Err(_) => return Self::default(),
+        };
+        match serde_json::from_str(&text) {
+            Ok(macros) => Self { macros },
+            Err(e) => {
+                log::warn!("Ignoring invalid macros.json: {}", e);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self) -> std::result::Result<(), Box<dyn std::error::Error>> {
+        std::fs::write(Self::path(), serde_json::to_string_pretty(&self.macros)?)?;
+        Ok(())
+    }
+}
+
+/// What the Edit → Macros menu needs to know.
+pub struct MacroMenu {
+    pub recording: bool,
+    pub playing: bool,
+    /// Whether there is a last recorded macro to play.
+    pub has_macro: bool,
+    pub saved: Vec<String>,
+}
+
+/// How many times a playback repeats the macro.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Repeat {
+    Times(usize),
+    /// Until the cursor reaches the end of the document or stops moving forward.
+    ToEnd,
+}
+
+/// A macro being played back on one tab.
+pub struct Playback {
+    pub steps: Vec<MacroStep>,
+    pub tab_id: crate::editor::TabId,
+    pub repeat: Repeat,
+    /// Index of the next step.
+    pub pos: usize,
+    /// Completed repetitions.
+    pub done: usize,
+    /// Cursor position when the current repetition started.
+    pub start_cursor: usize,
+}
+
+impl Playback {
+    /// Next batch to run: consecutive editing events, or a single other step.
+    pub fn next_batch(&mut self) -> Vec<MacroStep> {
+        let first_is_event = self.steps[self.pos].to_event().is_some();
+        let mut batch = vec![self.steps[self.pos].clone()];
+        self.pos += 1;
+        if first_is_event {
+            while self.pos < self.steps.len() && self.steps[self.pos].to_event().is_some() {
+                batch.push(self.steps[self.pos].clone());
+                self.pos += 1;
+            }
+        }
+        batch
+    }
+}
@@ -1,12 +1,28 @@
 #![windows_subsystem = "windows"] // Hide console window on Windows
 
 mod app;
+mod cli;
+mod commands;
 mod dialogs;
 mod editor;
+mod follow;
+mod headless;
+mod ipc;
+mod keymap;
+mod macros;
 mod plugin;
+mod recovery;
+mod rpc;
+mod save;
+mod scripting;
+mod settings;
+mod trust;
 mod ui;
 mod undo_manager;
 mod utils;
+mod watcher;
+#[cfg(feature = "wasm-plugins")]
+mod wasm_plugin;
 
 use app::NotosApp;
 use utils::load_icon;
@@ -24,66 +40,151 @@ fn main() -> eframe::Result<()> {
         ..Default::default()
     };
 
-    let args: Vec<String> = std::env::args()
-        .skip(1)
-        .map(|a| {
-            std::fs::canonicalize(&a)
-                .unwrap_or_else(|_| std::path::PathBuf::from(a))
-                .to_string_lossy()
-                .into_owned()
-        })
-        .collect();
+    let command = cli::parse(std::env::args_os().skip(1));
+    // Everything but the window prints its results
+    if !matches!(&command, Ok(cli::Command::Run(cli)) if !cli.headless && cli.call.is_none()) {
+        utils::attach_console();
+    }
+    let cli = match command {
+        Ok(cli::Command::Run(cli)) => *cli,
+        Ok(cli::Command::Help) => {
+            print!("{}", cli::USAGE);
+            return Ok(());
+        }
+        Ok(cli::Command::Version) => {
+            println!("notos {}", env!("CARGO_PKG_VERSION"));
+            return Ok(());
+        }
+        Err(e) => {
+            eprintln!("notos: {}\n\n{}", e, cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+    if cli.headless {
+        std::process::exit(headless::run(cli));
+    }
+    if let Some((method, params)) = cli.call {
+        std::process::exit(remote_call(&method, params));
+    }
+    let mut open = cli.open;
+    open.resolve_paths();
+
+    // Single instance: hand the request to a running instance if there is one
+    let server = if cli.new_window {
+        None
+    } else {
+        match ipc::Server::bind() {
+            Ok(server) => Some(server),
+            Err(e) if e.kind() == std::io::ErrorKind::AddrInUse => match hand_off(&open) {
+                Ok(()) => return Ok(()),
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        std::io::ErrorKind::ConnectionRefused | std::io::ErrorKind::NotFound
+                    ) =>
+                {
+                    // Left behind by an instance that exited without cleaning up
+                    ipc::Server::bind_stale()
+                        .map_err(|e| log::warn!("Failed to take over the IPC socket: {}", e))
+                        .ok()
+                }
+                Err(e) => {
+                    log::warn!(
+                        "Could not hand over to the running instance ({}), starting a new one",
+                        e
+                    );
+                    None
+                }
+            },
+            Err(e) => {
+                log::warn!(
+                    "Failed to create the IPC socket, running without single instance: {}",
+                    e
+                );
+                None
+            }
+        }
+    };
+    // A window that closes along with its files must not take over other requests
+    let server = server.filter(|_| !open.wait);
 
-    // Single instance logic: try to bind to a local port
     let (tx, rx) = std::sync::mpsc::channel();
-    let port = 55123;
-    let addr = format!("127.0.0.1:{}", port);
+    eframe::run_native(
+        &format!("Notos Text Editor v{}", env!("CARGO_PKG_VERSION")),
+        native_options,
+        Box::new(move |cc| {
+            if open.stdin {
+                spawn_stdin_reader(open.encoding, tx.clone(), cc.egui_ctx.clone());
+            }
+            if let Some(server) = server {
+                server.spawn(tx, cc.egui_ctx.clone());
+            }
+            Ok(Box::new(NotosApp::new(cc, open, cli.plugin_paths, rx)))
+        }),
+    )
+}
 
-    match std::net::TcpListener::bind(&addr) {
-        Ok(listener) => eframe::run_native(
-            &format!("Notos Text Editor v{}", env!("CARGO_PKG_VERSION")),
-            native_options,
-            Box::new(move |cc| {
-                let ctx = cc.egui_ctx.clone();
-                std::thread::spawn(move || {
-                    for mut stream in listener.incoming().flatten() {
-                        let mut buffer = String::new();
-                        use std::io::Read;
-                        if stream.read_to_string(&mut buffer).is_ok() {
-                            let paths: Vec<String> = buffer
-                                .split('\n')
-                                .filter(|s| !s.is_empty())
-                                .map(|s| s.to_string())
-                                .collect();
-                            for path in paths {
-                                let _ = tx.send(path);
-                            }
-                            ctx.request_repaint();
-                        }
-                    }
-                });
-                Ok(Box::new(NotosApp::new(cc, args, rx)))
-            }),
-        ),
-        Err(_) => {
-            // Another instance is likely running, send the paths and exit
-            if !args.is_empty() {
-                use std::io::Write;
-                if let Ok(mut stream) = std::net::TcpStream::connect(&addr) {
-                    let abs_paths: Vec<String> = args
-                        .iter()
-                        .map(|a| {
-                            std::fs::canonicalize(a)
-                                .unwrap_or_else(|_| std::path::PathBuf::from(a))
-                                .to_string_lossy()
-                                .into_owned()
-                        })
-                        .collect();
-                    let data = abs_paths.join("\n");
-                    let _ = stream.write_all(data.as_bytes());
-                }
+/// Pass `open` to the running instance, followed by standard input if it
+/// reads it, and with `--wait` block until it reports the files closed.
+fn hand_off(open: &cli::OpenRequest) -> std::io::Result<()> {
+    let mut client = ipc::Client::connect()?;
+    if let ipc::Reply::Error { message } = client.send(&ipc::Request::Open(open.clone()))? {
+        return Err(std::io::Error::other(message));
+    }
+    if open.stdin {
+        cli::stream_stdin(open.encoding, |text| client.post(&ipc::Request::Input { text }).is_ok());
+        let _ = client.post(&ipc::Request::InputEnd);
+    }
+    if open.wait {
+        // The instance exiting also ends the wait, as it closes the connection
+        if let Ok(ipc::Reply::Error { message }) = client.wait() {
+            utils::attach_console();
+            eprintln!("notos: {}", message);
+            std::process::exit(1);
+        }
+    }
+    Ok(())
+}
+
+/// Send a remote-control call to the running instance and print its result,
+/// returning the process exit code.
+fn remote_call(method: &str, params: serde_json::Value) -> i32 {
+    match rpc::RpcClient::connect().and_then(|mut client| client.call(method, params)) {
+        Ok(Ok(result)) => {
+            if !result.is_null() {
+                println!("{}", result);
             }
-            Ok(())
+            0
+        }
+        Ok(Err(e)) => {
+            eprintln!("notos: {}", e.message);
+            1
+        }
+        Err(e) => {
+            eprintln!("notos: cannot reach the running instance: {}", e);
+            1
         }
     }
 }
+
+/// Feed standard input to this process's own window, the same way input from
+/// another process arrives.
+fn spawn_stdin_reader(
+    encoding: Option<editor::Encoding>,
+    tx: std::sync::mpsc::Sender<ipc::Incoming>,
+    ctx: eframe::egui::Context,
+) {
+    std::thread::spawn(move || {
+        let send = |request| {
+            let sent = tx.send(ipc::Incoming {
+                connection: ipc::LOCAL_CONNECTION,
+                request,
+                reply: None,
+            });
+            ctx.request_repaint();
+            sent.is_ok()
+        };
+        cli::stream_stdin(encoding, |text| send(ipc::Request::Input { text }));
+        send(ipc::Request::InputEnd);
+    });
+}
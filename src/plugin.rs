@@ -1,137 +1,849 @@
 use egui::Context;
-use notos_sdk::{CreatePluginFn, DestroyPluginFn, EditorContext, NotosPlugin, PluginAction};
-use std::collections::HashSet;
+use notos_sdk::{
+    CreatePluginFn, DestroyPluginFn, EditorContext, HostServices, JobId, JobStatus, NotosPlugin,
+    PluginAction, PluginCommand, PluginId, PluginPanel, SettingSpec, StatusBarItem,
+    PLUGIN_ID_SYMBOL,
+};
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::trust::{TrustDecision, TrustStore, UntrustedPlugin, Verdict};
+
+/// Environment variable that turns on plugin developer mode (hot reload).
+pub const DEV_MODE_ENV: &str = "NOTOS_PLUGIN_DEV";
+
+/// Environment variable with extra plugin files or directories, separated like `PATH`.
+pub const PLUGIN_PATH_ENV: &str = "NOTOS_PLUGIN_PATH";
+
+/// How often developer mode checks the plugin directories for changes.
+const DEV_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A library must be this old before it is reloaded, so half-written builds are skipped.
+const DEV_SETTLE_TIME: Duration = Duration::from_millis(500);
+
+/// Private copies older than this are left over from earlier runs, and removed
+/// at startup (unless still loaded).
+const STALE_SHADOW_AGE: Duration = Duration::from_secs(60);
 
 /// A loaded plugin instance.
 struct PluginInstance {
-    // This is a Box<Box<dyn NotosPlugin>>
-    raw_wrapper: *mut std::ffi::c_void,
-    destroyer: DestroyPluginFn,
+    backend: Backend,
+    /// Library file the plugin was loaded from.
+    source: PathBuf,
+    /// Modification time of `source` when it was loaded.
+    modified: Option<SystemTime>,
+    /// SHA-256 of the bytes that were loaded, trusted as is when trust mode
+    /// is turned on.
+    sha256: String,
+    /// The plugin's background jobs, handed over in `attach`.
+    jobs: notos_sdk::Jobs,
+}
+
+enum Backend {
+    /// A native library exporting `_create_plugin` and `_destroy_plugin`.
+    Native {
+        // This is a Box<Box<dyn NotosPlugin>>
+        raw_wrapper: *mut std::ffi::c_void,
+        destroyer: DestroyPluginFn,
+        /// The library's `_PLUGIN_ID`, if it exports one.
+        exported_id: Option<String>,
+        /// Kept open in developer mode, and until a plugin is accepted (see
+        /// `PluginManager::admit`); otherwise the handle is leaked.
+        library: Option<libloading::Library>,
+        /// Private copy the library was loaded from (see [`Staged`]), deleted
+        /// once the library is closed.
+        shadow: Option<PathBuf>,
+    },
+    /// A sandboxed WebAssembly component.
+    #[cfg(feature = "wasm-plugins")]
+    Wasm(Box<crate::wasm_plugin::WasmPlugin>),
 }
 
 impl PluginInstance {
     /// Access the plugin trait object safely.
     unsafe fn as_plugin_mut(&mut self) -> &mut dyn NotosPlugin {
-        let box_ptr = self.raw_wrapper as *mut Box<dyn NotosPlugin>;
-        &mut **box_ptr
+        match &mut self.backend {
+            Backend::Native { raw_wrapper, .. } => {
+                let box_ptr = *raw_wrapper as *mut Box<dyn NotosPlugin>;
+                &mut **box_ptr
+            }
+            #[cfg(feature = "wasm-plugins")]
+            Backend::Wasm(plugin) => plugin.as_mut(),
+        }
+    }
+
+    /// Hand the plugin its host services, with its settings from `store`.
+    fn attach(&mut self, ctx: &Context, store: &mut crate::settings::SettingsStore) {
+        self.jobs = notos_sdk::Jobs::new(Some(ctx.clone()));
+        let jobs = self.jobs.clone();
+        unsafe {
+            let plugin = self.as_plugin_mut();
+            let settings = store.handle(plugin.id());
+            plugin.attach(HostServices { settings, jobs });
+        }
     }
 }
 
 impl Drop for PluginInstance {
+    // Without the WebAssembly backend, `Native` is the only variant
+    #[allow(irrefutable_let_patterns)]
     fn drop(&mut self) {
         log::debug!("Destroying plugin instance");
-        unsafe {
-            // Memory allocated in DLL must be freed in DLL
-            (self.destroyer)(self.raw_wrapper);
+        // Job threads run plugin code too
+        self.jobs.shutdown();
+        if let Backend::Native {
+            raw_wrapper,
+            destroyer,
+            library,
+            shadow,
+            ..
+        } = &mut self.backend
+        {
+            unsafe {
+                // Memory allocated in DLL must be freed in DLL
+                destroyer(*raw_wrapper);
+            }
+            // Only now that the plugin is gone may its code be unmapped
+            if let Some(library) = library.take() {
+                if let Err(e) = library.close() {
+                    log::warn!("Failed to close plugin library {:?}: {}", self.source, e);
+                }
+            }
+            if let Some(shadow) = shadow.take() {
+                let _ = fs::remove_file(shadow);
+            }
         }
     }
 }
 
+/// A plugin file about to be loaded. It is read once into a private copy,
+/// which is what gets hashed and loaded: the build can overwrite the original
+/// meanwhile, and a file swapped after the trust check is not the one that
+/// runs.
+struct Staged {
+    source: PathBuf,
+    /// Modification time of `source` when it was read.
+    modified: Option<SystemTime>,
+    /// SHA-256 of the copy.
+    sha256: String,
+    /// The private copy, deleted on drop unless a library took it over.
+    shadow: Option<PathBuf>,
+}
+
+impl Staged {
+    fn load_path(&self) -> &Path {
+        self.shadow.as_deref().unwrap_or(&self.source)
+    }
+}
+
+impl Drop for Staged {
+    fn drop(&mut self) {
+        if let Some(shadow) = self.shadow.take() {
+            let _ = fs::remove_file(shadow);
+        }
+    }
+}
+
+/// Where the private copies of plugin files go.
+fn shadow_dir() -> PathBuf {
+    crate::utils::config_dir().join("plugin_shadow")
+}
+
+/// Remove the private copies that crashed or leaked instances left behind.
+/// Copies still loaded cannot be removed on Windows, and are unaffected
+/// elsewhere.
+fn remove_stale_shadows() {
+    let Ok(entries) = fs::read_dir(shadow_dir()) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let stale = entry
+            .metadata()
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|m| m.elapsed().ok())
+            .is_some_and(|age| age >= STALE_SHADOW_AGE);
+        if stale {
+            let _ = fs::remove_file(entry.path());
+        }
+    }
+}
+
+/// Per-user plugin directory, `plugins` in the config directory.
+pub fn user_plugin_dir() -> PathBuf {
+    crate::utils::config_dir().join("plugins")
+}
+
+/// Whether `path` has the extension of a native library or (with the
+/// `wasm-plugins` feature) a WebAssembly component.
+fn is_plugin_file(path: &Path) -> bool {
+    let Some(ext) = path.extension() else {
+        return false;
+    };
+    let ext = ext.to_string_lossy().to_lowercase();
+    matches!(ext.as_str(), "dll" | "so" | "dylib")
+        || (cfg!(feature = "wasm-plugins") && ext == "wasm")
+}
+
 /// Manages the lifecycle of plugins.
 pub struct PluginManager {
     plugins: Vec<PluginInstance>,
+    /// Plugin files and directories from `--plugin`, searched first.
+    cli_paths: Vec<PathBuf>,
+    /// Plugin files not loaded because a plugin with the same id took precedence,
+    /// with their modification time.
+    shadowed: HashMap<PathBuf, Option<SystemTime>>,
+    trust: TrustStore,
+    /// Plugin files waiting for the user to trust or reject them.
+    pending: Vec<UntrustedPlugin>,
+    /// Plugin files the user rejected; listed, never loaded.
+    rejected: Vec<UntrustedPlugin>,
+    /// Developer mode: keep libraries closable and reload them when they change.
+    dev_mode: bool,
+    last_dev_poll: Instant,
+    /// Libraries that failed to load, with the modification time that failed.
+    dev_failed: HashMap<PathBuf, Option<SystemTime>>,
+    shadow_seq: u64,
+    /// WebAssembly engine, created when the first `.wasm` plugin is found.
+    #[cfg(feature = "wasm-plugins")]
+    wasm: Option<crate::wasm_plugin::WasmRuntime>,
 }
 
 impl PluginManager {
-    pub fn new() -> Self {
+    /// `cli_paths` are the plugin files and directories given with `--plugin`.
+    pub fn new(cli_paths: Vec<PathBuf>) -> Self {
+        let dev_mode = std::env::var_os(DEV_MODE_ENV).is_some();
+        if dev_mode {
+            log::info!("Plugin developer mode enabled: plugins reload when rebuilt");
+        }
+        remove_stale_shadows();
         Self {
             plugins: Vec::new(),
+            cli_paths,
+            shadowed: HashMap::new(),
+            trust: TrustStore::load(),
+            pending: Vec::new(),
+            rejected: Vec::new(),
+            dev_mode,
+            last_dev_poll: Instant::now(),
+            dev_failed: HashMap::new(),
+            shadow_seq: 0,
+            #[cfg(feature = "wasm-plugins")]
+            wasm: None,
         }
     }
 
-    /// Load all plugins from the executable directory and the "plugins" subdirectory.
+    pub fn dev_mode(&self) -> bool {
+        self.dev_mode
+    }
+
+    /// Load all plugins (native libraries and WebAssembly components) from the
+    /// search paths. When several files provide the same plugin id, the one
+    /// found first wins. In trust mode, files not on the allowlist are held
+    /// back (see [`Self::pending_trust`]).
     pub fn load_plugins(&mut self) {
         log::info!("Scanning for plugins...");
 
-        let mut loaded_filenames = HashSet::new();
+        for path in self.library_files() {
+            let Some(staged) = self.stage(&path) else {
+                continue;
+            };
+            if !self.check_trust(&staged) {
+                continue;
+            }
+            let Some(instance) = (unsafe { self.load_plugin(staged) }) else {
+                continue;
+            };
+            if let Some(instance) = self.admit(instance) {
+                self.plugins.push(instance);
+            }
+        }
 
-        // Find relevant directories
+        log::info!("Loaded {} plugins.", self.plugins.len());
+    }
+
+    /// Where plugins are looked for, highest precedence first: `--plugin`
+    /// arguments, `NOTOS_PLUGIN_PATH`, the user's plugin directory, then the
+    /// executable directory and its "plugins" subdirectory.
+    pub fn search_paths(&self) -> Vec<PathBuf> {
+        let mut paths = self.cli_paths.clone();
+        if let Some(env) = std::env::var_os(PLUGIN_PATH_ENV) {
+            paths.extend(std::env::split_paths(&env).filter(|p| !p.as_os_str().is_empty()));
+        }
+        paths.push(user_plugin_dir());
         let exe_path = std::env::current_exe().unwrap_or_default();
-        let exe_dir = exe_path
-            .parent()
-            .unwrap_or_else(|| std::path::Path::new("."));
-        let plugins_dir = exe_dir.join("plugins");
+        let exe_dir = exe_path.parent().unwrap_or_else(|| Path::new("."));
+        paths.push(exe_dir.to_path_buf());
+        paths.push(exe_dir.join("plugins"));
+        paths
+    }
 
-        // List of directories to scan
-        let scan_dirs = vec![exe_dir.to_path_buf(), plugins_dir];
+    /// Plugin files found in the search paths, in precedence order. A search
+    /// path may be a directory or a single plugin file.
+    fn library_files(&self) -> Vec<PathBuf> {
+        let exe_path = std::env::current_exe().unwrap_or_default();
+        let mut seen = HashSet::new();
+        let mut files = Vec::new();
 
-        for dir in scan_dirs {
-            if !dir.exists() {
+        for search_path in self.search_paths() {
+            let candidates = if search_path.is_dir() {
+                let Ok(entries) = fs::read_dir(&search_path) else {
+                    continue;
+                };
+                let mut entries: Vec<PathBuf> = entries.flatten().map(|e| e.path()).collect();
+                entries.sort();
+                entries
+            } else if search_path.is_file() {
+                vec![search_path]
+            } else {
                 continue;
+            };
+
+            for path in candidates {
+                // Skip the executable itself if it happens to have a library extension (unlikely but safe)
+                if path == exe_path || !is_plugin_file(&path) {
+                    continue;
+                }
+                // The same file can be reached through several search paths
+                let canonical = fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+                if seen.insert(canonical) {
+                    files.push(path);
+                }
             }
+        }
+        files
+    }
 
-            if let Ok(entries) = fs::read_dir(dir) {
-                for entry in entries.flatten() {
-                    let path = entry.path();
+    /// Read `path` into a private copy, see [`Staged`].
+    fn stage(&mut self, path: &Path) -> Option<Staged> {
+        let modified = fs::metadata(path).and_then(|m| m.modified()).ok();
+        let bytes = match fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                log::error!("Failed to read plugin {:?}: {}", path, e);
+                return None;
+            }
+        };
+        let shadow = match self.shadow_copy(path, &bytes) {
+            Ok(shadow) => shadow,
+            Err(e) => {
+                log::error!("Failed to copy plugin {:?}: {}", path, e);
+                return None;
+            }
+        };
+        Some(Staged {
+            source: path.to_path_buf(),
+            modified,
+            sha256: crate::trust::sha256(&bytes),
+            shadow: Some(shadow),
+        })
+    }
 
-                    // Skip the executable itself if it happens to have a library extension (unlikely but safe)
-                    if path == exe_path {
+    /// Whether `staged` may be loaded. In trust mode, a file not on the
+    /// allowlist is queued for the user's decision, or listed as rejected, and
+    /// must not be opened.
+    fn check_trust(&mut self, staged: &Staged) -> bool {
+        if !self.trust.enabled {
+            return true;
+        }
+        let path = staged.source.as_path();
+        let sha256 = staged.sha256.clone();
+        let verdict = self.trust.verdict(path, &sha256);
+        if verdict == Verdict::Trusted {
+            return true;
+        }
+        let entry = UntrustedPlugin {
+            path: path.to_path_buf(),
+            sha256,
+            modified: staged.modified,
+        };
+        self.pending.retain(|p| p.path != path);
+        self.rejected.retain(|p| p.path != path);
+        if verdict == Verdict::Rejected {
+            log::warn!("Not loading rejected plugin {:?}", path);
+            self.rejected.push(entry);
+        } else {
+            log::warn!(
+                "Plugin {:?} is not trusted (SHA-256 {}), asking the user",
+                path,
+                entry.sha256
+            );
+            self.pending.push(entry);
+        }
+        false
+    }
+
+    pub fn trust_enabled(&self) -> bool {
+        self.trust.enabled
+    }
+
+    /// Turn trust mode on or off. Plugins that are already loaded are added to
+    /// the allowlist when it is turned on, with the hash of the bytes that were
+    /// loaded: a file replaced since then waits for the user's decision.
+    pub fn set_trust_enabled(&mut self, enabled: bool) {
+        self.trust.enabled = enabled;
+        if enabled {
+            for plugin in &self.plugins {
+                let source = plugin.source.as_path();
+                let current = crate::trust::file_sha256(source);
+                if current.as_ref().is_ok_and(|hash| *hash == plugin.sha256) {
+                    self.trust.trust(source, &plugin.sha256);
+                    continue;
+                }
+                let sha256 = match current {
+                    Ok(hash) => hash,
+                    Err(e) => {
+                        log::error!("Failed to hash plugin {:?}: {}", source, e);
                         continue;
                     }
+                };
+                log::warn!(
+                    "Plugin {:?} changed since it was loaded (SHA-256 {}), asking the user",
+                    source,
+                    sha256
+                );
+                self.pending.retain(|p| p.path != source);
+                self.pending.push(UntrustedPlugin {
+                    path: source.to_path_buf(),
+                    sha256,
+                    modified: fs::metadata(source).and_then(|m| m.modified()).ok(),
+                });
+            }
+        } else {
+            self.pending.clear();
+        }
+        self.save_trust();
+    }
 
-                    if let Some(ext) = path.extension() {
-                        let ext_str = ext.to_string_lossy().to_lowercase();
-                        if ext_str == "dll" || ext_str == "so" || ext_str == "dylib" {
-                            if let Some(filename) = path.file_name() {
-                                let filename_str = filename.to_string_lossy().to_string();
-                                // Avoid loading the same plugin filename twice if it exists in both dirs
-                                if loaded_filenames.insert(filename_str) {
-                                    unsafe {
-                                        self.load_plugin_from_file(&path);
-                                    }
-                                }
-                            }
-                        }
-                    }
+    /// The next plugin file waiting for a "trust this plugin?" answer.
+    pub fn pending_trust(&self) -> Option<&UntrustedPlugin> {
+        self.pending.first()
+    }
+
+    /// Plugin files the user rejected. They are never loaded.
+    pub fn rejected_plugins(&self) -> &[UntrustedPlugin] {
+        &self.rejected
+    }
+
+    /// Apply the user's decision about an untrusted plugin file. A trusted
+    /// file is loaded right away; returns `true` when that added a plugin, so
+    /// the caller can refresh commands, panels and settings.
+    pub fn resolve_trust(
+        &mut self,
+        path: &Path,
+        decision: TrustDecision,
+        ctx: &Context,
+        store: &mut crate::settings::SettingsStore,
+    ) -> bool {
+        let position = |list: &[UntrustedPlugin]| list.iter().position(|p| p.path == path);
+        let entry = if let Some(i) = position(&self.pending) {
+            self.pending.remove(i)
+        } else if let Some(i) = position(&self.rejected) {
+            self.rejected.remove(i)
+        } else {
+            return false;
+        };
+
+        match decision {
+            TrustDecision::Later => return false,
+            TrustDecision::Reject => {
+                self.trust.reject(path, &entry.sha256);
+                self.rejected.push(entry);
+                self.save_trust();
+                return false;
+            }
+            TrustDecision::Trust => {
+                self.trust.trust(path, &entry.sha256);
+                self.save_trust();
+            }
+        }
+
+        // The file may have changed since it was hashed
+        let Some(staged) = self.stage(path) else {
+            return false;
+        };
+        if !self.check_trust(&staged) {
+            return false;
+        }
+        let Some(instance) = (unsafe { self.load_plugin(staged) }) else {
+            return false;
+        };
+        let Some(instance) = self.admit(instance) else {
+            return false;
+        };
+        self.install(instance, ctx, store);
+        ctx.request_repaint();
+        true
+    }
+
+    fn save_trust(&self) {
+        if let Err(e) = self.trust.save() {
+            log::error!("Failed to save plugin trust list: {}", e);
+        }
+    }
+
+    /// Whether a plugin loaded from another file already has id `id`, in which
+    /// case `path` is skipped and remembered as shadowed. The plugin loaded
+    /// from `path` itself is an older build, replaced in [`Self::install`].
+    fn is_shadowed(&mut self, id: &str, path: &Path, modified: Option<SystemTime>) -> bool {
+        let winner = self.plugins.iter_mut().find_map(|p| {
+            (p.source != path && unsafe { p.as_plugin_mut().id() } == id).then(|| p.source.clone())
+        });
+        let Some(winner) = winner else {
+            return false;
+        };
+        log::info!(
+            "Skipping plugin {:?} from {:?}: already loaded from {:?}",
+            id,
+            path,
+            winner
+        );
+        self.shadowed.insert(path.to_path_buf(), modified);
+        true
+    }
+
+    /// Accept a freshly loaded plugin unless a loaded plugin already has its id.
+    /// A rejected plugin is destroyed and its library closed. Native libraries
+    /// exporting `_PLUGIN_ID` are already checked before the plugin is created;
+    /// this catches older libraries without it, and WebAssembly plugins, whose
+    /// id is only known once running in the sandbox.
+    // Without the WebAssembly backend, `Native` is the only variant
+    #[allow(irrefutable_let_patterns)]
+    fn admit(&mut self, mut instance: PluginInstance) -> Option<PluginInstance> {
+        let id = unsafe { instance.as_plugin_mut().id().to_string() };
+        if crate::settings::is_reserved_id(&id) {
+            log::error!(
+                "Not loading plugin {:?}: id {:?} is reserved",
+                instance.source,
+                id
+            );
+            self.dev_failed
+                .insert(instance.source.clone(), instance.modified);
+            return None;
+        }
+        if let Backend::Native {
+            exported_id: Some(exported_id),
+            ..
+        } = &instance.backend
+        {
+            if *exported_id != id {
+                log::warn!(
+                    "Plugin {:?} exports _PLUGIN_ID {:?} but reports id {:?}",
+                    instance.source,
+                    exported_id,
+                    id
+                );
+            }
+        }
+        if self.is_shadowed(&id, &instance.source, instance.modified) {
+            return None;
+        }
+        self.shadowed.remove(&instance.source);
+
+        if let Backend::Native {
+            library, shadow, ..
+        } = &mut instance.backend
+        {
+            if !self.dev_mode {
+                // LEAK the library handle.
+                std::mem::forget(library.take());
+                // Fails on Windows while loaded; cleaned up on a later start then
+                if let Some(shadow) = shadow.take() {
+                    let _ = fs::remove_file(shadow);
                 }
+                log::info!("Plugin successfully loaded and locked in memory.");
             }
         }
+        Some(instance)
+    }
 
-        log::info!("Loaded {} plugins.", self.plugins.len());
+    /// Start an admitted plugin. An instance loaded from the same file is
+    /// unloaded first, and hands its state over (see `NotosPlugin::save_state`).
+    fn install(
+        &mut self,
+        mut instance: PluginInstance,
+        ctx: &Context,
+        store: &mut crate::settings::SettingsStore,
+    ) {
+        let existing = self
+            .plugins
+            .iter()
+            .position(|p| p.source == instance.source);
+        let mut state = None;
+        if let Some(i) = existing {
+            unsafe {
+                let plugin = self.plugins[i].as_plugin_mut();
+                state = plugin.save_state();
+                plugin.on_unload();
+            }
+            // Destroy the old instance and close its library before the new one starts
+            self.plugins.remove(i);
+        }
+        instance.attach(ctx, store);
+        unsafe {
+            let plugin = instance.as_plugin_mut();
+            plugin.on_load(ctx);
+            if let Some(state) = &state {
+                plugin.restore_state(state);
+            }
+        }
+        match existing {
+            Some(i) => self.plugins.insert(i, instance),
+            None => self.plugins.push(instance),
+        }
+    }
+
+    unsafe fn load_plugin(&mut self, staged: Staged) -> Option<PluginInstance> {
+        #[cfg(feature = "wasm-plugins")]
+        if staged
+            .source
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("wasm"))
+        {
+            return self.load_wasm_plugin(staged);
+        }
+        self.load_plugin_from_file(staged)
+    }
+
+    #[cfg(feature = "wasm-plugins")]
+    fn load_wasm_plugin(&mut self, staged: Staged) -> Option<PluginInstance> {
+        let path = &staged.source;
+        log::info!("Loading WebAssembly plugin: {:?}", path);
+        if self.wasm.is_none() {
+            match crate::wasm_plugin::WasmRuntime::new() {
+                Ok(runtime) => self.wasm = Some(runtime),
+                Err(e) => {
+                    log::error!("Failed to start the WebAssembly runtime: {}", e);
+                    return None;
+                }
+            }
+        }
+        // The component is compiled into memory, so the copy can go right away
+        match self.wasm.as_ref()?.load(staged.load_path()) {
+            Ok(plugin) => Some(PluginInstance {
+                backend: Backend::Wasm(Box::new(plugin)),
+                source: path.clone(),
+                modified: staged.modified,
+                sha256: staged.sha256.clone(),
+                jobs: notos_sdk::Jobs::default(),
+            }),
+            Err(e) => {
+                log::error!("Failed to load WebAssembly plugin {:?}: {}", path, e);
+                None
+            }
+        }
     }
 
-    unsafe fn load_plugin_from_file(&mut self, path: &PathBuf) {
+    // `staged` is dropped last, so its copy is only deleted once `lib` is closed
+    unsafe fn load_plugin_from_file(&mut self, mut staged: Staged) -> Option<PluginInstance> {
+        let path = staged.source.clone();
         log::info!("Loading plugin DLL: {:?}", path);
 
-        match libloading::Library::new(path) {
+        match libloading::Library::new(staged.load_path()) {
             Ok(lib) => {
-                let create_func: libloading::Symbol<CreatePluginFn> =
-                    match lib.get(b"_create_plugin") {
-                        Ok(f) => f,
-                        Err(e) => {
-                            log::warn!("Missing _create_plugin in {:?}: {}", path, e);
-                            return;
-                        }
-                    };
-
-                let destroy_func: libloading::Symbol<DestroyPluginFn> =
-                    match lib.get(b"_destroy_plugin") {
-                        Ok(f) => f,
-                        Err(e) => {
-                            log::warn!("Missing _destroy_plugin in {:?}: {}", path, e);
-                            return;
-                        }
-                    };
-
-                let destroyer = *destroy_func;
+                let symbols = lib
+                    .get::<CreatePluginFn>(b"_create_plugin")
+                    .map(|create| *create)
+                    .map_err(|e| ("_create_plugin", e))
+                    .and_then(|create| {
+                        lib.get::<DestroyPluginFn>(b"_destroy_plugin")
+                            .map(|destroy| (create, *destroy))
+                            .map_err(|e| ("_destroy_plugin", e))
+                    });
+                let (create_func, destroyer) = match symbols {
+                    Ok(symbols) => symbols,
+                    Err((symbol, e)) => {
+                        log::warn!("Missing {} in {:?}: {}", symbol, path, e);
+                        return None;
+                    }
+                };
+                // Optional: libraries built without it are checked in `admit`
+                let exported_id = lib
+                    .get::<*const PluginId>(PLUGIN_ID_SYMBOL)
+                    .ok()
+                    .map(|id| (**id).as_c_str().to_string_lossy().into_owned());
+                // A library overridden by one found earlier runs none of its code
+                if let Some(id) = &exported_id {
+                    if self.is_shadowed(id, &path, staged.modified) {
+                        return None;
+                    }
+                }
                 let raw_wrapper = create_func();
 
-                self.plugins.push(PluginInstance {
-                    raw_wrapper,
-                    destroyer,
-                });
-
-                // LEAK the library handle.
-                std::mem::forget(lib);
+                if self.dev_mode {
+                    log::info!("Plugin loaded in developer mode (reloadable).");
+                }
 
-                log::info!("Plugin successfully loaded and locked in memory.");
+                Some(PluginInstance {
+                    backend: Backend::Native {
+                        raw_wrapper,
+                        destroyer,
+                        exported_id,
+                        // Closed again if the plugin is rejected as a duplicate
+                        library: Some(lib),
+                        shadow: staged.shadow.take(),
+                    },
+                    source: path,
+                    modified: staged.modified,
+                    sha256: staged.sha256.clone(),
+                    jobs: notos_sdk::Jobs::default(),
+                })
             }
             Err(e) => {
                 log::error!("Failed to load library {:?}: {}", path, e);
+                None
+            }
+        }
+    }
+
+    /// Write `bytes`, the contents of plugin file `path`, to a new private copy.
+    fn shadow_copy(&mut self, path: &Path, bytes: &[u8]) -> std::io::Result<PathBuf> {
+        let dir = shadow_dir();
+        fs::create_dir_all(&dir)?;
+        self.shadow_seq += 1;
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        let ext = path.extension().unwrap_or_default().to_string_lossy();
+        let shadow = dir.join(format!(
+            "{}-{}-{}.{}",
+            stem,
+            std::process::id(),
+            self.shadow_seq,
+            ext
+        ));
+        fs::write(&shadow, bytes)?;
+        Ok(shadow)
+    }
+
+    /// Developer mode: reload plugins whose library was rebuilt, load new ones and
+    /// unload removed ones. Returns `true` when the set of plugins changed, so the
+    /// caller can refresh commands, panels and settings.
+    pub fn poll_dev_reload(
+        &mut self,
+        ctx: &Context,
+        store: &mut crate::settings::SettingsStore,
+    ) -> bool {
+        if !self.dev_mode {
+            return false;
+        }
+        // Keep watching while the window is idle
+        let elapsed = self.last_dev_poll.elapsed();
+        if elapsed < DEV_POLL_INTERVAL {
+            ctx.request_repaint_after(DEV_POLL_INTERVAL - elapsed);
+            return false;
+        }
+        ctx.request_repaint_after(DEV_POLL_INTERVAL);
+        self.last_dev_poll = Instant::now();
+
+        let files = self.library_files();
+        let mut changed = false;
+
+        let mut i = 0;
+        while i < self.plugins.len() {
+            if files.contains(&self.plugins[i].source) {
+                i += 1;
+                continue;
+            }
+            log::info!(
+                "Plugin library {:?} removed, unloading",
+                self.plugins[i].source
+            );
+            unsafe { self.plugins[i].as_plugin_mut().on_unload() };
+            self.plugins.remove(i);
+            // A file it shadowed may provide the plugin now
+            self.shadowed.clear();
+            changed = true;
+        }
+
+        for path in files {
+            let modified = fs::metadata(&path).and_then(|m| m.modified()).ok();
+            let settled = modified
+                .and_then(|m| m.elapsed().ok())
+                .is_some_and(|age| age >= DEV_SETTLE_TIME);
+            if !settled
+                || self.dev_failed.get(&path) == Some(&modified)
+                || self.shadowed.get(&path) == Some(&modified)
+                || self
+                    .pending
+                    .iter()
+                    .chain(&self.rejected)
+                    .any(|p| p.path == path && p.modified == modified)
+            {
+                continue;
+            }
+
+            let existing = self.plugins.iter().find(|p| p.source == path);
+            if existing.is_some_and(|p| p.modified == modified) {
+                continue;
+            }
+            if existing.is_some() {
+                log::info!("Plugin library {:?} changed, reloading", path);
+            }
+
+            // The old build keeps running until the new one is loaded and
+            // admitted, loaded side by side from its own private copy
+            let loaded = match self.stage(&path) {
+                // A changed build waits for approval like a new plugin
+                Some(staged) if !self.check_trust(&staged) => continue,
+                Some(staged) => unsafe { self.load_plugin(staged) },
+                None => None,
+            };
+            if loaded.is_some() {
+                self.dev_failed.remove(&path);
+            }
+            match loaded.map(|instance| self.admit(instance)) {
+                Some(Some(instance)) => {
+                    self.install(instance, ctx, store);
+                    changed = true;
+                }
+                // Shadowed by a plugin with the same id
+                Some(None) => {}
+                None if self.shadowed.get(&path) == Some(&modified) => {}
+                None => {
+                    self.dev_failed.insert(path, modified);
+                }
+            }
+        }
+
+        if changed {
+            ctx.request_repaint();
+        }
+        changed
+    }
+
+    /// Hand each plugin its host services, including its settings from `store`.
+    pub fn attach(&mut self, ctx: &Context, store: &mut crate::settings::SettingsStore) {
+        for p in &mut self.plugins {
+            p.attach(ctx, store);
+        }
+    }
+
+    /// Jobs of all plugins that are still running.
+    pub fn running_jobs(&self) -> Vec<JobStatus> {
+        self.plugins.iter().flat_map(|p| p.jobs.running()).collect()
+    }
+
+    pub fn cancel_job(&self, id: JobId) {
+        for p in &self.plugins {
+            if p.jobs.cancel(id) {
+                return;
+            }
+        }
+    }
+
+    /// Actions returned by jobs that finished since the last call.
+    pub fn take_job_results(&mut self) -> Vec<PluginAction> {
+        let mut actions = Vec::new();
+        for p in &mut self.plugins {
+            let finished = p.jobs.take_finished();
+            if finished.is_empty() {
+                continue;
             }
+            let id = unsafe { p.as_plugin_mut().id().to_string() };
+            actions.extend(finished.into_iter().map(|a| owned_by(a, &id)));
         }
+        actions
     }
 
     pub fn on_load(&mut self, ctx: &Context) {
@@ -154,9 +866,10 @@ impl PluginManager {
         let mut result = PluginAction::None;
         for p in &mut self.plugins {
             unsafe {
-                let action = p.as_plugin_mut().ui(ctx, ed);
+                let plugin = p.as_plugin_mut();
+                let action = plugin.ui(ctx, ed);
                 if action != PluginAction::None {
-                    result = action;
+                    result = owned_by(action, plugin.id());
                 }
             }
         }
@@ -167,9 +880,10 @@ impl PluginManager {
         let mut result = PluginAction::None;
         for p in &mut self.plugins {
             unsafe {
-                let action = p.as_plugin_mut().menu_ui(ui, ed);
+                let plugin = p.as_plugin_mut();
+                let action = plugin.menu_ui(ui, ed);
                 if action != PluginAction::None {
-                    result = action;
+                    result = owned_by(action, plugin.id());
                 }
             }
         }
@@ -180,9 +894,10 @@ impl PluginManager {
         let mut result = PluginAction::None;
         for p in &mut self.plugins {
             unsafe {
-                let action = p.as_plugin_mut().plugins_menu_ui(ui, ed);
+                let plugin = p.as_plugin_mut();
+                let action = plugin.plugins_menu_ui(ui, ed);
                 if action != PluginAction::None {
-                    result = action;
+                    result = owned_by(action, plugin.id());
                 }
             }
         }
@@ -193,12 +908,161 @@ impl PluginManager {
         let mut result = PluginAction::None;
         for p in &mut self.plugins {
             unsafe {
-                let action = p.as_plugin_mut().context_menu_ui(ui, ed);
+                let plugin = p.as_plugin_mut();
+                let action = plugin.context_menu_ui(ui, ed);
                 if action != PluginAction::None {
-                    result = action;
+                    result = owned_by(action, plugin.id());
                 }
             }
         }
         result
     }
+
+    /// Commands contributed by each plugin as `(plugin id, plugin name, commands)`.
+    pub fn commands(&mut self) -> Vec<(String, String, Vec<PluginCommand>)> {
+        self.plugins
+            .iter_mut()
+            .map(|p| unsafe {
+                let plugin = p.as_plugin_mut();
+                (
+                    plugin.id().to_string(),
+                    plugin.name().to_string(),
+                    plugin.commands(),
+                )
+            })
+            .collect()
+    }
+
+    /// Settings schemas as `(plugin id, plugin name, settings)`, skipping plugins without any.
+    pub fn settings_schemas(&mut self) -> Vec<(String, String, Vec<SettingSpec>)> {
+        self.plugins
+            .iter_mut()
+            .map(|p| unsafe {
+                let plugin = p.as_plugin_mut();
+                (
+                    plugin.id().to_string(),
+                    plugin.name().to_string(),
+                    plugin.settings_schema(),
+                )
+            })
+            .filter(|(_, _, specs)| !specs.is_empty())
+            .collect()
+    }
+
+    /// Run a command on the plugin with the given id.
+    pub fn run_command(
+        &mut self,
+        plugin_id: &str,
+        command_id: &str,
+        ed: &EditorContext,
+    ) -> PluginAction {
+        for p in &mut self.plugins {
+            unsafe {
+                let plugin = p.as_plugin_mut();
+                if plugin.id() == plugin_id {
+                    return owned_by(plugin.run_command(command_id, ed), plugin_id);
+                }
+            }
+        }
+        log::warn!("No plugin with id {:?} to run {:?}", plugin_id, command_id);
+        PluginAction::None
+    }
+
+    /// Panels contributed by each plugin as `(plugin id, panels)`.
+    pub fn panels(&mut self) -> Vec<(String, Vec<PluginPanel>)> {
+        self.plugins
+            .iter_mut()
+            .map(|p| unsafe {
+                let plugin = p.as_plugin_mut();
+                (plugin.id().to_string(), plugin.panels())
+            })
+            .collect()
+    }
+
+    /// Draw a panel owned by the plugin with the given id.
+    pub fn panel_ui(
+        &mut self,
+        plugin_id: &str,
+        panel_id: &str,
+        ui: &mut egui::Ui,
+        ed: &EditorContext,
+    ) -> PluginAction {
+        for p in &mut self.plugins {
+            unsafe {
+                let plugin = p.as_plugin_mut();
+                if plugin.id() == plugin_id {
+                    return owned_by(plugin.panel_ui(panel_id, ui, ed), plugin_id);
+                }
+            }
+        }
+        PluginAction::None
+    }
+
+    /// Status bar items of every plugin as `(plugin id, item)`.
+    pub fn status_bar_items(&mut self, ed: &EditorContext) -> Vec<(String, StatusBarItem)> {
+        let mut items = Vec::new();
+        for p in &mut self.plugins {
+            unsafe {
+                let plugin = p.as_plugin_mut();
+                let id = plugin.id().to_string();
+                items.extend(
+                    plugin
+                        .status_bar_items(ed)
+                        .into_iter()
+                        .map(|item| (id.clone(), item)),
+                );
+            }
+        }
+        items
+    }
+
+    pub fn on_status_bar_click(
+        &mut self,
+        plugin_id: &str,
+        item_id: &str,
+        ed: &EditorContext,
+    ) -> PluginAction {
+        for p in &mut self.plugins {
+            unsafe {
+                let plugin = p.as_plugin_mut();
+                if plugin.id() == plugin_id {
+                    return owned_by(plugin.on_status_bar_click(item_id, ed), plugin_id);
+                }
+            }
+        }
+        PluginAction::None
+    }
+
+    pub fn status_bar_popup_ui(
+        &mut self,
+        plugin_id: &str,
+        item_id: &str,
+        ui: &mut egui::Ui,
+        ed: &EditorContext,
+    ) -> PluginAction {
+        for p in &mut self.plugins {
+            unsafe {
+                let plugin = p.as_plugin_mut();
+                if plugin.id() == plugin_id {
+                    return owned_by(plugin.status_bar_popup_ui(item_id, ui, ed), plugin_id);
+                }
+            }
+        }
+        PluginAction::None
+    }
+}
+
+/// `action` as it leaves the plugin `plugin_id`. A plugin names its panels by
+/// its own ids, which other plugins may use too, so `ShowPanel` is given the
+/// host's key for the panel, `<plugin id>.<panel id>`.
+fn owned_by(action: PluginAction, plugin_id: &str) -> PluginAction {
+    match action {
+        PluginAction::ShowPanel(panel) => {
+            PluginAction::ShowPanel(format!("{}.{}", plugin_id, panel))
+        }
+        PluginAction::InTab(tab, action) => {
+            PluginAction::InTab(tab, Box::new(owned_by(*action, plugin_id)))
+        }
+        action => action,
+    }
 }
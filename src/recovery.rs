@@ -0,0 +1,566 @@
+//! Recovery of unsaved edits after a crash.
+//!
+//! Every running instance keeps a directory under `recovery/` in the config
+//! directory, holding a lock file that stays locked while it runs and a
+//! journal per tab with unsaved edits. A journal starts with the tab's text
+//! and then only appends what changed, so keeping it current is cheap even
+//! for large documents: only the changed part is copied and handed to a
+//! background thread, which does the writing. A clean exit
+//! removes the directory, so one that is left behind and no longer locked
+//! belongs to an instance that crashed, and its journals hold what it had
+//! not saved.
+
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::editor::{EditorTab, TabId};
+
+/// How often tabs are checked for edits to journal.
+const INTERVAL: Duration = Duration::from_millis(500);
+
+/// Journals are rewritten from the current text once they have grown by this
+/// much, or by twice the text if that is more.
+const COMPACT_AT: u64 = 256 * 1024;
+
+const LOCK_FILE: &str = "lock";
+const JOURNAL_EXTENSION: &str = "journal";
+
+/// A line of a journal.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum Record {
+    /// The tab as a whole; always the first line.
+    Start {
+        tab: TabId,
+        title: String,
+        path: Option<PathBuf>,
+        text: String,
+    },
+    /// Replace `removed` bytes at byte `at` with `text`.
+    Splice {
+        at: usize,
+        removed: usize,
+        text: String,
+    },
+}
+
+enum Message {
+    /// Start the journal of a tab over.
+    Start {
+        tab: TabId,
+        title: String,
+        path: Option<PathBuf>,
+        text: String,
+    },
+    /// Replace `removed` bytes at byte `at` of the tab's text with `text`.
+    Splice {
+        tab: TabId,
+        at: usize,
+        removed: usize,
+        text: String,
+    },
+    Forget(TabId),
+}
+
+/// A tab as last sent to the writer.
+struct Sent {
+    title: String,
+    path: Option<PathBuf>,
+    text: String,
+}
+
+/// The journals of this instance.
+pub struct Journal {
+    dir: PathBuf,
+    /// Held locked until the directory is removed.
+    _lock: File,
+    tx: Option<Sender<Message>>,
+    thread: Option<JoinHandle<()>>,
+    /// What was last sent for each journaled tab.
+    sent: HashMap<TabId, Sent>,
+    last_check: Instant,
+}
+
+impl Journal {
+    /// Create this instance's recovery directory and start the writer.
+    pub fn start() -> io::Result<Self> {
+        let stamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or_default();
+        Self::start_in(recovery_dir().join(format!("{}-{}", std::process::id(), stamp)))
+    }
+
+    fn start_in(dir: PathBuf) -> io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+        let lock = File::create(dir.join(LOCK_FILE))?;
+        lock.try_lock().map_err(io::Error::from)?;
+
+        let (tx, rx) = mpsc::channel();
+        let writer_dir = dir.clone();
+        let thread = std::thread::spawn(move || write_journals(&writer_dir, rx));
+        Ok(Self {
+            dir,
+            _lock: lock,
+            tx: Some(tx),
+            thread: Some(thread),
+            sent: HashMap::new(),
+            last_check: Instant::now(),
+        })
+    }
+
+    /// Journal the edits made to `tabs` since the last call, at most every
+    /// [`INTERVAL`], and forget tabs that were saved or closed. Returns when
+    /// to call again if edits may be waiting.
+    pub fn track(&mut self, tabs: &[EditorTab]) -> Option<Duration> {
+        // Large files only show a preview that cannot be saved anyway
+        let journaled = |tab: &&EditorTab| tab.is_dirty && !tab.large_file;
+        let elapsed = self.last_check.elapsed();
+        if elapsed < INTERVAL {
+            return tabs
+                .iter()
+                .any(|t| journaled(&t))
+                .then(|| INTERVAL - elapsed);
+        }
+        self.last_check = Instant::now();
+        let tx = self.tx.as_ref()?;
+
+        for tab in tabs.iter().filter(journaled) {
+            let message = match self.sent.get_mut(&tab.id) {
+                Some(sent) if sent.title == tab.title && sent.path == tab.path => {
+                    if sent.text == tab.content {
+                        continue;
+                    }
+                    // Only the changed part is copied
+                    let (at, removed, inserted) = splice(&sent.text, &tab.content);
+                    sent.text.replace_range(at..at + removed, inserted);
+                    Message::Splice {
+                        tab: tab.id,
+                        at,
+                        removed,
+                        text: inserted.to_string(),
+                    }
+                }
+                _ => {
+                    let sent = Sent {
+                        title: tab.title.clone(),
+                        path: tab.path.clone(),
+                        text: tab.content.clone(),
+                    };
+                    let message = Message::Start {
+                        tab: tab.id,
+                        title: sent.title.clone(),
+                        path: sent.path.clone(),
+                        text: sent.text.clone(),
+                    };
+                    self.sent.insert(tab.id, sent);
+                    message
+                }
+            };
+            let _ = tx.send(message);
+        }
+        self.sent.retain(|id, _| {
+            let keep = tabs.iter().filter(journaled).any(|t| t.id == *id);
+            if !keep {
+                let _ = tx.send(Message::Forget(*id));
+            }
+            keep
+        });
+        None
+    }
+
+    /// Finish writing and remove the journals, on a clean exit.
+    pub fn close(&mut self) {
+        self.tx = None;
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        if let Err(e) = fs::remove_dir_all(&self.dir) {
+            log::warn!("Failed to remove recovery journals: {}", e);
+        }
+    }
+}
+
+fn recovery_dir() -> PathBuf {
+    crate::utils::config_dir().join("recovery")
+}
+
+fn journal_path(dir: &Path, tab: TabId) -> PathBuf {
+    dir.join(format!("{}.{}", tab.0, JOURNAL_EXTENSION))
+}
+
+/// A journal being written.
+struct Open {
+    title: String,
+    path: Option<PathBuf>,
+    /// The text as journaled so far.
+    text: String,
+    /// Open for appending, or `None` until the journal is written.
+    file: Option<File>,
+    /// Bytes appended since the journal was last rewritten.
+    appended: u64,
+}
+
+fn write_journals(dir: &Path, rx: Receiver<Message>) {
+    let mut journals: HashMap<TabId, Open> = HashMap::new();
+    while let Ok(message) = rx.recv() {
+        let (tab, splice) = match message {
+            Message::Start {
+                tab,
+                title,
+                path,
+                text,
+            } => {
+                let open = Open {
+                    title,
+                    path,
+                    text,
+                    file: None,
+                    appended: 0,
+                };
+                journals.insert(tab, open);
+                (tab, None)
+            }
+            Message::Splice {
+                tab,
+                at,
+                removed,
+                text,
+            } => (tab, Some((at, removed, text))),
+            Message::Forget(tab) => {
+                if journals.remove(&tab).is_some() {
+                    let _ = fs::remove_file(journal_path(dir, tab));
+                }
+                continue;
+            }
+        };
+        let Some(open) = journals.get_mut(&tab) else {
+            continue;
+        };
+        if let Err(e) = write(dir, tab, open, splice) {
+            log::warn!("Failed to write recovery journal: {}", e);
+        }
+    }
+}
+
+/// Apply `splice` to the journaled text and append it, or start the journal
+/// over if it is new, was not written, or has grown too much.
+fn write(
+    dir: &Path,
+    tab: TabId,
+    open: &mut Open,
+    splice: Option<(usize, usize, String)>,
+) -> io::Result<()> {
+    let Some((at, removed, text)) = splice else {
+        return rewrite(dir, tab, open);
+    };
+    open.text.replace_range(at..at + removed, &text);
+    let compact = open.appended >= COMPACT_AT.max(2 * open.text.len() as u64);
+    let Some(file) = open.file.as_mut().filter(|_| !compact) else {
+        return rewrite(dir, tab, open);
+    };
+    let record = Record::Splice { at, removed, text };
+    let mut line = serde_json::to_vec(&record)?;
+    line.push(b'\n');
+    file.write_all(&line)?;
+    file.sync_data()?;
+    open.appended += line.len() as u64;
+    Ok(())
+}
+
+/// Start the journal of `tab` over with its current text.
+fn rewrite(dir: &Path, tab: TabId, open: &mut Open) -> io::Result<()> {
+    open.file = None;
+    let start = Record::Start {
+        tab,
+        title: open.title.clone(),
+        path: open.path.clone(),
+        text: open.text.clone(),
+    };
+    let mut line = serde_json::to_vec(&start)?;
+    line.push(b'\n');
+    let journal = journal_path(dir, tab);
+    crate::save::write_file(&journal, &line, crate::save::Backup::Off)?;
+    open.file = Some(OpenOptions::new().append(true).open(&journal)?);
+    open.appended = 0;
+    Ok(())
+}
+
+/// The single replacement that turns `old` into `new`: a byte offset, the
+/// number of bytes removed there and the text inserted instead.
+fn splice<'a>(old: &str, new: &'a str) -> (usize, usize, &'a str) {
+    let mut prefix = old
+        .bytes()
+        .zip(new.bytes())
+        .take_while(|(a, b)| a == b)
+        .count();
+    while !new.is_char_boundary(prefix) {
+        prefix -= 1;
+    }
+    let max_suffix = old.len().min(new.len()) - prefix;
+    let mut suffix = old
+        .bytes()
+        .rev()
+        .zip(new.bytes().rev())
+        .take(max_suffix)
+        .take_while(|(a, b)| a == b)
+        .count();
+    while !new.is_char_boundary(new.len() - suffix) {
+        suffix -= 1;
+    }
+    (
+        prefix,
+        old.len() - suffix - prefix,
+        &new[prefix..new.len() - suffix],
+    )
+}
+
+/// Unsaved edits of a tab, recovered from the journal of a crashed instance.
+pub struct RecoveredTab {
+    pub tab: TabId,
+    pub title: String,
+    pub path: Option<PathBuf>,
+    pub text: String,
+}
+
+/// What crashed instances left behind. Their journals are kept until
+/// [`Recovery::finish`], in case this instance crashes too.
+pub struct Recovery {
+    /// Directories of crashed instances, locked so no other instance
+    /// recovers them as well.
+    dirs: Vec<(PathBuf, File)>,
+    pub tabs: Vec<RecoveredTab>,
+}
+
+impl Recovery {
+    /// Look for journals of instances that did not exit cleanly.
+    pub fn find() -> Option<Self> {
+        let mut recovery = Self {
+            dirs: Vec::new(),
+            tabs: Vec::new(),
+        };
+        for entry in fs::read_dir(recovery_dir()).ok()?.flatten() {
+            let dir = entry.path();
+            let Ok(lock) = OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(false)
+                .open(dir.join(LOCK_FILE))
+            else {
+                continue;
+            };
+            // Still running
+            if lock.try_lock().is_err() {
+                continue;
+            }
+            let tabs = read_journals(&dir);
+            if tabs.is_empty() {
+                let _ = fs::remove_dir_all(&dir);
+            } else {
+                recovery.tabs.extend(tabs);
+                recovery.dirs.push((dir, lock));
+            }
+        }
+        (!recovery.tabs.is_empty()).then_some(recovery)
+    }
+
+    /// Remove the journals, once every tab was restored or discarded.
+    pub fn finish(self) {
+        for (dir, lock) in self.dirs {
+            drop(lock);
+            if let Err(e) = fs::remove_dir_all(&dir) {
+                log::warn!("Failed to remove recovery journals {:?}: {}", dir, e);
+            }
+        }
+    }
+}
+
+fn read_journals(dir: &Path) -> Vec<RecoveredTab> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|e| e == JOURNAL_EXTENSION))
+        .filter_map(|path| match read_journal(&path) {
+            Ok(tab) => tab,
+            Err(e) => {
+                log::warn!("Failed to read recovery journal {:?}: {}", path, e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Replay a journal. A line that does not parse or apply was cut off by the
+/// crash, and ends it.
+fn read_journal(path: &Path) -> io::Result<Option<RecoveredTab>> {
+    let mut lines = BufReader::new(File::open(path)?).lines();
+    let Some(Ok(first)) = lines.next() else {
+        return Ok(None);
+    };
+    let Ok(Record::Start {
+        tab,
+        title,
+        path,
+        mut text,
+    }) = serde_json::from_str(&first)
+    else {
+        return Ok(None);
+    };
+    for line in lines {
+        let Ok(line) = line else {
+            break;
+        };
+        let Ok(Record::Splice {
+            at,
+            removed,
+            text: inserted,
+        }) = serde_json::from_str(&line)
+        else {
+            break;
+        };
+        let end = at.saturating_add(removed);
+        if end > text.len() || !text.is_char_boundary(at) || !text.is_char_boundary(end) {
+            break;
+        }
+        text.replace_range(at..end, &inserted);
+    }
+    Ok(Some(RecoveredTab {
+        tab,
+        title,
+        path,
+        text,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_dir;
+
+    fn dirty_tab(text: &str) -> EditorTab {
+        let mut tab = EditorTab::new(Some(PathBuf::from("notes.txt")), text.to_string());
+        tab.is_dirty = true;
+        tab
+    }
+
+    /// Journal `tab` as it is now, without waiting for the interval.
+    fn track(journal: &mut Journal, tab: &EditorTab) {
+        journal.last_check -= INTERVAL;
+        journal.track(std::slice::from_ref(tab));
+    }
+
+    /// Stop the writer, leaving the journals behind like a crash would.
+    fn crash(mut journal: Journal) {
+        journal.tx = None;
+        journal.thread.take().unwrap().join().unwrap();
+    }
+
+    #[test]
+    fn splice_stays_on_char_boundaries() {
+        for (old, new) in [
+            ("héllo", "hèllo"),
+            ("aé", "aè"),
+            ("日本語", "日本人語"),
+            ("ab", "a😀b"),
+            ("😀😁", "😁"),
+            ("same", "same"),
+            ("", "new"),
+            ("old", ""),
+        ] {
+            let (at, removed, inserted) = splice(old, new);
+            let mut text = old.to_string();
+            text.replace_range(at..at + removed, inserted);
+            assert_eq!(text, new, "{:?} -> {:?}", old, new);
+        }
+        // Only the change itself
+        assert_eq!(splice("héllo wörld", "héllo, wörld"), (6, 0, ","));
+    }
+
+    #[test]
+    fn replay_restores_the_edits() {
+        let dir = test_dir("recovery-replay");
+        let mut journal = Journal::start_in(dir.clone()).unwrap();
+        let mut tab = dirty_tab("première ligne\n");
+        let edits = [
+            "première ligne\nzweite Zeile\n",
+            "première ligne\nzweite Zeile\n第三行\n",
+            "première ligne\n第三行\n",
+            "Première ligne 😀\n第三行\n",
+        ];
+        track(&mut journal, &tab);
+        for text in edits {
+            tab.content = text.to_string();
+            track(&mut journal, &tab);
+        }
+        crash(journal);
+
+        let path = journal_path(&dir, tab.id);
+        let recovered = read_journal(&path).unwrap().unwrap();
+        assert_eq!(recovered.tab, tab.id);
+        assert_eq!(recovered.path, tab.path);
+        assert_eq!(recovered.text, edits[3]);
+
+        // Cut inside the last line, in the middle of "😀": that edit is lost
+        let journal = fs::read(&path).unwrap();
+        let last_line = journal[..journal.len() - 1]
+            .iter()
+            .rposition(|&b| b == b'\n')
+            .unwrap();
+        let emoji = journal
+            .windows(4)
+            .rposition(|w| w == "😀".as_bytes())
+            .unwrap();
+        assert!(emoji > last_line);
+        fs::write(&path, &journal[..emoji + 2]).unwrap();
+        assert_eq!(read_journal(&path).unwrap().unwrap().text, edits[2]);
+        fs::write(&path, &journal[..last_line + 10]).unwrap();
+        assert_eq!(read_journal(&path).unwrap().unwrap().text, edits[2]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn large_journals_are_compacted() {
+        let dir = test_dir("recovery-compact");
+        let mut journal = Journal::start_in(dir.clone()).unwrap();
+        let mut tab = dirty_tab("");
+        track(&mut journal, &tab);
+        // Each edit replaces the whole text, so it is appended in full
+        for i in 0..40 {
+            tab.content = format!("{}", i).repeat(10_000);
+            track(&mut journal, &tab);
+        }
+        crash(journal);
+
+        let path = journal_path(&dir, tab.id);
+        assert!(fs::metadata(&path).unwrap().len() < 2 * COMPACT_AT);
+        assert_eq!(read_journal(&path).unwrap().unwrap().text, tab.content);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn saved_tabs_are_forgotten_and_close_cleans_up() {
+        let dir = test_dir("recovery-forget");
+        let mut journal = Journal::start_in(dir.clone()).unwrap();
+        let mut tab = dirty_tab("draft");
+        track(&mut journal, &tab);
+        tab.is_dirty = false;
+        track(&mut journal, &tab);
+        journal.tx = None;
+        journal.thread.take().unwrap().join().unwrap();
+        assert!(read_journals(&dir).is_empty());
+
+        journal.close();
+        assert!(!dir.exists());
+    }
+}
@@ -0,0 +1,426 @@
+//! Remote control over the single-instance socket.
+//!
+//! A connection whose first line is a JSON-RPC 2.0 request is a session: the
+//! client sends one request per line and gets one response per line, until it
+//! closes the connection. Notifications (requests without an `id`) are carried
+//! out without a response. Batches are not supported.
+//!
+//! ```text
+//! → {"jsonrpc":"2.0","id":1,"method":"goto","params":{"path":"/src/main.rs","line":12,"column":5}}
+//! ← {"jsonrpc":"2.0","id":1,"result":null}
+//! ```
+//!
+//! Methods, with 1-based lines and columns and absolute paths:
+//!
+//! - `goto {path?, line, column?}`: open the file (or use the active tab) and
+//!   put the cursor there
+//! - `highlight {path?, line, column?, end_line?, end_column?}`: select a range,
+//!   by default to the end of the line
+//! - `insert {text, path?, line?, column?}`: insert text at a position, or
+//!   replace the selection when no line is given
+//! - `active`: the file and selection of the active tab, see [`ActiveTab`]
+
+use interprocess::local_socket::{prelude::*, Name, Stream};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::io::BufReader;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Sender};
+use std::time::Duration;
+
+use crate::ipc::{read_line, write_line, Reply, Request};
+
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+/// The editor could not carry out a valid call, e.g. because no tab is open.
+pub const EDITOR_ERROR: i64 = -32000;
+
+/// How long a call may take the editor, which answers between frames.
+const CALL_TIMEOUT: Duration = Duration::from_secs(10);
+
+const METHODS: &[&str] = &["goto", "highlight", "insert", "active"];
+
+/// A call as passed to the editor.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "method", content = "params", rename_all = "snake_case")]
+pub enum Call {
+    Goto(Target),
+    Highlight(Target),
+    Insert(Insert),
+    Active,
+}
+
+/// A position or range in a file, or in the active tab when `path` is `None`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Target {
+    #[serde(default)]
+    pub path: Option<PathBuf>,
+    pub line: usize,
+    #[serde(default)]
+    pub column: Option<usize>,
+    #[serde(default)]
+    pub end_line: Option<usize>,
+    #[serde(default)]
+    pub end_column: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Insert {
+    pub text: String,
+    /// An open file; the active tab by default.
+    #[serde(default)]
+    pub path: Option<PathBuf>,
+    /// Where to insert; the selection is replaced when this is `None`.
+    #[serde(default)]
+    pub line: Option<usize>,
+    #[serde(default)]
+    pub column: Option<usize>,
+}
+
+/// The result of `active`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ActiveTab {
+    /// `None` for a tab that has never been saved.
+    pub path: Option<PathBuf>,
+    pub title: String,
+    pub dirty: bool,
+    pub read_only: bool,
+    pub selection: Selection,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Selection {
+    pub start: Position,
+    pub end: Position,
+    pub text: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for RpcError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.message, self.code)
+    }
+}
+
+impl std::error::Error for RpcError {}
+
+#[derive(Serialize)]
+struct RpcRequest<'a> {
+    jsonrpc: &'a str,
+    id: u64,
+    method: &'a str,
+    params: Value,
+}
+
+#[derive(Serialize, Deserialize)]
+struct RpcResponse {
+    jsonrpc: String,
+    id: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<RpcError>,
+}
+
+impl RpcResponse {
+    fn new(id: Value, result: Result<Value, RpcError>) -> Self {
+        let (result, error) = match result {
+            Ok(result) => (Some(result), None),
+            Err(error) => (None, Some(error)),
+        };
+        Self {
+            jsonrpc: "2.0".to_string(),
+            id,
+            result,
+            error,
+        }
+    }
+}
+
+/// Whether the first line of a connection starts a JSON-RPC session.
+pub(crate) fn is_rpc(line: &str) -> bool {
+    serde_json::from_str::<Map<String, Value>>(line).is_ok_and(|m| m.contains_key("jsonrpc"))
+}
+
+/// Answer the requests of a session, starting with `first`, passing calls on
+/// with `forward` like [`crate::ipc`] does with other requests.
+pub(crate) fn serve_session(
+    first: String,
+    conn: &mut BufReader<Stream>,
+    forward: impl Fn(Request, Option<Sender<Reply>>) -> bool,
+) -> std::io::Result<()> {
+    // A client may keep the session open between calls
+    conn.get_ref().set_recv_timeout(None)?;
+    let mut line = first;
+    loop {
+        if let Some(response) = respond(&line, &forward) {
+            write_line(conn.get_mut(), &response)?;
+        }
+        line = match read_line(conn) {
+            Ok(line) => line,
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(()),
+            Err(e) => return Err(e),
+        };
+    }
+}
+
+/// The response to one line, `None` for a notification.
+fn respond(
+    line: &str,
+    forward: &impl Fn(Request, Option<Sender<Reply>>) -> bool,
+) -> Option<RpcResponse> {
+    let request = match serde_json::from_str::<Value>(line) {
+        Ok(Value::Object(request)) => request,
+        Ok(_) => {
+            let error = RpcError::new(INVALID_REQUEST, "expected a request object");
+            return Some(RpcResponse::new(Value::Null, Err(error)));
+        }
+        Err(e) => {
+            let error = RpcError::new(PARSE_ERROR, e.to_string());
+            return Some(RpcResponse::new(Value::Null, Err(error)));
+        }
+    };
+    let id = request.get("id").cloned();
+    let result = call(request, forward);
+    id.map(|id| RpcResponse::new(id, result))
+}
+
+fn call(
+    mut request: Map<String, Value>,
+    forward: &impl Fn(Request, Option<Sender<Reply>>) -> bool,
+) -> Result<Value, RpcError> {
+    if request.get("jsonrpc").and_then(Value::as_str) != Some("2.0") {
+        return Err(RpcError::new(INVALID_REQUEST, "jsonrpc must be \"2.0\""));
+    }
+    let Some(Value::String(method)) = request.remove("method") else {
+        return Err(RpcError::new(INVALID_REQUEST, "method must be a string"));
+    };
+    if !METHODS.contains(&method.as_str()) {
+        let message = format!("unknown method: {}", method);
+        return Err(RpcError::new(METHOD_NOT_FOUND, message));
+    }
+
+    let mut call = Map::new();
+    call.insert("method".to_string(), Value::String(method));
+    match request.remove("params") {
+        None | Some(Value::Null) => {}
+        // `active` takes no parameters, but clients often send `{}`
+        Some(Value::Object(params)) if params.is_empty() => {}
+        Some(params) => {
+            call.insert("params".to_string(), params);
+        }
+    }
+    let call = serde_json::from_value::<Call>(Value::Object(call))
+        .map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))?;
+
+    let (tx, rx) = mpsc::channel();
+    if !forward(Request::Call(call), Some(tx)) {
+        return Err(RpcError::new(EDITOR_ERROR, "the editor is shutting down"));
+    }
+    match rx.recv_timeout(CALL_TIMEOUT) {
+        Ok(Reply::Result { result }) => Ok(result),
+        Ok(Reply::Error { message }) => Err(RpcError::new(EDITOR_ERROR, message)),
+        Ok(Reply::Ok | Reply::Closed) => Ok(Value::Null),
+        Err(_) => Err(RpcError::new(EDITOR_ERROR, "the editor did not answer")),
+    }
+}
+
+/// Remote-control session with the running instance.
+pub struct RpcClient {
+    conn: BufReader<Stream>,
+    next_id: u64,
+}
+
+impl RpcClient {
+    /// Fails with `ConnectionRefused` or `NotFound` when no instance is listening.
+    pub fn connect() -> std::io::Result<Self> {
+        Self::connect_to(crate::ipc::socket_name()?)
+    }
+
+    pub(crate) fn connect_to(name: Name<'_>) -> std::io::Result<Self> {
+        let conn = Stream::connect(name)?;
+        conn.set_recv_timeout(Some(CALL_TIMEOUT + Duration::from_secs(2)))?;
+        Ok(Self {
+            conn: BufReader::new(conn),
+            next_id: 0,
+        })
+    }
+
+    /// Call `method` and wait for its result. The outer error is a failed
+    /// connection, the inner one an error response.
+    pub fn call(
+        &mut self,
+        method: &str,
+        params: Value,
+    ) -> std::io::Result<Result<Value, RpcError>> {
+        self.next_id += 1;
+        let request = RpcRequest {
+            jsonrpc: "2.0",
+            id: self.next_id,
+            method,
+            params,
+        };
+        write_line(self.conn.get_mut(), &request)?;
+        let response: RpcResponse = serde_json::from_str(&read_line(&mut self.conn)?)?;
+        Ok(match response.error {
+            Some(error) => Err(error),
+            None => Ok(response.result.unwrap_or(Value::Null)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ipc::{Incoming, Server};
+    use std::io::{BufRead, Write};
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// A server on a socket of its own, with a stand-in for the editor that
+    /// answers `goto` and `active` and refuses `insert`.
+    fn start() -> Name<'static> {
+        static NEXT: AtomicU32 = AtomicU32::new(0);
+        let name = || {
+            #[cfg(unix)]
+            {
+                use interprocess::local_socket::GenericFilePath;
+                let file = format!(
+                    "notos-rpc-test-{}-{}.sock",
+                    std::process::id(),
+                    NEXT.fetch_add(1, Ordering::Relaxed)
+                );
+                std::env::temp_dir()
+                    .join(file)
+                    .to_fs_name::<GenericFilePath>()
+                    .unwrap()
+            }
+            #[cfg(windows)]
+            {
+                use interprocess::local_socket::GenericNamespaced;
+                format!(
+                    "notos-rpc-test-{}-{}",
+                    std::process::id(),
+                    NEXT.fetch_add(1, Ordering::Relaxed)
+                )
+                .to_ns_name::<GenericNamespaced>()
+                .unwrap()
+            }
+        };
+        let name = name();
+        let (tx, rx) = mpsc::channel::<Incoming>();
+        Server::bind_name(name.clone(), true)
+            .unwrap()
+            .spawn(tx, egui::Context::default());
+        std::thread::spawn(move || {
+            for incoming in rx {
+                let Request::Call(call) = incoming.request else {
+                    continue;
+                };
+                let reply = match call {
+                    Call::Goto(target) => Reply::Result {
+                        result: serde_json::to_value(&target).unwrap(),
+                    },
+                    Call::Active => Reply::Result {
+                        result: serde_json::json!({ "title": "notes.txt" }),
+                    },
+                    Call::Insert(_) | Call::Highlight(_) => Reply::Error {
+                        message: "the tab is read-only".to_string(),
+                    },
+                };
+                let _ = incoming.reply.unwrap().send(reply);
+            }
+        });
+        name
+    }
+
+    fn raw(name: Name<'_>, lines: &[&str]) -> Vec<Value> {
+        let mut conn = Stream::connect(name).unwrap();
+        for line in lines {
+            writeln!(conn, "{}", line).unwrap();
+        }
+        conn.flush().unwrap();
+        let mut conn = BufReader::new(conn);
+        let mut responses = Vec::new();
+        let mut line = String::new();
+        // One line per expected response; the session stays open
+        for _ in lines.iter().filter(|l| l.contains("\"id\"")) {
+            line.clear();
+            conn.read_line(&mut line).unwrap();
+            responses.push(serde_json::from_str(&line).unwrap());
+        }
+        responses
+    }
+
+    #[test]
+    fn calls_reach_the_editor() {
+        let mut client = RpcClient::connect_to(start()).unwrap();
+        let params = serde_json::json!({ "path": "/tmp/a.rs", "line": 3, "column": 7 });
+        let result = client.call("goto", params).unwrap().unwrap();
+        let target: Target = serde_json::from_value(result).unwrap();
+        assert_eq!(target.path, Some(PathBuf::from("/tmp/a.rs")));
+        assert_eq!((target.line, target.column), (3, Some(7)));
+
+        // Several calls on one connection; `active` takes no parameters
+        for params in [Value::Null, serde_json::json!({})] {
+            let result = client.call("active", params).unwrap().unwrap();
+            assert_eq!(result["title"], "notes.txt");
+        }
+    }
+
+    #[test]
+    fn errors_have_codes() {
+        let mut client = RpcClient::connect_to(start()).unwrap();
+        let code =
+            |result: std::io::Result<Result<Value, RpcError>>| result.unwrap().unwrap_err().code;
+        assert_eq!(code(client.call("save_all", Value::Null)), METHOD_NOT_FOUND);
+        let params = serde_json::json!({ "column": 2 });
+        assert_eq!(code(client.call("goto", params)), INVALID_PARAMS);
+        let params = serde_json::json!({ "text": "x" });
+        let error = client.call("insert", params).unwrap().unwrap_err();
+        assert_eq!(error.code, EDITOR_ERROR);
+        assert_eq!(error.message, "the tab is read-only");
+    }
+
+    #[test]
+    fn raw_requests() {
+        let responses = raw(
+            start(),
+            &[
+                r#"{"jsonrpc":"2.0","method":"goto","params":{"line":1}}"#,
+                r#"{"jsonrpc":"2.0","id":"a","method":"goto","params":{"line":2}}"#,
+                r#"{"jsonrpc":"2.0","id":7,"method":"#,
+                r#"{"jsonrpc":"1.0","id":8,"method":"active"}"#,
+            ],
+        );
+        // The notification got no response
+        assert_eq!(responses.len(), 3);
+        assert_eq!(responses[0]["id"], "a");
+        assert_eq!(responses[0]["result"]["line"], 2);
+        assert_eq!(responses[1]["id"], Value::Null);
+        assert_eq!(responses[1]["error"]["code"], PARSE_ERROR);
+        assert_eq!(responses[2]["id"], 8);
+        assert_eq!(responses[2]["error"]["code"], INVALID_REQUEST);
+    }
+}
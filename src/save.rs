@@ -0,0 +1,317 @@
+//! Writing files without losing them to a crash, plus optional backups.
+//!
+//! A save goes to a temporary file next to the original, which is flushed to
+//! disk and then renamed over it, so the file is always either the old or the
+//! new version. Where renaming would change what the file is — a hard link,
+//! an owner that cannot be restored, a directory that cannot be written — the
+//! file is written in place instead, like before.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// What to keep of a file's previous version when saving over it.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Backup {
+    #[default]
+    Off,
+    /// A copy named `<file>.bak` next to the file.
+    Copy,
+    /// The last `n` versions in the backup folder of the config directory.
+    Folder(usize),
+}
+
+/// Write `bytes` to `path`, keeping the previous version as `backup` says.
+pub fn write_file(path: &Path, bytes: &[u8], backup: Backup) -> io::Result<()> {
+    // Saving through a symlink replaces the file it points to, not the link
+    let target = resolve_symlink(path);
+    let original = fs::metadata(&target).ok().filter(|m| m.is_file());
+
+    if let Some(original) = &original {
+        if let Err(e) = back_up(&target, original, backup) {
+            log::warn!("Failed to back up {:?}: {}", target, e);
+        }
+        if !can_replace(original) {
+            return write_in_place(&target, bytes);
+        }
+    }
+    match write_replacing(&target, bytes, original.as_ref()) {
+        Err(e) if original.is_some() && e.kind() == io::ErrorKind::PermissionDenied => {
+            write_in_place(&target, bytes)
+        }
+        result => result,
+    }
+}
+
+/// Where a write to `path` ends up, following symlinks even when the file they
+/// point to does not exist yet.
+fn resolve_symlink(path: &Path) -> PathBuf {
+    let mut path = path.to_path_buf();
+    // Give up on loops, and write to the link itself like before
+    for _ in 0..40 {
+        let Ok(link) = fs::read_link(&path) else {
+            return path;
+        };
+        path = match path.parent() {
+            Some(parent) => parent.join(link),
+            None => link,
+        };
+    }
+    path
+}
+
+/// Whether renaming a new file over `original` keeps what it is.
+#[cfg(unix)]
+fn can_replace(original: &fs::Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    // Other links would keep the old version
+    original.nlink() <= 1
+}
+
+#[cfg(not(unix))]
+fn can_replace(_original: &fs::Metadata) -> bool {
+    true
+}
+
+fn write_replacing(target: &Path, bytes: &[u8], original: Option<&fs::Metadata>) -> io::Result<()> {
+    let dir = match target.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let (mut file, temp) = create_temp(dir, target)?;
+    let result = (|| {
+        file.write_all(bytes)?;
+        if let Some(original) = original {
+            keep_attributes(&file, original)?;
+        }
+        file.sync_all()?;
+        drop(file);
+        fs::rename(&temp, target)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+        return result;
+    }
+    // Make the rename itself survive a crash
+    #[cfg(unix)]
+    if let Ok(dir) = File::open(dir) {
+        let _ = dir.sync_all();
+    }
+    Ok(())
+}
+
+/// A new, empty file in `dir`, hidden and named after `target`.
+fn create_temp(dir: &Path, target: &Path) -> io::Result<(File, PathBuf)> {
+    let name = target
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mut attempt = 0;
+    loop {
+        let temp = dir.join(format!(".{}.{}-{}.tmp", name, std::process::id(), attempt));
+        match OpenOptions::new().write(true).create_new(true).open(&temp) {
+            Ok(file) => return Ok((file, temp)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists && attempt < 100 => attempt += 1,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Give the new version the permissions and owner of the old one.
+fn keep_attributes(file: &File, original: &fs::Metadata) -> io::Result<()> {
+    file.set_permissions(original.permissions())?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        let created = file.metadata()?;
+        if (created.uid(), created.gid()) != (original.uid(), original.gid()) {
+            // Only allowed for root, or for a group the user is in; otherwise
+            // the caller writes in place so the owner stays
+            std::os::unix::fs::fchown(file, Some(original.uid()), Some(original.gid()))?;
+        }
+    }
+    Ok(())
+}
+
+fn write_in_place(target: &Path, bytes: &[u8]) -> io::Result<()> {
+    let mut file = File::create(target)?;
+    file.write_all(bytes)?;
+    file.sync_all()
+}
+
+fn back_up(target: &Path, original: &fs::Metadata, backup: Backup) -> io::Result<()> {
+    match backup {
+        Backup::Off => Ok(()),
+        Backup::Copy => {
+            let mut name = target.as_os_str().to_owned();
+            name.push(".bak");
+            copy_file(target, Path::new(&name), original)
+        }
+        Backup::Folder(versions) => {
+            let dir = backup_dir(target);
+            fs::create_dir_all(&dir)?;
+            let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S%.3f");
+            copy_file(target, &dir.join(stamp.to_string()), original)?;
+            prune_backups(&dir, versions)
+        }
+    }
+}
+
+fn copy_file(from: &Path, to: &Path, original: &fs::Metadata) -> io::Result<()> {
+    fs::copy(from, to)?;
+    fs::set_permissions(to, original.permissions())
+}
+
+/// The folder with the backups of `target`, named after the file and a hash of
+/// its full path so files with the same name do not mix.
+fn backup_dir(target: &Path) -> PathBuf {
+    use sha2::{Digest, Sha256};
+    let full = fs::canonicalize(target).unwrap_or_else(|_| target.to_path_buf());
+    let hash = Sha256::digest(full.to_string_lossy().as_bytes());
+    let hash: String = hash[..4].iter().map(|b| format!("{:02x}", b)).collect();
+    let name = target
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    crate::utils::config_dir()
+        .join("backups")
+        .join(format!("{}-{}", name, hash))
+}
+
+/// Remove all but the newest `versions` backups in `dir`.
+fn prune_backups(dir: &Path, versions: usize) -> io::Result<()> {
+    let mut backups = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .collect::<Vec<_>>();
+    // Named by time, so oldest first
+    backups.sort();
+    let excess = backups.len().saturating_sub(versions.max(1));
+    for old in &backups[..excess] {
+        fs::remove_file(old)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_dir;
+
+    fn names(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn replaces_the_file_without_leftovers() {
+        let dir = test_dir("save-replace");
+        let file = dir.join("note.txt");
+        write_file(&file, b"old", Backup::Off).unwrap();
+        write_file(&file, b"new", Backup::Off).unwrap();
+        assert_eq!(fs::read(&file).unwrap(), b"new");
+        assert_eq!(names(&dir), ["note.txt"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn copy_backup_keeps_the_previous_version() {
+        let dir = test_dir("save-bak");
+        let file = dir.join("note.txt");
+        // Nothing to back up yet
+        write_file(&file, b"first", Backup::Copy).unwrap();
+        assert_eq!(names(&dir), ["note.txt"]);
+        write_file(&file, b"second", Backup::Copy).unwrap();
+        write_file(&file, b"third", Backup::Copy).unwrap();
+        assert_eq!(fs::read(&file).unwrap(), b"third");
+        assert_eq!(fs::read(dir.join("note.txt.bak")).unwrap(), b"second");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn pruning_keeps_the_newest_versions() {
+        let dir = test_dir("save-prune");
+        let stamps = [
+            "20240101-090000.000",
+            "20240101-100000.000",
+            "20240102-080000.000",
+            "20240102-080000.500",
+            "20240103-120000.000",
+        ];
+        for stamp in stamps {
+            fs::write(dir.join(stamp), stamp).unwrap();
+        }
+        prune_backups(&dir, 3).unwrap();
+        assert_eq!(names(&dir), stamps[2..]);
+        // At least the last version stays
+        prune_backups(&dir, 0).unwrap();
+        assert_eq!(names(&dir), stamps[4..]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn saving_through_a_symlink_keeps_the_link() {
+        use std::os::unix::fs::symlink;
+        let dir = test_dir("save-symlink");
+        fs::create_dir(dir.join("real")).unwrap();
+        let target = dir.join("real").join("note.txt");
+        fs::write(&target, "old").unwrap();
+        let relative = dir.join("relative");
+        let absolute = dir.join("absolute");
+        symlink(Path::new("real").join("note.txt"), &relative).unwrap();
+        symlink(&target, &absolute).unwrap();
+
+        for (link, text) in [(&relative, "relative"), (&absolute, "absolute")] {
+            write_file(link, text.as_bytes(), Backup::Copy).unwrap();
+            assert!(fs::symlink_metadata(link).unwrap().file_type().is_symlink());
+            assert_eq!(fs::read_to_string(&target).unwrap(), text);
+        }
+        // The backup is of the file, next to it
+        assert_eq!(
+            fs::read_to_string(dir.join("real").join("note.txt.bak")).unwrap(),
+            "relative"
+        );
+        assert_eq!(names(&dir.join("real")), ["note.txt", "note.txt.bak"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn keeps_the_mode_bits() {
+        use std::os::unix::fs::{MetadataExt, PermissionsExt};
+        let dir = test_dir("save-mode");
+        let file = dir.join("script.sh");
+        fs::write(&file, "#!/bin/sh\n").unwrap();
+        fs::set_permissions(&file, fs::Permissions::from_mode(0o751)).unwrap();
+        let inode = fs::metadata(&file).unwrap().ino();
+        write_file(&file, b"#!/bin/sh\necho hi\n", Backup::Copy).unwrap();
+        // A new file took its place
+        assert_ne!(fs::metadata(&file).unwrap().ino(), inode);
+        let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(&file), 0o751);
+        assert_eq!(mode(&dir.join("script.sh.bak")), 0o751);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn hard_links_are_written_in_place() {
+        use std::os::unix::fs::MetadataExt;
+        let dir = test_dir("save-hardlink");
+        let file = dir.join("note.txt");
+        let other = dir.join("other.txt");
+        fs::write(&file, "old").unwrap();
+        fs::hard_link(&file, &other).unwrap();
+        let inode = fs::metadata(&file).unwrap().ino();
+
+        write_file(&file, b"new", Backup::Off).unwrap();
+        assert_eq!(fs::read_to_string(&other).unwrap(), "new");
+        let metadata = fs::metadata(&file).unwrap();
+        assert_eq!((metadata.ino(), metadata.nlink()), (inode, 2));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,383 @@
+//! User scripts written in Rhai.
+//!
+//! Every `*.rhai` file in the scripts folder (`<config dir>/scripts`) becomes a
+//! `script.<file stem>` command. Optional header comments set its title and
+//! default keybinding:
+//!
+//! ```text
+//! // @title: Sort Lines
+//! // @key: Ctrl+Alt+S
+//! ```
+//!
+//! A script runs on a worker thread against a snapshot of the active tab and
+//! can be cancelled at any time. When it finishes, its changes to the text are
+//! applied as a single undo step, then any files it asked to open or save are
+//! handled.
+
+use std::cell::RefCell;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+
+use rhai::{Array, Dynamic, Engine, EvalAltResult, Map, INT};
+
+use crate::editor::TabId;
+
+/// Command id prefix of script commands.
+pub const SCRIPT_COMMAND_PREFIX: &str = "script.";
+
+/// Written by "New Script..." as a starting point.
+const SCRIPT_TEMPLATE: &str = r#"// @title: My Script
+// @key:
+//
+// text() / set_text(s)            whole document
+// selection() / select(start, end) selection as #{start, end} (character indices)
+// selected_text() / insert(s)     insert(s) replaces the selection, like typing
+// insert_at(pos, s)               insert at a character index
+// find(pattern[, from])           character index of the next match, or -1
+// lines() / line_count()          document lines
+// file_path()                     path of the tab, or () if untitled
+// open(path) / save() / save_as(path)
+// print(x)                        shows `x` in the status bar
+
+let lines = lines();
+lines.sort();
+let sorted = "";
+for (line, i) in lines {
+    if i > 0 { sorted += "\n"; }
+    sorted += line;
+}
+set_text(sorted);
+"#;
+
+/// A script found in the scripts folder.
+pub struct Script {
+    /// File stem, used in the command id.
+    pub id: String,
+    pub title: String,
+    /// Default keybinding from the `@key` header.
+    pub keybinding: Option<String>,
+    pub path: PathBuf,
+}
+
+impl Script {
+    pub fn command_id(&self) -> String {
+        format!("{}{}", SCRIPT_COMMAND_PREFIX, self.id)
+    }
+}
+
+pub fn scripts_dir() -> PathBuf {
+    crate::utils::config_dir().join("scripts")
+}
+
+/// All scripts in the scripts folder, sorted by title.
+pub fn discover() -> Vec<Script> {
+    let Ok(entries) = std::fs::read_dir(scripts_dir()) else {
+        return Vec::new();
+    };
+    let mut scripts: Vec<Script> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| {
+            p.extension()
+                .is_some_and(|e| e.eq_ignore_ascii_case("rhai"))
+        })
+        .filter_map(|path| {
+            let id = path.file_stem()?.to_string_lossy().into_owned();
+            let source = std::fs::read_to_string(&path).unwrap_or_default();
+            let header = |name: &str| {
+                source
+                    .lines()
+                    .map_while(|l| l.trim().strip_prefix("//"))
+                    .find_map(|l| l.trim().strip_prefix(name)?.trim().strip_prefix(':'))
+                    .map(|v| v.trim().to_string())
+                    .filter(|v| !v.is_empty())
+            };
+            Some(Script {
+                title: header("@title").unwrap_or_else(|| id.clone()),
+                keybinding: header("@key"),
+                id,
+                path,
+            })
+        })
+        .collect();
+    scripts.sort_by_key(|s| s.title.to_lowercase());
+    scripts
+}
+
+/// Create a new script from the template and return its path.
+pub fn create_script() -> std::io::Result<PathBuf> {
+    let dir = scripts_dir();
+    std::fs::create_dir_all(&dir)?;
+    let path = (1..)
+        .map(|n| match n {
+            1 => dir.join("new_script.rhai"),
+            n => dir.join(format!("new_script_{}.rhai", n)),
+        })
+        .find(|p| !p.exists())
+        .unwrap_or_default();
+    std::fs::write(&path, SCRIPT_TEMPLATE)?;
+    Ok(path)
+}
+
+/// The part of a tab a script works on.
+pub struct ScriptInput {
+    pub text: String,
+    /// Selection in character indices, as `(start, end)` with `start <= end`.
+    pub selection: (usize, usize),
+    pub path: Option<PathBuf>,
+}
+
+/// What a finished script asks the host to do.
+#[derive(Default)]
+pub struct ScriptOutput {
+    /// New document text, if the script changed it.
+    pub text: Option<String>,
+    /// New selection, if the script moved it.
+    pub selection: Option<(usize, usize)>,
+    /// Files to open in new tabs.
+    pub open: Vec<PathBuf>,
+    /// Save the tab afterwards: `Some(None)` to its own path, `Some(Some(p))` as `p`.
+    pub save: Option<Option<PathBuf>>,
+    /// Last value passed to `print`.
+    pub message: Option<String>,
+}
+
+/// A script running on a worker thread.
+pub struct ScriptJob {
+    pub title: String,
+    /// Tab the script works on.
+    pub tab_id: TabId,
+    /// Hash of the tab's text when the script started, to detect concurrent edits.
+    pub text_hash: u64,
+    cancel: Arc<AtomicBool>,
+    rx: mpsc::Receiver<Result<ScriptOutput, String>>,
+}
+
+impl ScriptJob {
+    pub fn spawn(script: &Script, tab_id: TabId, input: ScriptInput, ctx: egui::Context) -> Self {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let (tx, rx) = mpsc::channel();
+        let path = script.path.clone();
+        let flag = cancel.clone();
+        let text_hash = hash_text(&input.text);
+        std::thread::spawn(move || {
+            let result = run(&path, input, flag);
+            let _ = tx.send(result);
+            ctx.request_repaint();
+        });
+        Self {
+            title: script.title.clone(),
+            tab_id,
+            text_hash,
+            cancel,
+            rx,
+        }
+    }
+
+    /// Ask the script to stop; it ends with a "cancelled" error.
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    /// The result, once the script finished.
+    pub fn poll(&self) -> Option<Result<ScriptOutput, String>> {
+        match self.rx.try_recv() {
+            Ok(result) => Some(result),
+            Err(mpsc::TryRecvError::Empty) => None,
+            Err(mpsc::TryRecvError::Disconnected) => Some(Err("script thread panicked".into())),
+        }
+    }
+}
+
+pub fn hash_text(text: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    text.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Document state the script functions operate on.
+struct Document {
+    text: String,
+    selection: (usize, usize),
+    path: Option<PathBuf>,
+    output: ScriptOutput,
+}
+
+impl Document {
+    fn char_len(&self) -> usize {
+        self.text.chars().count()
+    }
+
+    fn byte_offset(&self, char_idx: usize) -> usize {
+        self.text
+            .char_indices()
+            .nth(char_idx)
+            .map_or(self.text.len(), |(i, _)| i)
+    }
+
+    fn clamp(&self, idx: INT) -> usize {
+        (idx.max(0) as usize).min(self.char_len())
+    }
+
+    fn set_selection(&mut self, start: usize, end: usize) {
+        self.selection = (start.min(end), start.max(end));
+        self.output.selection = Some(self.selection);
+    }
+
+    fn replace(&mut self, start: usize, end: usize, with: &str) {
+        let (bs, be) = (self.byte_offset(start), self.byte_offset(end));
+        self.text.replace_range(bs..be, with);
+        self.output.text = Some(String::new());
+    }
+}
+
+fn run(path: &Path, input: ScriptInput, cancel: Arc<AtomicBool>) -> Result<ScriptOutput, String> {
+    let doc = Rc::new(RefCell::new(Document {
+        text: input.text,
+        selection: input.selection,
+        path: input.path,
+        output: ScriptOutput::default(),
+    }));
+
+    let mut engine = Engine::new();
+    engine.on_progress(move |_| {
+        cancel
+            .load(Ordering::Relaxed)
+            .then_some(Dynamic::from("cancelled"))
+    });
+    register_api(&mut engine, &doc);
+
+    let result = engine.run_file(path.to_path_buf());
+    drop(engine);
+
+    let doc = Rc::try_unwrap(doc)
+        .map_err(|_| "script state still borrowed".to_string())?
+        .into_inner();
+    match result {
+        Ok(()) => {
+            let mut output = doc.output;
+            if output.text.is_some() {
+                output.text = Some(doc.text);
+            }
+            Ok(output)
+        }
+        Err(e) => match *e {
+            EvalAltResult::ErrorTerminated(..) => Err("cancelled".to_string()),
+            e => Err(e.to_string()),
+        },
+    }
+}
+
+fn register_api(engine: &mut Engine, doc: &Rc<RefCell<Document>>) {
+    let d = doc.clone();
+    engine.on_print(move |s| {
+        log::info!("[script] {}", s);
+        d.borrow_mut().output.message = Some(s.to_string());
+    });
+
+    let d = doc.clone();
+    engine.register_fn("text", move || d.borrow().text.clone());
+    let d = doc.clone();
+    engine.register_fn("set_text", move |text: &str| {
+        let mut doc = d.borrow_mut();
+        doc.text = text.to_string();
+        doc.output.text = Some(String::new());
+        let len = doc.char_len();
+        let (start, end) = doc.selection;
+        doc.selection = (start.min(len), end.min(len));
+    });
+
+    let d = doc.clone();
+    engine.register_fn("selection", move || {
+        let (start, end) = d.borrow().selection;
+        let mut map = Map::new();
+        map.insert("start".into(), (start as INT).into());
+        map.insert("end".into(), (end as INT).into());
+        map
+    });
+    let d = doc.clone();
+    engine.register_fn("select", move |start: INT, end: INT| {
+        let mut doc = d.borrow_mut();
+        let (start, end) = (doc.clamp(start), doc.clamp(end));
+        doc.set_selection(start, end);
+    });
+    let d = doc.clone();
+    engine.register_fn("selected_text", move || {
+        let doc = d.borrow();
+        let (start, end) = doc.selection;
+        doc.text[doc.byte_offset(start)..doc.byte_offset(end)].to_string()
+    });
+    let d = doc.clone();
+    engine.register_fn("insert", move |text: &str| {
+        let mut doc = d.borrow_mut();
+        let (start, end) = doc.selection;
+        doc.replace(start, end, text);
+        let caret = start + text.chars().count();
+        doc.set_selection(caret, caret);
+    });
+    let d = doc.clone();
+    engine.register_fn("insert_at", move |pos: INT, text: &str| {
+        let mut doc = d.borrow_mut();
+        let pos = doc.clamp(pos);
+        doc.replace(pos, pos, text);
+        // Keep the selection on the same text
+        let shift = |i: usize| {
+            if i >= pos {
+                i + text.chars().count()
+            } else {
+                i
+            }
+        };
+        let (start, end) = doc.selection;
+        doc.selection = (shift(start), shift(end));
+    });
+
+    let d = doc.clone();
+    let find = move |pattern: &str, from: INT| -> INT {
+        let doc = d.borrow();
+        let from = doc.byte_offset(doc.clamp(from));
+        match doc.text[from..].find(pattern) {
+            Some(i) => doc.text[..from + i].chars().count() as INT,
+            None => -1,
+        }
+    };
+    let find_from_start = find.clone();
+    engine.register_fn("find", move |pattern: &str| find_from_start(pattern, 0));
+    engine.register_fn("find", find);
+
+    let d = doc.clone();
+    engine.register_fn("lines", move || -> Array {
+        d.borrow()
+            .text
+            .lines()
+            .map(|l| Dynamic::from(l.to_string()))
+            .collect()
+    });
+    let d = doc.clone();
+    engine.register_fn("line_count", move || {
+        d.borrow().text.lines().count().max(1) as INT
+    });
+
+    let d = doc.clone();
+    engine.register_fn("file_path", move || -> Dynamic {
+        match &d.borrow().path {
+            Some(path) => path.to_string_lossy().into_owned().into(),
+            None => Dynamic::UNIT,
+        }
+    });
+    let d = doc.clone();
+    engine.register_fn("open", move |path: &str| {
+        d.borrow_mut().output.open.push(PathBuf::from(path));
+    });
+    let d = doc.clone();
+    engine.register_fn("save", move || {
+        d.borrow_mut().output.save = Some(None);
+    });
+    let d = doc.clone();
+    engine.register_fn("save_as", move |path: &str| {
+        d.borrow_mut().output.save = Some(Some(PathBuf::from(path)));
+    });
+}
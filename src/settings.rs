@@ -0,0 +1,184 @@
+//! Host-side storage of plugin settings.
+//!
+//! Every plugin gets its own `SettingsHandle`, keyed by plugin id. All stores
+//! are saved together in `plugin_settings.json` in the config directory; a
+//! plugin that is not currently loaded keeps its values.
+//!
+//! The editor's own settings are stored the same way, under [`EDITOR_ID`], and
+//! shown first in the settings window. That key is reserved: no plugin can
+//! take it as its id.
+
+use notos_sdk::{PluginSettings, SettingSpec, SettingsHandle};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use crate::save::Backup;
+
+/// The key the editor's own settings are stored under, see [`is_reserved_id`].
+pub const EDITOR_ID: &str = "@editor";
+
+/// Ids starting with `@` are kept for the editor. Plugins with such an id are
+/// not loaded, so none can read or overwrite the editor's settings.
+pub fn is_reserved_id(id: &str) -> bool {
+    id.starts_with('@')
+}
+
+const BACKUP: &str = "backup";
+const BACKUP_VERSIONS: &str = "backup_versions";
+const BACKUP_MODES: [&str; 3] = ["Off", "Copy next to the file", "Backup folder"];
+const AUTOSAVE: &str = "autosave";
+const AUTOSAVE_DELAY: &str = "autosave_delay";
+const AUTOSAVE_MODES: [&str; 4] = [
+    "Off",
+    "After a delay",
+    "When the window loses focus",
+    "When switching tabs",
+];
+
+/// When tabs with a file are saved without being asked to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AutoSave {
+    Off,
+    /// Once a tab was not edited for this long.
+    AfterDelay(Duration),
+    FocusLost,
+    TabSwitch,
+}
+
+/// The settings of the editor itself, as `(id, name, settings)` for the
+/// settings window.
+pub fn editor_schema() -> (String, String, Vec<SettingSpec>) {
+    let specs = vec![
+        SettingSpec::choice(BACKUP, "Backups", &BACKUP_MODES, BACKUP_MODES[0]).with_description(
+            "Keep the previous version of a file when saving: as <file>.bak, \
+             or in the backups folder of the config directory",
+        ),
+        SettingSpec::number(BACKUP_VERSIONS, "Versions kept", 5.0, 1.0, 100.0)
+            .with_description("How many versions of each file the backup folder keeps"),
+        SettingSpec::choice(AUTOSAVE, "Auto-save", &AUTOSAVE_MODES, AUTOSAVE_MODES[0])
+            .with_description("Save tabs that have a file without being asked to"),
+        SettingSpec::number(AUTOSAVE_DELAY, "Auto-save delay (s)", 5.0, 1.0, 600.0)
+            .with_description(
+                "How long after the last edit a tab is saved, with \"After a delay\"",
+            ),
+    ];
+    (EDITOR_ID.to_string(), "Editor".to_string(), specs)
+}
+
+/// How saving backs up files, from the editor settings.
+pub fn backup(settings: &SettingsHandle) -> Backup {
+    let mode = settings.get_string(BACKUP, BACKUP_MODES[0]);
+    if mode == BACKUP_MODES[1] {
+        Backup::Copy
+    } else if mode == BACKUP_MODES[2] {
+        Backup::Folder(settings.get_f64(BACKUP_VERSIONS, 5.0).max(1.0) as usize)
+    } else {
+        Backup::Off
+    }
+}
+
+/// When tabs are saved automatically, from the editor settings.
+pub fn autosave(settings: &SettingsHandle) -> AutoSave {
+    let mode = settings.get_string(AUTOSAVE, AUTOSAVE_MODES[0]);
+    if mode == AUTOSAVE_MODES[1] {
+        let delay = settings.get_f64(AUTOSAVE_DELAY, 5.0).max(1.0);
+        AutoSave::AfterDelay(Duration::from_secs_f64(delay))
+    } else if mode == AUTOSAVE_MODES[2] {
+        AutoSave::FocusLost
+    } else if mode == AUTOSAVE_MODES[3] {
+        AutoSave::TabSwitch
+    } else {
+        AutoSave::Off
+    }
+}
+
+#[derive(Default)]
+pub struct SettingsStore {
+    plugins: BTreeMap<String, SettingsHandle>,
+}
+
+impl SettingsStore {
+    fn path() -> std::path::PathBuf {
+        crate::utils::config_dir().join("plugin_settings.json")
+    }
+
+    pub fn load() -> Self {
+        let text = match std::fs::read_to_string(Self::path()) {
+            Ok(text) => text,
+            Err(_) => return Self::default(),
+        };
+        match serde_json::from_str::<BTreeMap<String, PluginSettings>>(&text) {
+            Ok(plugins) => Self {
+                plugins: plugins
+                    .into_iter()
+                    .map(|(id, settings)| (id, SettingsHandle::new(settings)))
+                    .collect(),
+            },
+            Err(e) => {
+                log::warn!("Ignoring invalid plugin_settings.json: {}", e);
+                Self::default()
+            }
+        }
+    }
+
+    /// The settings of a plugin, created empty on first use.
+    pub fn handle(&mut self, plugin_id: &str) -> SettingsHandle {
+        self.plugins
+            .entry(plugin_id.to_string())
+            .or_default()
+            .clone()
+    }
+
+    pub fn save(&self) -> std::result::Result<(), Box<dyn std::error::Error>> {
+        let snapshot: BTreeMap<&String, PluginSettings> = self
+            .plugins
+            .iter()
+            .map(|(id, handle)| (id, handle.read(|s| s.clone())))
+            .collect();
+        std::fs::write(Self::path(), serde_json::to_string_pretty(&snapshot)?)?;
+        Ok(())
+    }
+
+    /// Save if any plugin changed a value since the last call.
+    pub fn save_if_dirty(&self) {
+        let mut dirty = false;
+        for handle in self.plugins.values() {
+            dirty |= handle.write(|s| s.take_dirty());
+        }
+        if dirty {
+            if let Err(e) = self.save() {
+                log::error!("Failed to save plugin settings: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn autosave_mode_follows_the_setting() {
+        let settings = SettingsHandle::new(PluginSettings::default());
+        assert_eq!(autosave(&settings), AutoSave::Off);
+
+        settings.set(AUTOSAVE, AUTOSAVE_MODES[1]);
+        assert_eq!(
+            autosave(&settings),
+            AutoSave::AfterDelay(Duration::from_secs(5))
+        );
+        // Too short a delay would save on every keystroke
+        settings.set(AUTOSAVE_DELAY, 0.0);
+        assert_eq!(
+            autosave(&settings),
+            AutoSave::AfterDelay(Duration::from_secs(1))
+        );
+
+        settings.set(AUTOSAVE, AUTOSAVE_MODES[2]);
+        assert_eq!(autosave(&settings), AutoSave::FocusLost);
+        settings.set(AUTOSAVE, AUTOSAVE_MODES[3]);
+        assert_eq!(autosave(&settings), AutoSave::TabSwitch);
+        settings.set(AUTOSAVE, "unknown");
+        assert_eq!(autosave(&settings), AutoSave::Off);
+    }
+}
@@ -0,0 +1,204 @@
+//! Plugin trust allowlist.
+//!
+//! Trust mode is opt-in. When it is on, a plugin file is only loaded if its
+//! SHA-256 matches the hash the user approved for that path. New or changed
+//! files wait for the user's decision, and rejected files are never loaded.
+//! Decisions are saved in `plugin_trust.json` in the config directory.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// The user's answer to a "trust this plugin?" prompt.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TrustDecision {
+    Trust,
+    Reject,
+    /// Skip the plugin for this session and ask again next time.
+    Later,
+}
+
+/// How the allowlist rates a plugin file.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Verdict {
+    Trusted,
+    Rejected,
+    /// Never seen, or changed since it was approved or rejected.
+    Unknown,
+}
+
+/// A plugin file that was not loaded because it is not trusted.
+#[derive(Clone, Debug)]
+pub struct UntrustedPlugin {
+    pub path: PathBuf,
+    /// SHA-256 of the file, lowercase hex.
+    pub sha256: String,
+    /// Modification time when the hash was taken.
+    pub modified: Option<SystemTime>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct TrustStore {
+    /// Only load plugins on the allowlist.
+    #[serde(default)]
+    pub enabled: bool,
+    /// Approved plugin files and their SHA-256.
+    #[serde(default)]
+    trusted: BTreeMap<PathBuf, String>,
+    /// Rejected plugin files and their SHA-256.
+    #[serde(default)]
+    rejected: BTreeMap<PathBuf, String>,
+}
+
+impl TrustStore {
+    fn path() -> PathBuf {
+        crate::utils::config_dir().join("plugin_trust.json")
+    }
+
+    pub fn load() -> Self {
+        Self::load_from(&Self::path())
+    }
+
+    fn load_from(path: &Path) -> Self {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(_) => return Self::default(),
+        };
+        match serde_json::from_str(&text) {
+            Ok(store) => store,
+            Err(e) => {
+                // Fail closed: a broken allowlist must not turn trust mode off
+                log::warn!("Invalid plugin_trust.json, no plugin is trusted: {}", e);
+                Self {
+                    enabled: true,
+                    ..Self::default()
+                }
+            }
+        }
+    }
+
+    pub fn save(&self) -> std::result::Result<(), Box<dyn std::error::Error>> {
+        std::fs::write(Self::path(), serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn verdict(&self, path: &Path, sha256: &str) -> Verdict {
+        let key = Self::key(path);
+        if self.trusted.get(&key).is_some_and(|h| h == sha256) {
+            Verdict::Trusted
+        } else if self.rejected.get(&key).is_some_and(|h| h == sha256) {
+            Verdict::Rejected
+        } else {
+            Verdict::Unknown
+        }
+    }
+
+    pub fn trust(&mut self, path: &Path, sha256: &str) {
+        let key = Self::key(path);
+        self.rejected.remove(&key);
+        self.trusted.insert(key, sha256.to_string());
+    }
+
+    pub fn reject(&mut self, path: &Path, sha256: &str) {
+        let key = Self::key(path);
+        self.trusted.remove(&key);
+        self.rejected.insert(key, sha256.to_string());
+    }
+
+    /// Entries are keyed by canonical path, so the same file reached through
+    /// different search paths shares its decision.
+    fn key(path: &Path) -> PathBuf {
+        std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+    }
+}
+
+/// SHA-256 of a file's contents, lowercase hex.
+pub fn file_sha256(path: &Path) -> std::io::Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hex(&hasher.finalize()))
+}
+
+/// SHA-256 of `bytes`, lowercase hex.
+pub fn sha256(bytes: &[u8]) -> String {
+    hex(&Sha256::digest(bytes))
+}
+
+fn hex(hash: &[u8]) -> String {
+    hash.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_dir;
+
+    #[test]
+    fn verdict_follows_the_recorded_hash() {
+        let dir = test_dir("trust-verdict");
+        let (a, b) = (dir.join("a.so"), dir.join("b.so"));
+        let mut store = TrustStore::default();
+        assert_eq!(store.verdict(&a, "1111"), Verdict::Unknown);
+
+        store.trust(&a, "1111");
+        store.reject(&b, "2222");
+        assert_eq!(store.verdict(&a, "1111"), Verdict::Trusted);
+        assert_eq!(store.verdict(&b, "2222"), Verdict::Rejected);
+        // A changed file is neither
+        assert_eq!(store.verdict(&a, "3333"), Verdict::Unknown);
+        assert_eq!(store.verdict(&b, "3333"), Verdict::Unknown);
+
+        // A later decision replaces the earlier one
+        store.trust(&b, "2222");
+        assert_eq!(store.verdict(&b, "2222"), Verdict::Trusted);
+        store.reject(&a, "1111");
+        assert_eq!(store.verdict(&a, "1111"), Verdict::Rejected);
+    }
+
+    #[test]
+    fn entries_are_keyed_by_canonical_path() {
+        let dir = test_dir("trust-key");
+        std::fs::create_dir(dir.join("sub")).unwrap();
+        let plugin = dir.join("plugin.so");
+        std::fs::write(&plugin, "").unwrap();
+
+        let mut store = TrustStore::default();
+        store.trust(&dir.join("sub").join("..").join("plugin.so"), "1111");
+        assert_eq!(store.verdict(&plugin, "1111"), Verdict::Trusted);
+        let canonical = std::fs::canonicalize(&plugin).unwrap();
+        assert_eq!(store.trusted.keys().collect::<Vec<_>>(), [&canonical]);
+    }
+
+    #[test]
+    fn invalid_allowlist_keeps_trust_mode_on() {
+        let dir = test_dir("trust-load");
+        let path = dir.join("plugin_trust.json");
+        assert!(!TrustStore::load_from(&path).enabled);
+
+        std::fs::write(&path, "{ not json").unwrap();
+        let store = TrustStore::load_from(&path);
+        assert!(store.enabled);
+        assert!(store.trusted.is_empty());
+    }
+
+    #[test]
+    fn hashes_are_lowercase_hex() {
+        let dir = test_dir("trust-hash");
+        let path = dir.join("plugin.so");
+        std::fs::write(&path, "abc").unwrap();
+        let expected = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+        assert_eq!(sha256(b"abc"), expected);
+        assert_eq!(file_sha256(&path).unwrap(), expected);
+    }
+}
@@ -2,6 +2,6 @@ mod menu;
 mod status_bar;
 mod tabs;
 
-pub use menu::{menu_bar, MenuAction};
+pub use menu::{menu_bar, MenuAction, MenuState};
 pub use status_bar::{status_bar, StatusBarAction};
 pub use tabs::{tab_bar, TabAction};
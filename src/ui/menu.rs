@@ -1,12 +1,15 @@
+use crate::commands::CommandRegistry;
 use crate::plugin::PluginManager;
 use egui::Ui;
 use notos_sdk::{EditorContext, PluginAction};
 
+#[derive(Clone, PartialEq)]
 pub enum MenuAction {
     NewTab,
     Open,
     Save,
     SaveAs,
+    CloseTab,
     Exit,
     Undo,
     Redo,
@@ -16,6 +19,7 @@ pub enum MenuAction {
     TimeDate,
     SelectAll,
     ToggleWordWrap,
+    ToggleFollow,
     ToggleLineNumbers,
     ToggleDarkMode,
     ZoomIn,
@@ -25,19 +29,59 @@ pub enum MenuAction {
     LoadFont,
     OpenRecent(std::path::PathBuf),
     ClearHistory,
+    CommandPalette,
+    KeyboardShortcuts,
+    TogglePanel(String),
+    Settings,
+    RunScript(String),
+    CancelScript,
+    NewScript,
+    ReloadScripts,
+    ToggleMacroRecording,
+    PlayMacro,
+    PlayMacroTimes,
+    PlayMacroToEnd,
+    SaveMacro,
+    PlaySavedMacro(String),
+    DeleteMacro(String),
+    TogglePluginTrust,
+    TrustPlugin(std::path::PathBuf),
+}
+
+/// Editor state the menus show: shortcuts, view toggles, panels, scripts and macros.
+pub struct MenuState<'a> {
+    pub commands: &'a CommandRegistry,
+    pub word_wrap: bool,
+    pub show_line_numbers: bool,
+    pub dark_mode: bool,
+    /// Whether the active tab follows its file; `None` when it cannot.
+    pub following: Option<bool>,
+    /// Plugin panels as `(key, title, visible)`.
+    pub panels: &'a [(String, String, bool)],
+    /// Title of the script that is running, if any.
+    pub running_script: Option<&'a str>,
+    pub macros: &'a crate::macros::MacroMenu,
 }
 
 pub fn menu_bar(
     ui: &mut Ui,
     plugin_manager: &mut PluginManager,
-    word_wrap: &mut bool,
-    show_line_numbers: &mut bool,
-    dark_mode: &mut bool,
+    state: MenuState,
     editor_font_family: &str,
     custom_fonts: &std::collections::HashMap<String, Vec<u8>>,
     recent_files: &[std::path::PathBuf],
     ed_ctx: &EditorContext,
 ) -> (Option<MenuAction>, PluginAction) {
+    let MenuState {
+        commands,
+        word_wrap,
+        show_line_numbers,
+        dark_mode,
+        following,
+        panels,
+        running_script,
+        macros,
+    } = state;
     let mut action = None;
     let mut plugin_action = PluginAction::None;
 
@@ -46,28 +90,38 @@ pub fn menu_bar(
         ui.spacing_mut().interact_size.y = 20.0; // Fixed height for all menu buttons
         ui.menu_button("File", |ui| {
             if ui
-                .add(egui::Button::new("📄 New Tab").shortcut_text("Ctrl+T"))
+                .add(
+                    egui::Button::new("📄 New Tab")
+                        .shortcut_text(commands.shortcut_text("file.new")),
+                )
                 .clicked()
             {
                 action = Some(MenuAction::NewTab);
                 ui.close_menu();
             }
             if ui
-                .add(egui::Button::new("📂 Open").shortcut_text("Ctrl+O"))
+                .add(
+                    egui::Button::new("📂 Open").shortcut_text(commands.shortcut_text("file.open")),
+                )
                 .clicked()
             {
                 action = Some(MenuAction::Open);
                 ui.close_menu();
             }
             if ui
-                .add(egui::Button::new("💾 Save").shortcut_text("Ctrl+S"))
+                .add(
+                    egui::Button::new("💾 Save").shortcut_text(commands.shortcut_text("file.save")),
+                )
                 .clicked()
             {
                 action = Some(MenuAction::Save);
                 ui.close_menu();
             }
             if ui
-                .add(egui::Button::new("💾 Save As").shortcut_text("Ctrl+Shift+S"))
+                .add(
+                    egui::Button::new("💾 Save As")
+                        .shortcut_text(commands.shortcut_text("file.save_as")),
+                )
                 .clicked()
             {
                 action = Some(MenuAction::SaveAs);
@@ -101,6 +155,28 @@ pub fn menu_bar(
                 }
             });
 
+            ui.separator();
+            if ui
+                .add(
+                    egui::Button::new("✖ Close Tab")
+                        .shortcut_text(commands.shortcut_text("file.close_tab")),
+                )
+                .clicked()
+            {
+                action = Some(MenuAction::CloseTab);
+                ui.close_menu();
+            }
+            ui.separator();
+            if ui
+                .add(
+                    egui::Button::new("⚙ Settings")
+                        .shortcut_text(commands.shortcut_text("app.settings")),
+                )
+                .clicked()
+            {
+                action = Some(MenuAction::Settings);
+                ui.close_menu();
+            }
             ui.separator();
             if ui.button("🚪 Exit").clicked() {
                 action = Some(MenuAction::Exit);
@@ -109,14 +185,14 @@ pub fn menu_bar(
 
         ui.menu_button("Edit", |ui| {
             if ui
-                .add(egui::Button::new("↩ Undo").shortcut_text("Ctrl+Z"))
+                .add(egui::Button::new("↩ Undo").shortcut_text(commands.shortcut_text("edit.undo")))
                 .clicked()
             {
                 action = Some(MenuAction::Undo);
                 ui.close_menu();
             }
             if ui
-                .add(egui::Button::new("↪ Redo").shortcut_text("Ctrl+Y"))
+                .add(egui::Button::new("↪ Redo").shortcut_text(commands.shortcut_text("edit.redo")))
                 .clicked()
             {
                 action = Some(MenuAction::Redo);
@@ -124,64 +200,211 @@ pub fn menu_bar(
             }
             ui.separator();
             if ui
-                .add(egui::Button::new("🔍 Find").shortcut_text("Ctrl+F"))
+                .add(
+                    egui::Button::new("🔍 Find").shortcut_text(commands.shortcut_text("edit.find")),
+                )
                 .clicked()
             {
                 action = Some(MenuAction::Find);
                 ui.close_menu();
             }
             if ui
-                .add(egui::Button::new("🔄 Replace").shortcut_text("Ctrl+H"))
+                .add(
+                    egui::Button::new("🔄 Replace")
+                        .shortcut_text(commands.shortcut_text("edit.replace")),
+                )
                 .clicked()
             {
                 action = Some(MenuAction::Replace);
                 ui.close_menu();
             }
             if ui
-                .add(egui::Button::new("🎯 Go To...").shortcut_text("Ctrl+G"))
+                .add(
+                    egui::Button::new("🎯 Go To...")
+                        .shortcut_text(commands.shortcut_text("edit.goto_line")),
+                )
                 .clicked()
             {
                 action = Some(MenuAction::GotoLine);
                 ui.close_menu();
             }
             ui.separator();
-            if ui.button("📅 Time/Date  F5").clicked() {
+            if ui
+                .add(
+                    egui::Button::new("📅 Time/Date")
+                        .shortcut_text(commands.shortcut_text("edit.insert_date_time")),
+                )
+                .clicked()
+            {
                 action = Some(MenuAction::TimeDate);
                 ui.close_menu();
             }
             if ui
-                .add(egui::Button::new("✅ Select All").shortcut_text("Ctrl+A"))
+                .add(
+                    egui::Button::new("✅ Select All")
+                        .shortcut_text(commands.shortcut_text("edit.select_all")),
+                )
                 .clicked()
             {
                 action = Some(MenuAction::SelectAll);
                 ui.close_menu();
             }
+
+            ui.separator();
+            ui.menu_button("⏺ Macros", |ui| {
+                let record_label = if macros.recording {
+                    "⏹ Stop Recording"
+                } else {
+                    "⏺ Start Recording"
+                };
+                if ui
+                    .add_enabled(
+                        !macros.playing,
+                        egui::Button::new(record_label)
+                            .shortcut_text(commands.shortcut_text("macro.toggle_record")),
+                    )
+                    .clicked()
+                {
+                    action = Some(MenuAction::ToggleMacroRecording);
+                    ui.close_menu();
+                }
+                let can_play = macros.has_macro && !macros.recording && !macros.playing;
+                let entries = [
+                    ("▶ Play", "macro.play", MenuAction::PlayMacro),
+                    (
+                        "🔁 Play N Times...",
+                        "macro.play_times",
+                        MenuAction::PlayMacroTimes,
+                    ),
+                    (
+                        "⏩ Play to End of File",
+                        "macro.play_to_end",
+                        MenuAction::PlayMacroToEnd,
+                    ),
+                    ("💾 Save Macro As...", "macro.save", MenuAction::SaveMacro),
+                ];
+                for (label, id, menu_action) in entries {
+                    if ui
+                        .add_enabled(
+                            can_play,
+                            egui::Button::new(label).shortcut_text(commands.shortcut_text(id)),
+                        )
+                        .clicked()
+                    {
+                        action = Some(menu_action);
+                        ui.close_menu();
+                    }
+                }
+                if !macros.saved.is_empty() {
+                    ui.separator();
+                    for name in &macros.saved {
+                        ui.menu_button(name, |ui| {
+                            if ui
+                                .add_enabled(
+                                    !macros.recording && !macros.playing,
+                                    egui::Button::new("▶ Play"),
+                                )
+                                .clicked()
+                            {
+                                action = Some(MenuAction::PlaySavedMacro(name.clone()));
+                                ui.close_menu();
+                            }
+                            if ui.button("🗑 Delete").clicked() {
+                                action = Some(MenuAction::DeleteMacro(name.clone()));
+                                ui.close_menu();
+                            }
+                        });
+                    }
+                }
+            });
         });
 
         ui.menu_button("View", |ui| {
-            if ui.checkbox(word_wrap, "Wrap Word").clicked() {
+            if ui
+                .add(
+                    egui::Button::new("🎛 Command Palette")
+                        .shortcut_text(commands.shortcut_text("view.command_palette")),
+                )
+                .clicked()
+            {
+                action = Some(MenuAction::CommandPalette);
+                ui.close_menu();
+            }
+            if ui
+                .add(
+                    egui::Button::new("⌨ Keyboard Shortcuts")
+                        .shortcut_text(commands.shortcut_text("view.keyboard_shortcuts")),
+                )
+                .clicked()
+            {
+                action = Some(MenuAction::KeyboardShortcuts);
+                ui.close_menu();
+            }
+            ui.separator();
+            // The handlers flip the flags, so the checkboxes only work on copies
+            if ui.checkbox(&mut { word_wrap }, "Wrap Word").clicked() {
                 action = Some(MenuAction::ToggleWordWrap);
                 ui.close_menu();
             }
             if ui
-                .checkbox(show_line_numbers, "🔢 Show Line Number")
+                .checkbox(&mut { show_line_numbers }, "🔢 Show Line Number")
                 .clicked()
             {
                 action = Some(MenuAction::ToggleLineNumbers);
                 ui.close_menu();
             }
-            if ui.checkbox(dark_mode, "🌙 Dark Mode").clicked() {
+            if ui.checkbox(&mut { dark_mode }, "🌙 Dark Mode").clicked() {
                 action = Some(MenuAction::ToggleDarkMode);
                 ui.close_menu();
             }
+            // Only for tabs with a file and no unsaved changes
+            if ui
+                .add_enabled(
+                    following.is_some(),
+                    egui::Checkbox::new(&mut following.unwrap_or(false), "⏬ Follow File"),
+                )
+                .on_hover_text("Keep reading the file as it grows, like tail -f")
+                .clicked()
+            {
+                action = Some(MenuAction::ToggleFollow);
+                ui.close_menu();
+            }
+            if !panels.is_empty() {
+                ui.menu_button("🗔 Panels", |ui| {
+                    for (key, title, visible) in panels {
+                        if ui.checkbox(&mut { *visible }, title).clicked() {
+                            action = Some(MenuAction::TogglePanel(key.clone()));
+                            ui.close_menu();
+                        }
+                    }
+                });
+            }
             ui.separator();
-            if ui.button("➕ Zoom In").clicked() {
+            if ui
+                .add(
+                    egui::Button::new("➕ Zoom In")
+                        .shortcut_text(commands.shortcut_text("view.zoom_in")),
+                )
+                .clicked()
+            {
                 action = Some(MenuAction::ZoomIn);
             }
-            if ui.button("➖ Zoom Out").clicked() {
+            if ui
+                .add(
+                    egui::Button::new("➖ Zoom Out")
+                        .shortcut_text(commands.shortcut_text("view.zoom_out")),
+                )
+                .clicked()
+            {
                 action = Some(MenuAction::ZoomOut);
             }
-            if ui.button("🔄 Reset Zoom").clicked() {
+            if ui
+                .add(
+                    egui::Button::new("🔄 Reset Zoom")
+                        .shortcut_text(commands.shortcut_text("view.reset_zoom")),
+                )
+                .clicked()
+            {
                 action = Some(MenuAction::ResetZoom);
             }
             ui.separator();
@@ -237,6 +460,78 @@ pub fn menu_bar(
             if p_action != PluginAction::None {
                 plugin_action = p_action;
             }
+
+            ui.separator();
+            let mut trust = plugin_manager.trust_enabled();
+            if ui
+                .checkbox(&mut trust, "🛡 Require Approval for Plugins")
+                .on_hover_text("Only load plugin files whose SHA-256 you approved")
+                .clicked()
+            {
+                action = Some(MenuAction::TogglePluginTrust);
+                ui.close_menu();
+            }
+            let rejected = plugin_manager.rejected_plugins();
+            if trust && !rejected.is_empty() {
+                ui.menu_button(format!("⛔ Rejected Plugins ({})", rejected.len()), |ui| {
+                    for plugin in rejected {
+                        let name = plugin.path.file_name().unwrap_or_default();
+                        if ui
+                            .button(format!("Trust {}", name.to_string_lossy()))
+                            .on_hover_text(format!(
+                                "{}\nSHA-256 {}",
+                                plugin.path.display(),
+                                plugin.sha256
+                            ))
+                            .clicked()
+                        {
+                            action = Some(MenuAction::TrustPlugin(plugin.path.clone()));
+                            ui.close_menu();
+                        }
+                    }
+                });
+            }
+        });
+
+        ui.menu_button("Scripts", |ui| {
+            let mut any = false;
+            for cmd in commands.iter() {
+                let Some(script_id) = cmd.id.strip_prefix(crate::scripting::SCRIPT_COMMAND_PREFIX)
+                else {
+                    continue;
+                };
+                any = true;
+                let title = cmd.title.strip_prefix("Script: ").unwrap_or(&cmd.title);
+                if ui
+                    .add_enabled(
+                        running_script.is_none(),
+                        egui::Button::new(format!("📜 {}", title))
+                            .shortcut_text(commands.shortcut_text(&cmd.id)),
+                    )
+                    .clicked()
+                {
+                    action = Some(MenuAction::RunScript(script_id.to_string()));
+                    ui.close_menu();
+                }
+            }
+            if !any {
+                ui.label("No scripts yet");
+            }
+            ui.separator();
+            if let Some(title) = running_script {
+                if ui.button(format!("⏹ Cancel \"{}\"", title)).clicked() {
+                    action = Some(MenuAction::CancelScript);
+                    ui.close_menu();
+                }
+            }
+            if ui.button("📄 New Script...").clicked() {
+                action = Some(MenuAction::NewScript);
+                ui.close_menu();
+            }
+            if ui.button("⟳ Reload Scripts").clicked() {
+                action = Some(MenuAction::ReloadScripts);
+                ui.close_menu();
+            }
         });
 
         // Other plugin menu extensions (e.g., custom menus like Help)
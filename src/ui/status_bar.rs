@@ -1,21 +1,31 @@
 use crate::editor::EditorTab;
+use crate::plugin::PluginManager;
 use egui::Ui;
+use notos_sdk::{EditorContext, JobStatus, PluginAction, StatusBarAlignment, StatusBarItem};
 
 pub enum StatusBarAction {
     SwitchTab(crate::editor::TabId),
     CloseTab(crate::editor::TabId),
     SetLineEnding(crate::editor::TabId, crate::editor::LineEnding),
     SetEncoding(crate::editor::TabId, crate::editor::Encoding),
+    CancelJob(notos_sdk::JobId),
+    ToggleFollow(crate::editor::TabId),
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn status_bar(
     ui: &mut Ui,
+    plugin_manager: &mut PluginManager,
+    ed_ctx: &EditorContext,
     tabs: &[EditorTab],
     active_tab_id: Option<crate::editor::TabId>,
     cursor_pos: (usize, usize),
     zoom_level: f32,
-) -> Option<StatusBarAction> {
+    notice: Option<&str>,
+    following: bool,
+) -> (Option<StatusBarAction>, PluginAction) {
     let mut action = None;
+    let mut plugin_action = PluginAction::None;
     ui.with_layout(egui::Layout::left_to_right(egui::Align::Center), |ui| {
         let active_tab_index = tabs.iter().position(|t| Some(t.id) == active_tab_id);
 
@@ -31,6 +41,29 @@ pub fn status_bar(
             ui.label(format!("{} characters", chars));
             ui.separator();
 
+            if let Some(notice) = notice {
+                ui.label(egui::RichText::new(notice).color(ui.visuals().weak_text_color()));
+                ui.separator();
+            }
+
+            for job in plugin_manager.running_jobs() {
+                if job_item(ui, &job) {
+                    action = Some(StatusBarAction::CancelJob(job.id));
+                }
+                ui.separator();
+            }
+
+            let plugin_items = plugin_manager.status_bar_items(ed_ctx);
+            for (plugin_id, item) in &plugin_items {
+                if item.alignment == StatusBarAlignment::Left {
+                    let a = plugin_item(ui, plugin_manager, plugin_id, item, ed_ctx);
+                    if a != PluginAction::None {
+                        plugin_action = a;
+                    }
+                    ui.separator();
+                }
+            }
+
             ui.menu_button(format!("Tabs: {}", tabs.len()), |ui| {
                 ui.set_width(220.0);
                 ui.style_mut().wrap_mode = Some(egui::TextWrapMode::Truncate);
@@ -69,68 +102,152 @@ pub fn status_bar(
             // Right side items
             ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                 if let Some(tab) = tabs.get(index) {
-                    ui.menu_button(tab.encoding.name(), |ui| {
-                        if ui.button("UTF-8").clicked() {
-                            action = Some(StatusBarAction::SetEncoding(
-                                tab.id,
-                                crate::editor::Encoding::Utf8,
-                            ));
-                            ui.close_menu();
-                        }
-                        if ui.button("Windows-1252").clicked() {
-                            action = Some(StatusBarAction::SetEncoding(
-                                tab.id,
-                                crate::editor::Encoding::Windows1252,
-                            ));
-                            ui.close_menu();
-                        }
-                        if ui.button("UTF-16LE").clicked() {
-                            action = Some(StatusBarAction::SetEncoding(
-                                tab.id,
-                                crate::editor::Encoding::Utf16Le,
-                            ));
-                            ui.close_menu();
-                        }
-                        if ui.button("UTF-16BE").clicked() {
-                            action = Some(StatusBarAction::SetEncoding(
-                                tab.id,
-                                crate::editor::Encoding::Utf16Be,
-                            ));
-                            ui.close_menu();
-                        }
-                    });
+                    // Converting a read-only tab would make it dirty
+                    ui.add_enabled_ui(!tab.read_only, |ui| {
+                        ui.menu_button(tab.encoding.name(), |ui| {
+                            if ui.button("UTF-8").clicked() {
+                                action = Some(StatusBarAction::SetEncoding(
+                                    tab.id,
+                                    crate::editor::Encoding::Utf8,
+                                ));
+                                ui.close_menu();
+                            }
+                            if ui.button("Windows-1252").clicked() {
+                                action = Some(StatusBarAction::SetEncoding(
+                                    tab.id,
+                                    crate::editor::Encoding::Windows1252,
+                                ));
+                                ui.close_menu();
+                            }
+                            if ui.button("UTF-16LE").clicked() {
+                                action = Some(StatusBarAction::SetEncoding(
+                                    tab.id,
+                                    crate::editor::Encoding::Utf16Le,
+                                ));
+                                ui.close_menu();
+                            }
+                            if ui.button("UTF-16BE").clicked() {
+                                action = Some(StatusBarAction::SetEncoding(
+                                    tab.id,
+                                    crate::editor::Encoding::Utf16Be,
+                                ));
+                                ui.close_menu();
+                            }
+                        });
 
-                    ui.separator();
+                        ui.separator();
 
-                    ui.menu_button(tab.line_ending.name(), |ui| {
-                        if ui.button("Windows (CRLF)").clicked() {
-                            action = Some(StatusBarAction::SetLineEnding(
-                                tab.id,
-                                crate::editor::LineEnding::Crlf,
-                            ));
-                            ui.close_menu();
-                        }
-                        if ui.button("Unix (LF)").clicked() {
-                            action = Some(StatusBarAction::SetLineEnding(
-                                tab.id,
-                                crate::editor::LineEnding::Lf,
-                            ));
-                            ui.close_menu();
-                        }
-                        if ui.button("Mac (CR)").clicked() {
-                            action = Some(StatusBarAction::SetLineEnding(
-                                tab.id,
-                                crate::editor::LineEnding::Cr,
-                            ));
-                            ui.close_menu();
-                        }
+                        ui.menu_button(tab.line_ending.name(), |ui| {
+                            if ui.button("Windows (CRLF)").clicked() {
+                                action = Some(StatusBarAction::SetLineEnding(
+                                    tab.id,
+                                    crate::editor::LineEnding::Crlf,
+                                ));
+                                ui.close_menu();
+                            }
+                            if ui.button("Unix (LF)").clicked() {
+                                action = Some(StatusBarAction::SetLineEnding(
+                                    tab.id,
+                                    crate::editor::LineEnding::Lf,
+                                ));
+                                ui.close_menu();
+                            }
+                            if ui.button("Mac (CR)").clicked() {
+                                action = Some(StatusBarAction::SetLineEnding(
+                                    tab.id,
+                                    crate::editor::LineEnding::Cr,
+                                ));
+                                ui.close_menu();
+                            }
+                        });
                     });
-
                     ui.separator();
                     ui.label(format!("{:.0}%", (zoom_level / 14.0) * 100.0));
+
+                    if following {
+                        ui.separator();
+                        if ui
+                            .selectable_label(true, "⏬ Following")
+                            .on_hover_text("Stop following the file")
+                            .clicked()
+                        {
+                            action = Some(StatusBarAction::ToggleFollow(tab.id));
+                        }
+                    } else if tab.read_only {
+                        ui.separator();
+                        ui.label("🔒 Read-only");
+                    }
+
+                    for (plugin_id, item) in &plugin_items {
+                        if item.alignment == StatusBarAlignment::Right {
+                            ui.separator();
+                            let a = plugin_item(ui, plugin_manager, plugin_id, item, ed_ctx);
+                            if a != PluginAction::None {
+                                plugin_action = a;
+                            }
+                        }
+                    }
                 }
             });
         }
     });
-    action
+    (action, plugin_action)
+}
+
+/// Draw one plugin-contributed segment and forward clicks to its plugin.
+fn plugin_item(
+    ui: &mut Ui,
+    plugin_manager: &mut PluginManager,
+    plugin_id: &str,
+    item: &StatusBarItem,
+    ed_ctx: &EditorContext,
+) -> PluginAction {
+    let mut text = egui::RichText::new(&item.text);
+    if let Some(color) = item.color {
+        text = text.color(color);
+    }
+
+    if item.has_popup {
+        let res = ui.menu_button(text, |ui| {
+            plugin_manager.status_bar_popup_ui(plugin_id, &item.id, ui, ed_ctx)
+        });
+        if let Some(tooltip) = &item.tooltip {
+            res.response.on_hover_text(tooltip);
+        }
+        res.inner.unwrap_or(PluginAction::None)
+    } else {
+        let mut res = ui.add(egui::Label::new(text).sense(egui::Sense::click()));
+        if let Some(tooltip) = &item.tooltip {
+            res = res.on_hover_text(tooltip);
+        }
+        if res.clicked() {
+            plugin_manager.on_status_bar_click(plugin_id, &item.id, ed_ctx)
+        } else {
+            PluginAction::None
+        }
+    }
+}
+
+/// Progress of a running plugin job. Returns `true` when the user cancels it.
+fn job_item(ui: &mut Ui, job: &JobStatus) -> bool {
+    let res = match job.progress.fraction {
+        Some(fraction) => ui.add(
+            egui::ProgressBar::new(fraction)
+                .desired_width(80.0)
+                .desired_height(12.0)
+                .show_percentage(),
+        ),
+        None => ui.add(egui::Spinner::new().size(12.0)),
+    };
+    let label = if job.progress.message.is_empty() {
+        job.title.clone()
+    } else {
+        format!("{}: {}", job.title, job.progress.message)
+    };
+    ui.add(egui::Label::new(label).truncate())
+        .union(res)
+        .on_hover_text(&job.title);
+    ui.add_enabled(!job.cancelled, egui::Button::new("✕").small())
+        .on_hover_text("Cancel")
+        .clicked()
 }
@@ -172,6 +172,8 @@ pub struct UndoManager {
     dir: PathBuf,
     tx: mpsc::Sender<BgTask>,
     handle: Option<std::thread::JoinHandle<()>>,
+    /// Tabs whose edits are grouped into one step, and whether that step was recorded yet.
+    groups: HashMap<usize, bool>,
 }
 
 impl UndoManager {
@@ -186,7 +188,7 @@ impl UndoManager {
             .spawn(move || Self::worker(rx))
             .expect("spawn undo-io thread");
 
-        let mut mgr = Self { tabs: HashMap::new(), dir: dir.clone(), tx, handle: Some(handle) };
+        let mut mgr = Self { tabs: HashMap::new(), dir: dir.clone(), tx, handle: Some(handle), groups: HashMap::new() };
 
         if let Some(s) = state {
             for (id, ts) in s.tabs {
@@ -223,6 +225,11 @@ impl UndoManager {
     /// Record an undo snapshot.  Clears the redo stack (new edit).
     pub fn push_undo(&mut self, id: TabId, content: String, cursor_pos: usize, large: bool) {
         if large { return; }
+        if let Some(recorded) = self.groups.get_mut(&id.0) {
+            // Only the state before the group's first edit is kept
+            if *recorded { return; }
+            *recorded = true;
+        }
         let s = self.tabs.entry(id.0).or_insert_with(TabState::new);
         s.redo.clear(&self.tx);
         s.undo.push(UndoEntry { content, cursor_pos });
@@ -231,6 +238,15 @@ impl UndoManager {
         s.undo.evict(MAX_UNDO_STEPS, &self.tx);
     }
 
+    /// Start grouping: until `end_group`, all edits to the tab undo as a single step.
+    pub fn begin_group(&mut self, id: TabId) {
+        self.groups.insert(id.0, false);
+    }
+
+    pub fn end_group(&mut self, id: TabId) {
+        self.groups.remove(&id.0);
+    }
+
     /// Undo: returns entry to restore, or `None`.
     pub fn undo(&mut self, id: TabId, current: String, current_cursor: usize) -> Option<UndoEntry> {
         let s = self.tabs.get_mut(&id.0)?;
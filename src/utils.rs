@@ -13,3 +13,49 @@ pub fn load_icon() -> IconData {
         height,
     }
 }
+
+/// Per-user configuration directory (`%APPDATA%\Notos`, `~/Library/Application
+/// Support/Notos` or `$XDG_CONFIG_HOME/notos`). Created on first use.
+pub fn config_dir() -> std::path::PathBuf {
+    let home = || std::env::var_os("HOME").map(std::path::PathBuf::from);
+    let dir = if cfg!(windows) {
+        std::env::var_os("APPDATA").map(|d| std::path::PathBuf::from(d).join("Notos"))
+    } else if cfg!(target_os = "macos") {
+        home().map(|h| h.join("Library/Application Support/Notos"))
+    } else {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .map(std::path::PathBuf::from)
+            .or_else(|| home().map(|h| h.join(".config")))
+            .map(|d| d.join("notos"))
+    }
+    .unwrap_or_else(|| std::env::temp_dir().join("notos"));
+    let _ = std::fs::create_dir_all(&dir);
+    dir
+}
+
+/// Let output reach the console the program was started from. On Windows it
+/// is a GUI program without a console of its own, so output would go nowhere.
+#[cfg(windows)]
+pub fn attach_console() {
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn AttachConsole(process_id: u32) -> i32;
+    }
+    // Fails when not started from a console, and then there is nowhere to print
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+#[cfg(not(windows))]
+pub fn attach_console() {}
+
+/// An empty directory of its own for test `name`, in the system temp directory.
+#[cfg(test)]
+pub fn test_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("notos-test-{}-{}", std::process::id(), name));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
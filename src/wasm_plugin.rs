@@ -0,0 +1,533 @@
+//! Sandboxed WebAssembly plugin backend.
+//!
+//! `.wasm` plugins are components implementing the `plugin` world of
+//! `notos_sdk/wit/notos-plugin.wit`. Each one is wrapped in a [`WasmPlugin`],
+//! which implements `NotosPlugin` on the host side so the plugin manager can
+//! treat it like a native plugin.
+//!
+//! The guest gets no WASI: its only way out is the `host` interface. File
+//! access is refused unless the user lists directories in the plugin's
+//! "Read access" / "Write access" settings, and every call into the guest runs
+//! with a fuel budget, a wall-clock deadline and a memory cap. A plugin that
+//! traps (including running out of fuel or time) is disabled until restart.
+
+use notos_sdk::{
+    EditorContext, HostServices, NotosPlugin, PluginAction, PluginCommand, SettingSpec,
+    SettingsHandle, TextEdit,
+};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use wasmtime::component::{Component, HasSelf, Linker};
+use wasmtime::{Config, Engine, Store, StoreLimits, StoreLimitsBuilder};
+
+wasmtime::component::bindgen!({
+    path: "notos_sdk/wit",
+    world: "plugin",
+});
+
+use notos::plugin::types::LogLevel;
+
+/// Instructions (roughly) a single call into a plugin may execute.
+const FUEL_PER_CALL: u64 = 500_000_000;
+
+/// Granularity of the wall-clock limit.
+const EPOCH_TICK: Duration = Duration::from_millis(10);
+
+/// Wall-clock limit of a single call, in epoch ticks (500 ms).
+const DEADLINE_TICKS: u64 = 50;
+
+/// Maximum linear memory of a plugin instance.
+const MAX_MEMORY: usize = 64 * 1024 * 1024;
+
+/// Prefix of the command ids generated for the plugin's text transforms.
+const TRANSFORM_PREFIX: &str = "transform:";
+
+/// Key of a decoration lookup: (content ptr, content len, hovered byte).
+type HoverKey = (usize, usize, usize);
+
+/// Shared engine for all WebAssembly plugins.
+pub struct WasmRuntime {
+    engine: Engine,
+    linker: Linker<HostState>,
+    /// Stops the epoch ticker thread when the runtime is dropped.
+    ticker_stop: Arc<AtomicBool>,
+}
+
+impl WasmRuntime {
+    pub fn new() -> wasmtime::Result<Self> {
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        config.epoch_interruption(true);
+        let engine = Engine::new(&config)?;
+
+        let mut linker = Linker::new(&engine);
+        Plugin::add_to_linker::<_, HasSelf<_>>(&mut linker, |state| state)?;
+
+        let ticker_stop = Arc::new(AtomicBool::new(false));
+        let ticker_engine = engine.clone();
+        let stop = ticker_stop.clone();
+        std::thread::spawn(move || {
+            while !stop.load(Ordering::Relaxed) {
+                std::thread::sleep(EPOCH_TICK);
+                ticker_engine.increment_epoch();
+            }
+        });
+
+        Ok(Self {
+            engine,
+            linker,
+            ticker_stop,
+        })
+    }
+
+    /// Compile and instantiate the component at `path`.
+    pub fn load(&self, path: &Path) -> wasmtime::Result<WasmPlugin> {
+        let component = Component::from_file(&self.engine, path)?;
+        let label = path.file_name().unwrap_or_default().to_string_lossy();
+        let mut store = self.new_store(label.into_owned());
+        arm(&mut store)?;
+        let bindings = Plugin::instantiate(&mut store, &component, &self.linker)?;
+
+        arm(&mut store)?;
+        let id = bindings.call_id(&mut store)?;
+        arm(&mut store)?;
+        let name = bindings.call_name(&mut store)?;
+        store.data_mut().label = id.clone();
+
+        // `NotosPlugin::commands` takes `&self`, so the list is fetched once here
+        arm(&mut store)?;
+        let mut commands: Vec<PluginCommand> = bindings
+            .call_commands(&mut store)?
+            .into_iter()
+            .map(|c| {
+                let command = PluginCommand::new(c.id, c.title);
+                match c.keybinding {
+                    Some(keybinding) => command.with_keybinding(keybinding),
+                    None => command,
+                }
+            })
+            .collect();
+        arm(&mut store)?;
+        commands.extend(
+            bindings
+                .call_transforms(&mut store)?
+                .into_iter()
+                .map(|t| PluginCommand::new(format!("{}{}", TRANSFORM_PREFIX, t.id), t.title)),
+        );
+
+        Ok(WasmPlugin {
+            id,
+            name,
+            store,
+            bindings,
+            commands,
+            faulted: None,
+            hover_cache: None,
+        })
+    }
+
+    /// A store for one plugin instance, with its memory capped.
+    fn new_store(&self, label: String) -> Store<HostState> {
+        let state = HostState {
+            label,
+            settings: SettingsHandle::default(),
+            limits: StoreLimitsBuilder::new().memory_size(MAX_MEMORY).build(),
+        };
+        let mut store = Store::new(&self.engine, state);
+        store.limiter(|state| &mut state.limits);
+        store
+    }
+}
+
+impl Drop for WasmRuntime {
+    fn drop(&mut self) {
+        self.ticker_stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Refill the fuel and wall-clock budget before a call into the guest.
+fn arm(store: &mut Store<HostState>) -> wasmtime::Result<()> {
+    store.set_fuel(FUEL_PER_CALL)?;
+    store.set_epoch_deadline(DEADLINE_TICKS);
+    Ok(())
+}
+
+/// Host-side state of one plugin instance.
+struct HostState {
+    /// Plugin id once known, for log messages.
+    label: String,
+    /// Holds the user's capability grants.
+    settings: SettingsHandle,
+    limits: StoreLimits,
+}
+
+impl HostState {
+    /// Whether `path` lies inside one of the directories listed in setting `key`.
+    fn granted(&self, key: &str, path: &Path) -> bool {
+        // Resolve `..` and symlinks; a file that doesn't exist yet is checked by its directory
+        let resolved = path.canonicalize().ok().or_else(|| {
+            let parent = path.parent()?.canonicalize().ok()?;
+            Some(parent.join(path.file_name()?))
+        });
+        let Some(resolved) = resolved else {
+            return false;
+        };
+        self.settings
+            .get_string(key, "")
+            .split(';')
+            .map(str::trim)
+            .filter(|dir| !dir.is_empty())
+            .filter_map(|dir| PathBuf::from(dir).canonicalize().ok())
+            .any(|dir| resolved.starts_with(dir))
+    }
+}
+
+impl notos::plugin::types::Host for HostState {}
+
+impl notos::plugin::host::Host for HostState {
+    fn log(&mut self, level: LogLevel, message: String) {
+        let level = match level {
+            LogLevel::Error => log::Level::Error,
+            LogLevel::Warn => log::Level::Warn,
+            LogLevel::Info => log::Level::Info,
+            LogLevel::Debug => log::Level::Debug,
+        };
+        log::log!(level, "[{}] {}", self.label, message);
+    }
+
+    fn read_file(&mut self, path: String) -> Result<String, String> {
+        if !self.granted("fs_read", Path::new(&path)) {
+            return Err(format!("permission denied: {}", path));
+        }
+        std::fs::read_to_string(&path).map_err(|e| e.to_string())
+    }
+
+    fn write_file(&mut self, path: String, contents: String) -> Result<(), String> {
+        if !self.granted("fs_write", Path::new(&path)) {
+            return Err(format!("permission denied: {}", path));
+        }
+        std::fs::write(&path, contents).map_err(|e| e.to_string())
+    }
+}
+
+/// A loaded WebAssembly plugin, exposed to the host as a `NotosPlugin`.
+pub struct WasmPlugin {
+    id: String,
+    name: String,
+    store: Store<HostState>,
+    bindings: Plugin,
+    commands: Vec<PluginCommand>,
+    /// Set once the guest trapped; the plugin is not called again.
+    faulted: Option<String>,
+    /// Last decoration lookup and the range it underlined.
+    hover_cache: Option<(HoverKey, Option<(usize, usize)>)>,
+}
+
+impl WasmPlugin {
+    /// Run one call into the guest with a fresh budget. A trap disables the plugin.
+    fn call<R>(
+        &mut self,
+        f: impl FnOnce(&Plugin, &mut Store<HostState>) -> wasmtime::Result<R>,
+    ) -> Option<R> {
+        if self.faulted.is_some() {
+            return None;
+        }
+        let result = arm(&mut self.store).and_then(|_| f(&self.bindings, &mut self.store));
+        match result {
+            Ok(value) => Some(value),
+            Err(e) => {
+                log::error!("WebAssembly plugin {} disabled: {:#}", self.id, e);
+                self.faulted = Some(e.to_string());
+                None
+            }
+        }
+    }
+
+    fn transforms(&mut self) -> Vec<Transform> {
+        self.call(|b, s| b.call_transforms(s)).unwrap_or_default()
+    }
+
+    fn apply_transform(&mut self, id: &str, ed: &EditorContext) -> PluginAction {
+        let selected = selection_bytes(ed)
+            .filter(|(start, end)| start != end)
+            .and_then(|(start, end)| ed.content.get(start..end));
+        let input = selected.unwrap_or(ed.content).to_string();
+        match self.call(|b, s| b.call_transform(s, id, &input)) {
+            Some(Ok(output)) if selected.is_some() => PluginAction::ReplaceSelection(output),
+            Some(Ok(output)) => PluginAction::ReplaceAll(output),
+            Some(Err(e)) => {
+                log::warn!("Transform {} of {} failed: {}", id, self.id, e);
+                PluginAction::None
+            }
+            None => PluginAction::None,
+        }
+    }
+
+    fn run(&mut self, command: &str, ed: &EditorContext) -> PluginAction {
+        if let Some(transform) = command.strip_prefix(TRANSFORM_PREFIX) {
+            return self.apply_transform(transform, ed);
+        }
+        let state = editor_state(ed);
+        match self.call(|b, s| b.call_run_command(s, command, &state)) {
+            Some(action) => convert_action(action),
+            None => PluginAction::None,
+        }
+    }
+}
+
+/// Convert a char-index position of the editor to a byte offset.
+fn byte_offset(content: &str, char_idx: usize) -> usize {
+    content
+        .char_indices()
+        .nth(char_idx)
+        .map_or(content.len(), |(i, _)| i)
+}
+
+fn selection_bytes(ed: &EditorContext) -> Option<(usize, usize)> {
+    let (a, b) = ed.selection?;
+    let (a, b) = (byte_offset(ed.content, a), byte_offset(ed.content, b));
+    Some((a.min(b), a.max(b)))
+}
+
+fn editor_state(ed: &EditorContext) -> EditorState {
+    EditorState {
+        content: ed.content.to_string(),
+        selection: ed.selection.map(|(a, b)| {
+            (
+                byte_offset(ed.content, a) as u32,
+                byte_offset(ed.content, b) as u32,
+            )
+        }),
+        hovered: ed
+            .hovered_char_idx
+            .map(|i| byte_offset(ed.content, i) as u32),
+        file_path: ed.file_path.map(|p| p.to_string_lossy().into_owned()),
+    }
+}
+
+fn convert_action(action: Action) -> PluginAction {
+    match action {
+        Action::None => PluginAction::None,
+        Action::ReplaceAll(text) => PluginAction::ReplaceAll(text),
+        Action::ReplaceSelection(text) => PluginAction::ReplaceSelection(text),
+        Action::ApplyEdits(edits) => PluginAction::ApplyEdits(
+            edits
+                .into_iter()
+                .map(|e| TextEdit::new(e.start as usize, e.end as usize, e.text))
+                .collect(),
+        ),
+    }
+}
+
+impl NotosPlugin for WasmPlugin {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn attach(&mut self, host: HostServices) {
+        self.store.data_mut().settings = host.settings;
+    }
+
+    fn settings_schema(&self) -> Vec<SettingSpec> {
+        vec![
+            SettingSpec::string("fs_read", "Read access", "")
+                .with_description("Directories this WebAssembly plugin may read, separated by ';'"),
+            SettingSpec::string("fs_write", "Write access", "").with_description(
+                "Directories this WebAssembly plugin may write to, separated by ';'",
+            ),
+        ]
+    }
+
+    fn ui(&mut self, _ctx: &egui::Context, ed: &EditorContext) -> PluginAction {
+        let Some(hovered) = ed.hovered_char_idx else {
+            return PluginAction::None;
+        };
+        let hovered = byte_offset(ed.content, hovered);
+        let key = (ed.content.as_ptr() as usize, ed.content.len(), hovered);
+        let underline = match self.hover_cache {
+            Some((k, underline)) if k == key => underline,
+            _ => {
+                let state = editor_state(ed);
+                let decorations: Vec<Decoration> = self
+                    .call(|b, s| b.call_decorations(s, &state))
+                    .unwrap_or_default();
+                let underline = decorations
+                    .iter()
+                    .map(|d| (d.start as usize, d.end as usize))
+                    .find(|&(start, end)| start <= hovered && hovered < end);
+                self.hover_cache = Some((key, underline));
+                underline
+            }
+        };
+        match underline {
+            Some((start, end)) => PluginAction::UnderlineRegion(start, end),
+            None => PluginAction::None,
+        }
+    }
+
+    fn plugins_menu_ui(&mut self, ui: &mut egui::Ui, ed: &EditorContext) -> PluginAction {
+        let mut action = PluginAction::None;
+        let title = format!("🧩 {}", self.name);
+        if let Some(reason) = &self.faulted {
+            ui.add_enabled(false, egui::Button::new(title))
+                .on_disabled_hover_text(format!("Disabled after an error: {}", reason));
+            return action;
+        }
+        let items = self.call(|b, s| b.call_menu_items(s)).unwrap_or_default();
+        if items.is_empty() {
+            return action;
+        }
+        ui.menu_button(title, |ui| {
+            for item in items {
+                if ui.button(&item.label).clicked() {
+                    action = self.run(&item.command, ed);
+                    ui.close_menu();
+                }
+            }
+        });
+        action
+    }
+
+    fn context_menu_ui(&mut self, ui: &mut egui::Ui, ed: &EditorContext) -> PluginAction {
+        let mut action = PluginAction::None;
+        if self.faulted.is_some() {
+            return action;
+        }
+        for transform in self.transforms() {
+            if ui.button(&transform.title).clicked() {
+                action = self.apply_transform(&transform.id, ed);
+                ui.close_menu();
+            }
+        }
+        action
+    }
+
+    fn commands(&self) -> Vec<PluginCommand> {
+        self.commands.clone()
+    }
+
+    fn run_command(&mut self, id: &str, ed: &EditorContext) -> PluginAction {
+        self.run(id, ed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notos::plugin::host::Host;
+    use wasmtime::{Instance, Module, Trap};
+
+    /// Instantiate the core module `wat` in a plugin store, armed for a call.
+    fn instantiate(runtime: &WasmRuntime, wat: &str) -> (Store<HostState>, Instance) {
+        let module = Module::new(&runtime.engine, wat::parse_str(wat).unwrap()).unwrap();
+        let mut store = runtime.new_store("test".into());
+        arm(&mut store).unwrap();
+        let instance = Instance::new(&mut store, &module, &[]).unwrap();
+        arm(&mut store).unwrap();
+        (store, instance)
+    }
+
+    const LOOP: &str = r#"(module (func (export "run") (loop (br 0))))"#;
+
+    fn trap_of(error: wasmtime::Error) -> Option<Trap> {
+        error.downcast_ref::<Trap>().copied()
+    }
+
+    #[test]
+    fn endless_loop_runs_out_of_fuel() {
+        let runtime = WasmRuntime::new().unwrap();
+        let (mut store, instance) = instantiate(&runtime, LOOP);
+        // Leave only the fuel to stop it
+        store.set_epoch_deadline(u64::MAX / 2);
+        let run = instance
+            .get_typed_func::<(), ()>(&mut store, "run")
+            .unwrap();
+        let error = run.call(&mut store, ()).unwrap_err();
+        assert_eq!(trap_of(error), Some(Trap::OutOfFuel));
+    }
+
+    #[test]
+    fn endless_loop_hits_the_deadline() {
+        let runtime = WasmRuntime::new().unwrap();
+        let (mut store, instance) = instantiate(&runtime, LOOP);
+        // Leave only the deadline to stop it
+        store.set_fuel(u64::MAX).unwrap();
+        let run = instance
+            .get_typed_func::<(), ()>(&mut store, "run")
+            .unwrap();
+        let started = std::time::Instant::now();
+        let error = run.call(&mut store, ()).unwrap_err();
+        assert_eq!(trap_of(error), Some(Trap::Interrupt));
+        assert!(started.elapsed() < EPOCH_TICK * DEADLINE_TICKS as u32 * 10);
+    }
+
+    #[test]
+    fn memory_cannot_grow_past_the_cap() {
+        let runtime = WasmRuntime::new().unwrap();
+        let wat = r#"(module
+            (memory 1)
+            (func (export "grow") (param i32) (result i32) (memory.grow (local.get 0))))"#;
+        let (mut store, instance) = instantiate(&runtime, wat);
+        let grow = instance
+            .get_typed_func::<i32, i32>(&mut store, "grow")
+            .unwrap();
+        let page = 64 * 1024;
+        // Up to the cap is fine, one page more is refused
+        let pages = (MAX_MEMORY / page) as i32;
+        assert_eq!(grow.call(&mut store, pages - 1).unwrap(), 1);
+        assert_eq!(grow.call(&mut store, 1).unwrap(), -1);
+    }
+
+    #[test]
+    fn memory_over_the_cap_fails_to_instantiate() {
+        let runtime = WasmRuntime::new().unwrap();
+        let pages = MAX_MEMORY / (64 * 1024) + 1;
+        let wat = format!("(module (memory {}))", pages);
+        let module = Module::new(&runtime.engine, wat::parse_str(wat).unwrap()).unwrap();
+        let mut store = runtime.new_store("test".into());
+        arm(&mut store).unwrap();
+        assert!(Instance::new(&mut store, &module, &[]).is_err());
+    }
+
+    #[test]
+    fn file_access_needs_a_grant() {
+        let runtime = WasmRuntime::new().unwrap();
+        let root = std::env::temp_dir().join(format!("notos-wasm-test-{}", std::process::id()));
+        let (granted, other) = (root.join("granted"), root.join("other"));
+        std::fs::create_dir_all(&granted).unwrap();
+        std::fs::create_dir_all(&other).unwrap();
+        let secret = other.join("secret.txt");
+        std::fs::write(&secret, "secret").unwrap();
+        let path = |p: &Path| p.to_string_lossy().into_owned();
+
+        let mut store = runtime.new_store("test".into());
+        let state = store.data_mut();
+        // Nothing granted
+        assert!(state.read_file(path(&secret)).is_err());
+        assert!(state
+            .write_file(path(&other.join("new.txt")), "x".into())
+            .is_err());
+        assert!(!other.join("new.txt").exists());
+
+        state.settings.write(|s| {
+            s.set("fs_read", path(&granted));
+            s.set("fs_write", path(&granted));
+        });
+        let inside = granted.join("note.txt");
+        state.write_file(path(&inside), "note".into()).unwrap();
+        assert_eq!(state.read_file(path(&inside)).unwrap(), "note");
+        // Only inside the granted directory, even through `..`
+        let escape = granted.join("..").join("other").join("secret.txt");
+        assert!(state.read_file(path(&escape)).is_err());
+        assert!(state.write_file(path(&escape), "x".into()).is_err());
+        assert_eq!(std::fs::read_to_string(&secret).unwrap(), "secret");
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
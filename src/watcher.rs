@@ -0,0 +1,76 @@
+//! Notices changes made to open files by other programs.
+//!
+//! A background thread polls the watched files: a `stat` per file and
+//! interval, and a full read only when the size or modification time differs
+//! from what the tab last saw. Polling works the same on every platform and
+//! file system, network mounts included, which native watchers do not.
+
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::editor::{DiskChange, DiskState, EditorTab, Encoding};
+
+/// How often the files are checked.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A file to watch and what it is expected to look like.
+#[derive(Clone, PartialEq)]
+pub struct Watched {
+    pub path: PathBuf,
+    pub state: DiskState,
+    /// How to decode the file when it changed.
+    pub encoding: Encoding,
+}
+
+pub struct FileWatcher {
+    files: Arc<Mutex<Vec<Watched>>>,
+    changes: Receiver<(PathBuf, DiskChange)>,
+}
+
+impl FileWatcher {
+    /// Start polling, waking the UI when a change is found. The thread ends
+    /// once the watcher is dropped.
+    pub fn spawn(ctx: egui::Context) -> Self {
+        let files = Arc::new(Mutex::new(Vec::<Watched>::new()));
+        let (tx, changes) = mpsc::channel();
+        let watched = Arc::downgrade(&files);
+        std::thread::spawn(move || loop {
+            std::thread::sleep(POLL_INTERVAL);
+            let Some(files) = watched.upgrade() else {
+                return;
+            };
+            let files = files.lock().map(|f| f.clone()).unwrap_or_default();
+            let mut found = false;
+            for file in files {
+                let change = EditorTab::check_disk(&file.path, &file.state, file.encoding);
+                let Some(change) = change else {
+                    continue;
+                };
+                if tx.send((file.path, change)).is_err() {
+                    return;
+                }
+                found = true;
+            }
+            if found {
+                ctx.request_repaint();
+            }
+        });
+        Self { files, changes }
+    }
+
+    /// Watch exactly `files` from now on.
+    pub fn watch(&self, files: Vec<Watched>) {
+        if let Ok(mut watched) = self.files.lock() {
+            if *watched != files {
+                *watched = files;
+            }
+        }
+    }
+
+    /// The next change found, if any.
+    pub fn try_recv(&self) -> Option<(PathBuf, DiskChange)> {
+        self.changes.try_recv().ok()
+    }
+}